@@ -0,0 +1,178 @@
+use actix_web::{
+    HttpResponse, ResponseError,
+    http::{StatusCode, header::RETRY_AFTER},
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use utoipa::ToSchema;
+
+/// Machine readable error category, stable across message wording changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    Conflict,
+    PreconditionFailed,
+    RangeNotSatisfiable,
+    TooManyRequests,
+    Internal,
+    Unavailable,
+}
+
+impl ErrorCode {
+    fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::METHOD_NOT_ALLOWED => ErrorCode::MethodNotAllowed,
+            StatusCode::CONFLICT => ErrorCode::Conflict,
+            StatusCode::PRECONDITION_FAILED => ErrorCode::PreconditionFailed,
+            StatusCode::RANGE_NOT_SATISFIABLE => ErrorCode::RangeNotSatisfiable,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCode::TooManyRequests,
+            StatusCode::SERVICE_UNAVAILABLE => ErrorCode::Unavailable,
+            s if s.is_client_error() => ErrorCode::BadRequest,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+/// Body of every error returned under `/api`
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiError {
+    #[serde(skip)]
+    status: StatusCode,
+    pub code: ErrorCode,
+    pub message: String,
+    /// Extra context for the code, such as the invalid value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    /// Matches the request's `X-Request-Id` header and log span
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Seconds to send in `Retry-After`
+    #[serde(skip)]
+    retry_after: Option<u64>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl fmt::Display) -> Self {
+        Self {
+            status,
+            code: ErrorCode::from_status(status),
+            message: message.to_string(),
+            details: None,
+            request_id: None,
+            retry_after: None,
+        }
+    }
+
+    pub fn bad_request(message: impl fmt::Display) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn unauthorized(message: impl fmt::Display) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, message)
+    }
+
+    pub fn forbidden(message: impl fmt::Display) -> Self {
+        Self::new(StatusCode::FORBIDDEN, message)
+    }
+
+    pub fn not_found(message: impl fmt::Display) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn conflict(message: impl fmt::Display) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    /// Rate limited; clients should wait `retry_after` seconds
+    pub fn too_many_requests(message: impl fmt::Display, retry_after: u64) -> Self {
+        let mut error = Self::new(StatusCode::TOO_MANY_REQUESTS, message);
+        error.retry_after = Some(retry_after);
+        error
+    }
+
+    /// The message is returned to clients, so it should not carry internals
+    pub fn internal(message: impl fmt::Display) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    #[must_use]
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    #[must_use]
+    pub fn with_request_id(mut self, request_id: Option<String>) -> Self {
+        self.request_id = request_id;
+        self
+    }
+
+    /// Wrap any actix error, keeping an `ApiError` as is
+    pub fn from_error(error: &actix_web::Error) -> Self {
+        error.as_error::<ApiError>().cloned().unwrap_or_else(|| {
+            let response = error.as_response_error();
+            Self::new(response.status_code(), response)
+        })
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut res = HttpResponse::build(self.status);
+        if let Some(secs) = self.retry_after {
+            res.insert_header((RETRY_AFTER, secs));
+        }
+        res.json(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{body::to_bytes, error::ErrorNotFound};
+
+    #[actix_web::test]
+    async fn test_serializes_error_model() {
+        let error = ApiError::bad_request("Invalid grid color: zz")
+            .with_details(serde_json::json!({ "grid_color": "zz" }))
+            .with_request_id(Some("abc".to_string()));
+        let res = error.error_response();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "bad_request");
+        assert_eq!(json["message"], "Invalid grid color: zz");
+        assert_eq!(json["details"]["grid_color"], "zz");
+        assert_eq!(json["request_id"], "abc");
+    }
+
+    #[test]
+    fn test_from_error_keeps_status() {
+        let error = ApiError::from_error(&ErrorNotFound("Tile out of range"));
+        assert_eq!(error.code, ErrorCode::NotFound);
+        assert_eq!(error.message, "Tile out of range");
+
+        let error = ApiError::from_error(&ApiError::forbidden("nope").into());
+        assert_eq!(error.code, ErrorCode::Forbidden);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::api::{API_V1, ApiError};
+use actix_web::{
+    Error, HttpMessage, ResponseError,
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{CONTENT_LENGTH, CONTENT_TYPE, HeaderName, HeaderValue, LINK},
+    middleware::Next,
+};
+use tracing_actix_web::RequestId;
+
+const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+
+fn is_json(res: &ServiceResponse<impl MessageBody>) -> bool {
+    res.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+/// Render every error below the API scopes as an [`ApiError`] tagged with the
+/// request id, including extractor failures and unmatched routes, and echo
+/// the id in `X-Request-Id`. Headers of the original response, such as
+/// `Content-Range` on a 416 or `Allow` on a 405, are kept.
+pub async fn error_model(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let request_id = req.extensions().get::<RequestId>().map(ToString::to_string);
+
+    let res = match next.call(req).await {
+        Ok(res) => res,
+        Err(e) => {
+            return Err(ApiError::from_error(&e).with_request_id(request_id).into());
+        }
+    };
+
+    let status = res.status();
+    let api_error = match res.response().error() {
+        Some(e) => Some(ApiError::from_error(e)),
+        None if (status.is_client_error() || status.is_server_error()) && !is_json(&res) => Some(
+            ApiError::new(status, status.canonical_reason().unwrap_or("Error")),
+        ),
+        None => None,
+    };
+
+    let mut res = match api_error {
+        Some(api_error) => {
+            let api_error = api_error.with_request_id(request_id.clone());
+            let (req, original) = res.into_parts();
+            let mut rendered = api_error.error_response();
+            let own = rendered.headers().clone();
+            for (name, value) in original.headers() {
+                if name != CONTENT_TYPE && name != CONTENT_LENGTH && !own.contains_key(name) {
+                    rendered.headers_mut().append(name.clone(), value.clone());
+                }
+            }
+            ServiceResponse::new(req, rendered)
+        }
+        None => res.map_into_boxed_body(),
+    };
+    if let Some(id) = request_id.and_then(|id| HeaderValue::from_str(&id).ok()) {
+        res.headers_mut().insert(X_REQUEST_ID, id);
+    }
+    Ok(res)
+}
+
+/// Mark responses from the unversioned `/api` paths as deprecated and point
+/// at their `/api/v1` successor
+pub async fn deprecated_alias(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let successor = req
+        .path()
+        .strip_prefix("/api")
+        .map(|rest| format!("<{API_V1}{rest}>; rel=\"successor-version\""));
+
+    let mut res = next.call(req).await?;
+    res.headers_mut()
+        .insert(DEPRECATION, HeaderValue::from_static("true"));
+    if let Some(link) = successor.and_then(|l| HeaderValue::from_str(&l).ok()) {
+        res.headers_mut().insert(LINK, link);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        App, HttpResponse,
+        http::StatusCode,
+        middleware::from_fn,
+        test::{self, TestRequest},
+        web,
+    };
+    use serde::Deserialize;
+    use tracing_actix_web::TracingLogger;
+
+    #[derive(Deserialize)]
+    struct Page {
+        #[allow(dead_code)]
+        limit: usize,
+    }
+
+    fn routes(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/maps/all",
+            web::get().to(|_: web::Query<Page>| async { HttpResponse::Ok().json([0; 0]) }),
+        )
+        .route(
+            "/maps/{id}",
+            web::get().to(|| async { Err::<&str, _>(ApiError::not_found("Map not found")) }),
+        )
+        .route(
+            "/maps/content/{id}",
+            web::get().to(|| async { HttpResponse::NotFound().body("Map content not found") }),
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_errors_use_error_model() {
+        let app = test::init_service(
+            App::new().wrap(TracingLogger::default()).service(
+                web::scope(API_V1)
+                    .wrap(from_fn(error_model))
+                    .configure(routes),
+            ),
+        )
+        .await;
+
+        for (uri, status, code) in [
+            ("/api/v1/maps/abc", StatusCode::NOT_FOUND, "not_found"),
+            (
+                "/api/v1/maps/all?limit=x",
+                StatusCode::BAD_REQUEST,
+                "bad_request",
+            ),
+            (
+                "/api/v1/maps/content/abc",
+                StatusCode::NOT_FOUND,
+                "not_found",
+            ),
+        ] {
+            let res = test::call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(res.status(), status, "{uri}");
+            let request_id = res.headers().get(X_REQUEST_ID).cloned().unwrap();
+            let body: serde_json::Value = test::read_body_json(res).await;
+            assert_eq!(body["code"], code, "{uri}");
+            assert_eq!(body["request_id"], request_id.to_str().unwrap(), "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_unversioned_alias_is_deprecated() {
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/api")
+                    .wrap(from_fn(deprecated_alias))
+                    .configure(routes),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/api/maps/all?limit=1").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        assert_eq!(res.headers().get(DEPRECATION).unwrap(), "true");
+        assert_eq!(
+            res.headers().get(LINK).unwrap(),
+            "</api/v1/maps/all>; rel=\"successor-version\""
+        );
+    }
+}
@@ -0,0 +1,129 @@
+pub mod error;
+mod middleware;
+
+pub use error::ApiError;
+pub use middleware::{deprecated_alias, error_model};
+
+use crate::hooks::admin_auth::AdminAuth;
+use crate::hooks::rate_limit::{Budget, RateLimit};
+use crate::utils::admin_token::Scope;
+use crate::utils::{admin_session, admin_tokens, audit_log};
+use crate::{docs, maps, users, utils};
+use actix_web::{middleware::from_fn, web};
+
+/// Current API namespace
+pub const API_V1: &str = "/api/v1";
+
+/// Routes served below both [`API_V1`] and the deprecated unversioned `/api`
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/maps")
+            .service(
+                web::resource("/all")
+                    .wrap(RateLimit::new(Budget::Search))
+                    .route(web::get().to(maps::maps_all)),
+            )
+            .route("/{id}", web::get().to(maps::map_detail))
+            .route("/{id}/lint", web::get().to(maps::map_lint))
+            .service(
+                web::resource("/rebuild")
+                    .wrap(AdminAuth::require(Scope::Rebuild))
+                    .wrap(RateLimit::new(Budget::Admin))
+                    .route(web::post().to(maps::maps_rebuild)),
+            )
+            .route("/rebuild/status", web::get().to(maps::rebuild_status))
+            .service(
+                web::resource("/rebuild/clear")
+                    .wrap(AdminAuth::require(Scope::LockClear))
+                    .wrap(RateLimit::new(Budget::Admin))
+                    .route(web::delete().to(maps::clear_rebuild_lock)),
+            )
+            .service(
+                web::resource("/download/{id}")
+                    .wrap(from_fn(maps::download::download_preconditions))
+                    .wrap(RateLimit::new(Budget::Downloads))
+                    .route(web::get().to(maps::download_map)),
+            )
+            .service(
+                web::resource("/tiled/{id}")
+                    .wrap(RateLimit::new(Budget::Images))
+                    .route(web::get().to(maps::tiled_map)),
+            )
+            .service(
+                web::resource("/image/{id}")
+                    .wrap(RateLimit::new(Budget::Images))
+                    .route(web::get().to(maps::map_image)),
+            )
+            .service(
+                web::resource("/print/{id}.pdf")
+                    .wrap(RateLimit::new(Budget::Images))
+                    .route(web::get().to(maps::print_map)),
+            )
+            .service(
+                web::resource("/tiles/{id}/pyramid.json")
+                    .wrap(RateLimit::new(Budget::Images))
+                    .route(web::get().to(maps::tile_pyramid)),
+            )
+            // Tiles are static once generated; `map_tile` meters generation itself
+            .route(maps::tiles::TILE_ROUTE, web::get().to(maps::map_tile))
+            .route("/content/{id}", web::get().to(maps::map_content)),
+    )
+    .service(
+        web::scope("/docs")
+            .route("/readme", web::get().to(docs::docs_readme))
+            .route("/license", web::get().to(docs::docs_license)),
+    )
+    .service(
+        web::scope("/admin")
+            .route(
+                "/token/info",
+                web::get().to(utils::admin_info::get_admin_token_info),
+            )
+            .service(
+                web::resource("/login")
+                    .wrap(RateLimit::new(Budget::Admin))
+                    .route(web::post().to(admin_session::login)),
+            )
+            .route("/logout", web::post().to(admin_session::logout))
+            .route("/session", web::get().to(admin_session::get_admin_session))
+            .service(
+                web::scope("/tokens")
+                    .wrap(AdminAuth::require(Scope::Tokens))
+                    .wrap(RateLimit::new(Budget::Admin))
+                    .route("", web::get().to(admin_tokens::list_admin_tokens))
+                    .route("", web::post().to(admin_tokens::create_admin_token))
+                    .route(
+                        "/{name}/rotate",
+                        web::post().to(admin_tokens::rotate_admin_token),
+                    )
+                    .route(
+                        "/{name}",
+                        web::delete().to(admin_tokens::revoke_admin_token),
+                    ),
+            )
+            .service(
+                web::resource("/audit")
+                    .wrap(AdminAuth::require(Scope::Audit))
+                    .wrap(RateLimit::new(Budget::Admin))
+                    .route(web::get().to(audit_log::get_audit_log)),
+            ),
+    )
+    .service(
+        web::scope("/auth")
+            .service(
+                web::resource("/login")
+                    .wrap(RateLimit::new(Budget::Admin))
+                    .route(web::get().to(users::login)),
+            )
+            .service(
+                web::resource("/callback")
+                    .wrap(RateLimit::new(Budget::Admin))
+                    .route(web::get().to(users::callback)),
+            )
+            .route("/logout", web::post().to(users::logout)),
+    )
+    .route("/me", web::get().to(users::me))
+    .default_service(web::to(|| async {
+        Err::<&str, _>(ApiError::not_found("No such API endpoint"))
+    }));
+}
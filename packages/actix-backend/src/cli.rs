@@ -0,0 +1,166 @@
+use crate::hooks::identity::rotate_session_key;
+use crate::users::store::{Role, User, list_users, set_role};
+use crate::utils::admin_token::{
+    AdminToken, Scope, create_token, list_tokens, revoke_token, rotate_token,
+};
+use clap::{Parser, Subcommand};
+use std::env;
+use std::path::PathBuf;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// D&D VTT Maps server; serves the catalog when run without a command
+#[derive(Parser)]
+#[command(name = "actix-backend", about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Write the catalog as a static site to a directory and exit
+    Export { out: PathBuf },
+    /// Manage admin tokens; a running server picks up changes immediately
+    #[command(subcommand)]
+    Tokens(TokenCommand),
+    /// Manage the key that encrypts session cookies
+    #[command(subcommand)]
+    SessionKey(SessionKeyCommand),
+    /// Manage users who signed in through the identity provider
+    #[command(subcommand)]
+    Users(UserCommand),
+}
+
+#[derive(Subcommand)]
+pub enum TokenCommand {
+    /// List tokens without their secrets
+    List,
+    /// Create a token and print its secret once
+    Create {
+        name: String,
+        /// rebuild, lock-clear, content-edit, tokens or audit; repeat for several
+        #[arg(short, long = "scope", required = true)]
+        scopes: Vec<Scope>,
+        /// Omit for a token that never expires
+        #[arg(long)]
+        expires_in_days: Option<u64>,
+    },
+    /// Replace a token's secret and print the new one
+    Rotate { name: String },
+    /// Revoke a token
+    Revoke { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum SessionKeyCommand {
+    /// Generate a new key, keeping the old one so existing sessions survive;
+    /// running servers pick it up on restart
+    Rotate,
+}
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    /// List users and their roles
+    List,
+    /// Override a user's role; omit the role to use the provider's again
+    Role {
+        id: String,
+        /// user, curator or admin
+        role: Option<Role>,
+    },
+}
+
+/// Who performed a change, as recorded on the token
+fn actor() -> String {
+    format!(
+        "cli:{}",
+        env::var("USER").unwrap_or_else(|_| "unknown".to_string())
+    )
+}
+
+fn describe(token: &AdminToken) -> String {
+    let scopes = token
+        .scopes
+        .iter()
+        .map(|scope| scope.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    let by = token.created_by.as_deref().unwrap_or("unknown");
+    format!(
+        "{:<24} {:<8} {:<40} created by {}",
+        token.name,
+        token.status(),
+        scopes,
+        by
+    )
+}
+
+pub fn run_tokens(command: TokenCommand) -> anyhow::Result<()> {
+    match command {
+        TokenCommand::List => {
+            for token in list_tokens()? {
+                println!("{}", describe(&token));
+            }
+        }
+        TokenCommand::Create {
+            name,
+            scopes,
+            expires_in_days,
+        } => {
+            let expires_in = expires_in_days.map(|days| days.saturating_mul(SECONDS_PER_DAY));
+            let (token, secret) = create_token(&name, scopes, expires_in, &actor(), &Scope::ALL)?;
+            eprintln!("Created {}", describe(&token));
+            println!("{secret}");
+        }
+        TokenCommand::Rotate { name } => {
+            let (token, secret) = rotate_token(&name, &actor(), &Scope::ALL)?;
+            eprintln!("Rotated {}", describe(&token));
+            println!("{secret}");
+        }
+        TokenCommand::Revoke { name } => {
+            let token = revoke_token(&name, &actor(), &Scope::ALL)?;
+            eprintln!("Revoked {}", describe(&token));
+        }
+    }
+    Ok(())
+}
+
+pub fn run_session_key(command: SessionKeyCommand) -> anyhow::Result<()> {
+    match command {
+        SessionKeyCommand::Rotate => {
+            let path = rotate_session_key()?;
+            eprintln!("Rotated the session key in {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn describe_user(user: &User) -> String {
+    let role = match user.role_override {
+        Some(role) => format!("{role} (set locally)"),
+        None => user.claimed_role.to_string(),
+    };
+    format!(
+        "{:<18} {:<24} {:<32} {}",
+        user.id,
+        role,
+        user.email.as_deref().unwrap_or("-"),
+        user.name.as_deref().unwrap_or("-")
+    )
+}
+
+pub fn run_users(command: UserCommand) -> anyhow::Result<()> {
+    match command {
+        UserCommand::List => {
+            for user in list_users()? {
+                println!("{}", describe_user(&user));
+            }
+        }
+        UserCommand::Role { id, role } => {
+            let user = set_role(&id, role)?;
+            eprintln!("Updated {}", describe_user(&user));
+        }
+    }
+    Ok(())
+}
@@ -1,8 +1,7 @@
 mod license;
+pub mod openapi;
 mod readme;
 mod serve_markdown;
 
 pub use license::docs_license;
 pub use readme::docs_readme;
-
-pub use serve_markdown::serve_markdown_file;
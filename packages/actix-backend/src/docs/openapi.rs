@@ -0,0 +1,137 @@
+use crate::{docs, health, maps, metrics, users, utils};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Where the generated document is served
+pub const OPENAPI_ROUTE: &str = "/api/openapi.json";
+
+/// Interactive docs; bundled with the binary so they satisfy our CSP
+const SWAGGER_UI_ROUTE: &str = "/api/docs/ui/{_:.*}";
+
+/// Registers the bearer scheme used by the admin-only endpoints
+struct AdminToken;
+
+impl Modify for AdminToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some(
+                        "Named admin token with the endpoint's scope; see `/api/v1/admin/token/info`. \
+                        Browsers may instead log in at `/api/v1/admin/login` and send `X-CSRF-Token`",
+                    ))
+                    .build(),
+            ),
+        );
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "D&D VTT Maps API",
+        description = "Catalog, downloads and renderings of the battle maps in the repository. \
+            Errors share the `ApiError` body; the unversioned `/api` paths are deprecated aliases."
+    ),
+    paths(
+        maps::all::maps_all,
+        maps::detail::map_detail,
+        maps::lint::map_lint,
+        maps::content::map_content,
+        maps::download::download_map,
+        maps::tiled::tiled_map,
+        maps::image::map_image,
+        maps::print::print_map,
+        maps::tiles::tile_pyramid,
+        maps::tiles::map_tile,
+        maps::rebuild::maps_rebuild,
+        maps::rebuild::rebuild_status,
+        maps::rebuild::clear_rebuild_lock,
+        docs::readme::docs_readme,
+        docs::license::docs_license,
+        utils::admin_info::get_admin_token_info,
+        utils::admin_session::login,
+        utils::admin_session::logout,
+        utils::admin_session::get_admin_session,
+        utils::admin_tokens::list_admin_tokens,
+        utils::admin_tokens::create_admin_token,
+        utils::admin_tokens::rotate_admin_token,
+        utils::admin_tokens::revoke_admin_token,
+        utils::audit_log::get_audit_log,
+        users::handlers::login,
+        users::handlers::callback,
+        users::handlers::logout,
+        users::handlers::me,
+        health::probes::liveness,
+        health::probes::readiness,
+        metrics::metrics,
+    ),
+    modifiers(&AdminToken),
+    tags(
+        (name = "maps", description = "The map catalog and map files"),
+        (name = "images", description = "Map images, tiles and printable PDFs"),
+        (name = "rebuild", description = "Reindexing the repository's maps"),
+        (name = "docs", description = "Repository documents"),
+        (name = "admin", description = "Admin access, token management and the audit log"),
+        (name = "users", description = "Signing in with the identity provider"),
+        (name = "health", description = "Probes and metrics"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Swagger UI at `/api/docs/ui/`, also serving the document at [`OPENAPI_ROUTE`]
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new(SWAGGER_UI_ROUTE).url(OPENAPI_ROUTE, ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, test};
+
+    #[actix_web::test]
+    async fn test_document_covers_api() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        for path in [
+            "/api/v1/maps/all",
+            "/api/v1/maps/{id}",
+            "/api/v1/maps/tiles/{id}/{z}/{x}/{y}.webp",
+            "/api/v1/maps/rebuild/status",
+        ] {
+            assert!(doc["paths"][path].is_object(), "missing {path}");
+        }
+        for schema in [
+            "MapDocument",
+            "MapResolution",
+            "RebuildStatus",
+            "GcSummary",
+            "ApiError",
+        ] {
+            assert!(
+                doc["components"]["schemas"][schema].is_object(),
+                "missing {schema}"
+            );
+        }
+        assert_eq!(
+            doc["paths"]["/api/v1/maps/rebuild"]["post"]["security"][0]["admin_token"],
+            serde_json::json!(["rebuild"])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_serves_document_and_ui() {
+        let app = test::init_service(App::new().service(swagger_ui())).await;
+
+        let req = test::TestRequest::get().uri(OPENAPI_ROUTE).to_request();
+        let doc: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(doc["info"]["title"], "D&D VTT Maps API");
+
+        let req = test::TestRequest::get().uri("/api/docs/ui/").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+    }
+}
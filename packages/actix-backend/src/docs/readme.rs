@@ -1,9 +1,17 @@
+use crate::api::ApiError;
 use crate::docs::serve_markdown::serve_markdown_file;
-use actix_web::{HttpResponse, error::ErrorInternalServerError};
+use actix_web::HttpResponse;
 use shared::utils::root_dir::root_dir;
 
+/// The project README, rendered from markdown
+#[utoipa::path(
+    get,
+    path = "/api/v1/docs/readme",
+    tag = "docs",
+    responses((status = 200, description = "Rendered README", body = String, content_type = "text/html"))
+)]
 pub async fn docs_readme() -> Result<HttpResponse, actix_web::Error> {
-    let root = root_dir().map_err(ErrorInternalServerError)?;
+    let root = root_dir().map_err(ApiError::internal)?;
     let license = root.join("README.md");
     serve_markdown_file(license).await
 }
@@ -1,6 +1,7 @@
 // src/utils/markdown_server.rs
+use crate::api::ApiError;
 use crate::utils::markdown::markdown_to_html;
-use actix_web::{HttpResponse, error::ErrorInternalServerError};
+use actix_web::HttpResponse;
 use std::path::Path;
 use tokio::fs;
 use tracing::{debug, error};
@@ -12,7 +13,7 @@ pub async fn serve_markdown_file(path: impl AsRef<Path>) -> Result<HttpResponse,
 
     let md = fs::read_to_string(path).await.map_err(|e| {
         error!("Failed to read {}: {}", path.display(), e);
-        ErrorInternalServerError("Failed to read markdown file")
+        ApiError::internal("Failed to read markdown file")
     })?;
 
     let html = markdown_to_html(&md);
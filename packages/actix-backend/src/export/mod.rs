@@ -0,0 +1,3 @@
+mod site;
+
+pub use site::export_site;
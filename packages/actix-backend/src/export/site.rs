@@ -0,0 +1,242 @@
+use crate::maps::rebuild::{find_dd2vtt_paths, map_ref_to_doc, process_one};
+use crate::maps::tiles::{MANIFEST_FILE, PyramidManifest, build_pyramid};
+use crate::utils::folders::dist_dir;
+use crate::utils::markdown::markdown_to_html;
+use crate::wrappers::seo::{SeoData, default_seo, map_seo, render_seo_metadata};
+use anyhow::{Context, anyhow, bail};
+use serde::Serialize;
+use shared::render::variant::{OutputFormat, VariantSpec, render_map_variant};
+use shared::static_site::{
+    CATALOG_PAGE_SIZE, catalog_page, doc_page, map_content, map_document, map_download, map_image,
+    map_page, tiles,
+};
+use shared::types::dd2vtt::DD2VTTFile;
+use shared::types::map_document::MapDocument as MapDoc;
+use shared::utils::root_dir::{maps_dir, root_dir};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{error, info, instrument, warn};
+
+/// Repository documents exported alongside the catalog
+const DOCS: [(&str, &str); 2] = [("readme", "README.md"), ("license", "LICENSE")];
+
+/// Frontend routes besides `/` and the map pages, pre-rendered so they are
+/// not served through the 404 fallback
+const ROUTES: [&str; 2] = ["catalog", "LICENSE"];
+
+/// Destination of a URL path inside the export
+fn target(out: &Path, url: &str) -> PathBuf {
+    out.join(url.trim_start_matches('/'))
+}
+
+fn write(out: &Path, url: &str, bytes: impl AsRef<[u8]>) -> anyhow::Result<()> {
+    let path = target(out, url);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn write_json(out: &Path, url: &str, value: &impl Serialize) -> anyhow::Result<()> {
+    write(out, url, serde_json::to_vec(value)?)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else {
+            fs::copy(entry.path(), dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Public origin the site is hosted at (`SITE_URL`), used for canonical links
+fn site_url() -> String {
+    env::var("SITE_URL").map_or_else(
+        |_| {
+            warn!("⚠️  SITE_URL is not set; canonical links will be relative");
+            String::new()
+        },
+        |url| url.trim_end_matches('/').to_string(),
+    )
+}
+
+/// The SPA shell with a page's SEO tags in its `<head>`
+fn seo_page(shell: &str, canonical: &str, seo: SeoData) -> anyhow::Result<String> {
+    render_seo_metadata(shell.to_string(), canonical, seo).map_err(|e| anyhow!(e.to_string()))
+}
+
+/// The map's image in the format the static frontend links to
+fn export_image(map: &DD2VTTFile) -> anyhow::Result<Vec<u8>> {
+    let embedded = map.embedded_image()?;
+    if embedded.mime_type() == OutputFormat::Webp.mime_type() {
+        return Ok(embedded.bytes);
+    }
+    Ok(render_map_variant(
+        map,
+        &VariantSpec::new(OutputFormat::Webp),
+    )?)
+}
+
+/// Write everything a map's detail page fetches
+#[instrument(level = "info", skip_all, fields(map = %doc.name))]
+fn export_map(
+    out: &Path,
+    source: &Path,
+    doc: &MapDoc,
+    shell: &str,
+    site: &str,
+) -> anyhow::Result<()> {
+    let root = root_dir()?;
+    write_json(out, &map_document(&doc.id), doc)?;
+
+    if let Some(content) = &doc.content {
+        let md = fs::read_to_string(root.join(content.trim_start_matches('/')))?;
+        write(out, &map_content(&doc.id), markdown_to_html(&md))?;
+    }
+
+    let map = DD2VTTFile::load(source)?;
+    write(out, &map_image(&doc.id), export_image(&map)?)?;
+    let download = target(out, &map_download(&doc.id));
+    fs::create_dir_all(download.parent().unwrap_or(out))?;
+    fs::copy(source, download)?;
+
+    let tiles_url = tiles(&doc.id);
+    let tiles = target(out, &tiles_url);
+    fs::create_dir_all(tiles.parent().unwrap_or(out))?;
+    let pyramid = build_pyramid(source, &tiles)?;
+    fs::write(
+        tiles.join(MANIFEST_FILE),
+        serde_json::to_vec(&PyramidManifest::new(&tiles_url, pyramid))?,
+    )?;
+
+    let page = map_page(&doc.id);
+    let mut seo = map_seo(doc);
+    if !doc.thumbnails.contains_key("social") {
+        // The resizing endpoint the live site falls back to does not exist here
+        seo.image_url.clone_from(&doc.thumbnail);
+    }
+    let canonical = format!("{site}{}", page.trim_end_matches(".html"));
+    write(out, &page, seo_page(shell, &canonical, seo)?)?;
+    Ok(())
+}
+
+/// Export the catalog as a static site that needs no backend or Meilisearch.
+///
+/// Starts from the frontend in `DIST_DIR`, built with the `static-site`
+/// feature, and writes every file at the URL that build requests. Returns
+/// the number of maps exported; maps that fail are skipped, and reported as
+/// an error once the rest of the site is written.
+#[instrument(level = "info", skip_all, fields(out = %out.display()))]
+pub fn export_site(out: &Path) -> anyhow::Result<usize> {
+    let start = Instant::now();
+    let dist = dist_dir();
+    let shell_path = dist.join("index.html");
+    if !shell_path.exists() {
+        bail!(
+            "No frontend build at {}; run `trunk build --features static-site` first",
+            dist.display()
+        );
+    }
+
+    info!("📦 Exporting static site to {}", out.display());
+    copy_dir(&dist, out)?;
+    let shell = fs::read_to_string(&shell_path)?;
+    let site = site_url();
+
+    let base = maps_dir()?.to_string_lossy().to_string();
+    let thumb_dir = target(out, "/assets/thumbnails");
+    let mut docs = Vec::new();
+    let mut failed = Vec::new();
+    for path in find_dd2vtt_paths().map_err(|e| anyhow!(e))? {
+        // Like the rebuild, one broken map should not take the site down
+        let exported =
+            process_one(path.clone(), thumb_dir.clone()).and_then(|(map_ref, report)| {
+                if report.errors() > 0 {
+                    warn!("⚠️  {} has {} lint errors", path.display(), report.errors());
+                }
+                let doc = map_ref_to_doc(map_ref, &base);
+                export_map(out, &path, &doc, &shell, &site)?;
+                Ok(doc)
+            });
+        match exported {
+            Ok(doc) => {
+                info!("✅ Exported {}", doc.name);
+                docs.push(doc);
+            }
+            Err(e) => {
+                error!("❌ Failed to export {}: {:?}", path.display(), e);
+                failed.push(path);
+            }
+        }
+    }
+
+    docs.sort_by(|a, b| a.name.cmp(&b.name));
+    let pages = docs.chunks(CATALOG_PAGE_SIZE).collect::<Vec<_>>();
+    if pages.is_empty() {
+        write_json(out, &catalog_page(0), &docs)?;
+    }
+    for (page, chunk) in pages.iter().enumerate() {
+        write_json(out, &catalog_page(page), chunk)?;
+    }
+
+    let root = root_dir()?;
+    for (name, file) in DOCS {
+        let md = fs::read_to_string(root.join(file))?;
+        write(out, &doc_page(name), markdown_to_html(&md))?;
+    }
+
+    // Hosts such as GitHub Pages serve 404.html for unknown paths, which
+    // lets the SPA route everything without a pre-rendered page
+    let index = seo_page(&shell, &format!("{site}/"), default_seo())?;
+    write(out, "/index.html", &index)?;
+    write(out, "/404.html", &index)?;
+    for route in ROUTES {
+        let page = seo_page(&shell, &format!("{site}/{route}"), default_seo())?;
+        write(out, &format!("/{route}.html"), page)?;
+    }
+
+    info!(
+        "🎉 Exported {} maps in {:?} to {}",
+        docs.len(),
+        start.elapsed(),
+        out.display()
+    );
+    if !failed.is_empty() {
+        let paths = failed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>();
+        bail!(
+            "{} maps failed to export: {}",
+            failed.len(),
+            paths.join(", ")
+        );
+    }
+    Ok(docs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_nests_url_paths() {
+        let out = Path::new("/tmp/site");
+        assert_eq!(
+            target(out, &map_document("abc")),
+            Path::new("/tmp/site/api/maps/abc.json")
+        );
+        assert_eq!(
+            target(out, &map_page("abc")),
+            Path::new("/tmp/site/maps/abc.html")
+        );
+    }
+}
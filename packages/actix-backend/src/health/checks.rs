@@ -0,0 +1,102 @@
+use crate::clients::meilisearch::get_meilisearch_client;
+use crate::maps::has_completed_rebuild;
+use crate::utils::folders::dist_dir;
+use serde::Serialize;
+use shared::utils::root_dir::{maps_dir, root_dir};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time::timeout;
+use utoipa::ToSchema;
+
+const MEILISEARCH_TIMEOUT: Duration = Duration::from_secs(2);
+const MAPS_INDEX: &str = "maps";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+/// Outcome of a single readiness check
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl CheckResult {
+    pub fn up() -> Self {
+        Self {
+            status: CheckStatus::Up,
+            detail: None,
+        }
+    }
+
+    pub fn down(detail: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Down,
+            detail: Some(detail.into()),
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.status == CheckStatus::Up
+    }
+}
+
+/// Meilisearch must be healthy and the `maps` index must exist
+pub async fn check_meilisearch() -> CheckResult {
+    let client = match get_meilisearch_client() {
+        Ok(client) => client,
+        Err(e) => return CheckResult::down(format!("Client unavailable: {e}")),
+    };
+
+    match timeout(MEILISEARCH_TIMEOUT, client.health()).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => return CheckResult::down(format!("Health check failed: {e}")),
+        Err(_) => return CheckResult::down("Health check timed out"),
+    }
+
+    match timeout(MEILISEARCH_TIMEOUT, client.get_index(MAPS_INDEX)).await {
+        Ok(Ok(_)) => CheckResult::up(),
+        Ok(Err(e)) => CheckResult::down(format!("Index '{MAPS_INDEX}' unavailable: {e}")),
+        Err(_) => CheckResult::down("Index lookup timed out"),
+    }
+}
+
+/// The directory must resolve and be listable
+pub fn check_directory(resolve: fn() -> io::Result<PathBuf>) -> CheckResult {
+    match resolve().and_then(|path| path.read_dir().map(|_| path)) {
+        Ok(_) => CheckResult::up(),
+        Err(e) => CheckResult::down(e.to_string()),
+    }
+}
+
+pub fn check_root_dir() -> CheckResult {
+    check_directory(root_dir)
+}
+
+pub fn check_maps_dir() -> CheckResult {
+    check_directory(maps_dir)
+}
+
+/// The SPA shell must be present so the fallback route can serve it
+pub fn check_frontend() -> CheckResult {
+    let index = dist_dir().join("index.html");
+    if index.is_file() {
+        CheckResult::up()
+    } else {
+        CheckResult::down(format!("Missing {}", index.display()))
+    }
+}
+
+pub fn check_rebuild() -> CheckResult {
+    if has_completed_rebuild() {
+        CheckResult::up()
+    } else {
+        CheckResult::down("No map rebuild has completed yet")
+    }
+}
@@ -1,3 +1,4 @@
-mod probes;
+mod checks;
+pub(crate) mod probes;
 
 pub use probes::{liveness, readiness};
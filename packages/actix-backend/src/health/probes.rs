@@ -1,15 +1,53 @@
+use crate::health::checks::{
+    CheckResult, check_frontend, check_maps_dir, check_meilisearch, check_rebuild, check_root_dir,
+};
 use actix_web::{HttpResponse, Responder};
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
 
-#[derive(Serialize)]
-struct HealthResponse {
+#[derive(Serialize, ToSchema)]
+pub(crate) struct HealthResponse {
     status: String,
     timestamp: u64,
     version: String,
 }
 
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ReadinessResponse {
+    status: String,
+    timestamp: u64,
+    version: String,
+    #[schema(value_type = BTreeMap<String, CheckResult>)]
+    checks: BTreeMap<&'static str, CheckResult>,
+}
+
+impl ReadinessResponse {
+    fn from_checks(checks: BTreeMap<&'static str, CheckResult>, timestamp: u64) -> Self {
+        let ready = checks.values().all(CheckResult::is_up);
+        let version = option_env!("CARGO_PKG_VERSION").unwrap_or("unknown");
+
+        Self {
+            status: if ready { "READY" } else { "NOT_READY" }.to_string(),
+            timestamp,
+            version: version.to_string(),
+            checks,
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.checks.values().all(CheckResult::is_up)
+    }
+}
+
 /// Liveness probe - indicates if the application is running
+#[utoipa::path(
+    get,
+    path = "/health/liveness",
+    tag = "health",
+    responses((status = 200, description = "The process is up", body = HealthResponse))
+)]
 pub async fn liveness() -> impl Responder {
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -26,19 +64,61 @@ pub async fn liveness() -> impl Responder {
 }
 
 /// Readiness probe - indicates if the application is ready to accept traffic
+///
+/// Returns 503 with a per-check breakdown when any dependency is unavailable.
+#[utoipa::path(
+    get,
+    path = "/health/readiness",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready to serve traffic", body = ReadinessResponse),
+        (status = 503, description = "A dependency is unavailable", body = ReadinessResponse),
+    )
+)]
 pub async fn readiness() -> impl Responder {
-    // In a more complex application, you might check dependencies here
-    // like database connections, external services, etc.
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs();
 
-    let version = option_env!("CARGO_PKG_VERSION").unwrap_or("unknown");
+    let checks = BTreeMap::from([
+        ("meilisearch", check_meilisearch().await),
+        ("root_dir", check_root_dir()),
+        ("maps_dir", check_maps_dir()),
+        ("frontend", check_frontend()),
+        ("rebuild", check_rebuild()),
+    ]);
 
-    HttpResponse::Ok().json(HealthResponse {
-        status: "READY".to_string(),
-        timestamp,
-        version: version.to_string(),
-    })
+    let response = ReadinessResponse::from_checks(checks, timestamp);
+    if response.is_ready() {
+        HttpResponse::Ok().json(response)
+    } else {
+        HttpResponse::ServiceUnavailable().json(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_requires_all_checks_up() {
+        let checks = BTreeMap::from([("a", CheckResult::up()), ("b", CheckResult::up())]);
+        let response = ReadinessResponse::from_checks(checks, 0);
+        assert!(response.is_ready());
+        assert_eq!(response.status, "READY");
+
+        let checks = BTreeMap::from([("a", CheckResult::up()), ("b", CheckResult::down("gone"))]);
+        let response = ReadinessResponse::from_checks(checks, 0);
+        assert!(!response.is_ready());
+        assert_eq!(response.status, "NOT_READY");
+    }
+
+    #[test]
+    fn test_readiness_serializes_check_breakdown() {
+        let checks = BTreeMap::from([("rebuild", CheckResult::down("pending"))]);
+        let json = serde_json::to_value(ReadinessResponse::from_checks(checks, 1)).unwrap();
+        assert_eq!(json["checks"]["rebuild"]["status"], "DOWN");
+        assert_eq!(json["checks"]["rebuild"]["detail"], "pending");
+    }
 }
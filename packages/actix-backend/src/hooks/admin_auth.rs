@@ -1,12 +1,17 @@
-use crate::utils::admin_token::validate_admin_token;
+use crate::api::ApiError;
+use crate::users::session_user;
+use crate::utils::admin_session::session_login;
+use crate::utils::admin_token::{Scope, authenticate};
+use crate::utils::audit_log::{self, AuditEntry};
 use actix_web::{
-    Error, HttpMessage, HttpRequest, HttpResponse,
+    Error, HttpMessage, HttpRequest,
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
     http::header::AUTHORIZATION,
 };
 use futures_util::future::{LocalBoxFuture, Ready, ok};
 use serde_json::json;
 use std::rc::Rc;
+use std::time::Instant;
 use tracing::{debug, warn};
 
 /// Middleware for admin token authentication.
@@ -15,32 +20,51 @@ use tracing::{debug, warn};
 /// 1. Authorization header: `Bearer <token>`
 /// 2. Authorization header: `<token>` (without Bearer prefix)
 /// 3. Query parameter: `?admin_token=<token>`
-pub struct AdminAuth;
+///
+/// Without one, a login session from `POST /api/v1/admin/login` is accepted,
+/// as is a signed-in user whose role grants the scope; state-changing
+/// requests then also need the session's `X-CSRF-Token`.
+/// The token must carry the scope the route was wrapped with.
+pub struct AdminAuth {
+    scope: Scope,
+}
+
+impl AdminAuth {
+    pub fn require(scope: Scope) -> Self {
+        Self { scope }
+    }
+}
 
 impl<S, B> Transform<S, ServiceRequest> for AdminAuth
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     B: actix_web::body::MessageBody + 'static,
 {
-    type Response = ServiceResponse<actix_web::body::EitherBody<B>>;
+    type Response = ServiceResponse<B>;
     type Error = Error;
     type Transform = AdminAuthMiddleware<S>;
     type InitError = ();
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(AdminAuthMiddleware(Rc::new(service)))
+        ok(AdminAuthMiddleware {
+            service: Rc::new(service),
+            scope: self.scope,
+        })
     }
 }
 
-pub struct AdminAuthMiddleware<S>(Rc<S>);
+pub struct AdminAuthMiddleware<S> {
+    service: Rc<S>,
+    scope: Scope,
+}
 
 impl<S, B> Service<ServiceRequest> for AdminAuthMiddleware<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     B: actix_web::body::MessageBody + 'static,
 {
-    type Response = ServiceResponse<actix_web::body::EitherBody<B>>;
+    type Response = ServiceResponse<B>;
     type Error = Error;
     type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
@@ -48,64 +72,151 @@ where
         &self,
         ctx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<(), Self::Error>> {
-        self.0.poll_ready(ctx)
+        self.service.poll_ready(ctx)
     }
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let srv = Rc::clone(&self.0);
+        let srv = Rc::clone(&self.service);
+        let scope = self.scope;
 
         Box::pin(async move {
-            let token = extract_admin_token(&req);
-
-            match token {
-                Some(provided_token) => {
-                    match validate_admin_token(&provided_token) {
-                        Ok(true) => {
-                            debug!("✅ Admin token validated successfully");
-                            // Store admin flag in request extensions for later use
-                            req.extensions_mut().insert(AdminAuthenticated);
-                            let res = srv.call(req).await?;
-                            Ok(res.map_into_left_body())
-                        }
-                        Ok(false) => {
-                            warn!("❌ Invalid admin token provided");
-                            let (req, _) = req.into_parts();
-                            let response = HttpResponse::Forbidden().json(json!({
-                                "error": "Invalid admin token",
-                                "code": "INVALID_ADMIN_TOKEN"
-                            }));
-                            Ok(ServiceResponse::new(req, response).map_into_right_body())
-                        }
-                        Err(e) => {
-                            warn!("❌ Error validating admin token: {}", e);
-                            let (req, _) = req.into_parts();
-                            let response = HttpResponse::InternalServerError().json(json!({
-                                "error": "Authentication service unavailable",
-                                "code": "AUTH_SERVICE_ERROR"
-                            }));
-                            Ok(ServiceResponse::new(req, response).map_into_right_body())
-                        }
-                    }
-                }
-                None => {
-                    warn!("❌ No admin token provided for protected endpoint");
-                    let (req, _) = req.into_parts();
-                    let response = HttpResponse::Unauthorized()
-                        .json(json!({
-                            "error": "Admin token required",
-                            "code": "ADMIN_TOKEN_REQUIRED",
-                            "hint": "Provide admin token via 'Authorization: Bearer <token>' header or '?admin_token=<token>' query parameter"
-                        }));
-                    Ok(ServiceResponse::new(req, response).map_into_right_body())
+            let started = Instant::now();
+            let mut entry = AuditEntry::begin(req.request());
+
+            let result = match authorize(&req, scope, &mut entry) {
+                Ok(admin) => {
+                    // Store who authenticated in request extensions for later use
+                    req.extensions_mut().insert(admin);
+                    srv.call(req).await
                 }
+                Err(e) => Err(e.into()),
+            };
+
+            let status = match &result {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            entry.finish(status, started.elapsed());
+            audit_log::record(entry).await;
+            result
+        })
+    }
+}
+
+/// Check the request's token or login session against `scope`, returning
+/// who authenticated
+fn authorize(
+    req: &ServiceRequest,
+    scope: Scope,
+    entry: &mut AuditEntry,
+) -> Result<AdminAuthenticated, ApiError> {
+    let Some(provided_token) = extract_admin_token(req.request()) else {
+        return authorize_session(req, scope, entry);
+    };
+
+    match authenticate(&provided_token) {
+        Ok(Some(token)) => {
+            entry.token = Some(token.name.clone());
+            check_scope(token.allows(scope), token.name, token.scopes, scope)
+        }
+        Ok(None) => {
+            warn!("❌ Invalid admin token provided");
+            Err(ApiError::forbidden("Invalid or expired admin token"))
+        }
+        Err(e) => {
+            warn!("❌ Error validating admin token: {}", e);
+            Err(ApiError::internal("Authentication service unavailable"))
+        }
+    }
+}
+
+/// Who a login session belongs to and what it may do
+struct SessionPrincipal {
+    name: String,
+    scopes: Vec<Scope>,
+    csrf_ok: bool,
+}
+
+fn session_principal(req: &HttpRequest) -> anyhow::Result<Option<SessionPrincipal>> {
+    if let Some(login) = session_login(req)? {
+        return Ok(Some(SessionPrincipal {
+            csrf_ok: login.allows(req),
+            name: login.token.name,
+            scopes: login.token.scopes,
+        }));
+    }
+    Ok(session_user(req)?.map(|session| SessionPrincipal {
+        csrf_ok: session.allows(req),
+        name: session.actor(),
+        scopes: session.user.role().scopes().to_vec(),
+    }))
+}
+
+fn authorize_session(
+    req: &ServiceRequest,
+    scope: Scope,
+    entry: &mut AuditEntry,
+) -> Result<AdminAuthenticated, ApiError> {
+    match session_principal(req.request()) {
+        Ok(Some(principal)) => {
+            entry.token = Some(principal.name.clone());
+            if !principal.csrf_ok {
+                warn!("❌ Session {:?} sent no valid CSRF token", principal.name);
+                return Err(
+                    ApiError::forbidden("Missing or invalid CSRF token").with_details(json!({
+                        "hint": "Send the csrf_token from login in the 'X-CSRF-Token' header"
+                    })),
+                );
             }
+            check_scope(
+                principal.scopes.contains(&scope),
+                principal.name,
+                principal.scopes,
+                scope,
+            )
+        }
+        Ok(None) => {
+            warn!("❌ No admin token provided for protected endpoint");
+            Err(ApiError::unauthorized("Admin token required").with_details(json!({
+                "hint": "Provide admin token via 'Authorization: Bearer <token>' header or '?admin_token=<token>' query parameter, or log in with POST /api/v1/admin/login"
+            })))
+        }
+        Err(e) => {
+            warn!("❌ Error validating session: {}", e);
+            Err(ApiError::internal("Authentication service unavailable"))
+        }
+    }
+}
+
+fn check_scope(
+    allowed: bool,
+    name: String,
+    scopes: Vec<Scope>,
+    scope: Scope,
+) -> Result<AdminAuthenticated, ApiError> {
+    if allowed {
+        debug!("✅ Admin access {:?} validated for {}", name, scope);
+        Ok(AdminAuthenticated {
+            token: name,
+            scopes,
         })
+    } else {
+        warn!("❌ Admin access {:?} lacks the {} scope", name, scope);
+        Err(
+            ApiError::forbidden(format!("Admin token lacks the `{scope}` scope"))
+                .with_details(json!({ "required_scope": scope })),
+        )
     }
 }
 
-/// Marker type to indicate admin authentication in request extensions
+/// Who authenticated the request, in request extensions
 #[derive(Clone)]
-pub struct AdminAuthenticated;
+pub struct AdminAuthenticated {
+    /// The admin token's name, or `user:<id>` for a signed-in user
+    pub token: String,
+    /// Scopes the token or user holds, which bound the tokens it may issue
+    pub scopes: Vec<Scope>,
+}
 
 /// Extracts admin token from the request.
 ///
@@ -113,28 +224,28 @@ pub struct AdminAuthenticated;
 /// 1. Authorization header (Bearer token)
 /// 2. Authorization header (direct token)
 /// 3. Query parameter `admin_token`
-fn extract_admin_token(req: &ServiceRequest) -> Option<String> {
+pub(crate) fn extract_admin_token(req: &HttpRequest) -> Option<String> {
     // Check Authorization header first
-    if let Some(auth_header) = req.headers().get(AUTHORIZATION) {
-        if let Ok(auth_str) = auth_header.to_str() {
-            // Try Bearer token format
-            if let Some(token) = auth_str.strip_prefix("Bearer ") {
-                return Some(token.trim().to_string());
-            }
-            // Try direct token format
-            if !auth_str.is_empty() {
-                return Some(auth_str.trim().to_string());
-            }
+    if let Some(auth_header) = req.headers().get(AUTHORIZATION)
+        && let Ok(auth_str) = auth_header.to_str()
+    {
+        // Try Bearer token format
+        if let Some(token) = auth_str.strip_prefix("Bearer ") {
+            return Some(token.trim().to_string());
+        }
+        // Try direct token format
+        if !auth_str.is_empty() {
+            return Some(auth_str.trim().to_string());
         }
     }
 
     // Check query parameter
     if let Some(query_str) = req.uri().query() {
         for pair in query_str.split('&') {
-            if let Some((key, value)) = pair.split_once('=') {
-                if key == "admin_token" {
-                    return Some(value.to_string());
-                }
+            if let Some((key, value)) = pair.split_once('=')
+                && key == "admin_token"
+            {
+                return Some(value.to_string());
             }
         }
     }
@@ -157,7 +268,7 @@ mod tests {
     async fn test_extract_admin_token_from_bearer_header() {
         let req = test::TestRequest::default()
             .append_header(("Authorization", "Bearer test_token_123"))
-            .to_srv_request();
+            .to_http_request();
 
         let token = extract_admin_token(&req);
         assert_eq!(token, Some("test_token_123".to_string()));
@@ -167,7 +278,7 @@ mod tests {
     async fn test_extract_admin_token_from_direct_header() {
         let req = test::TestRequest::default()
             .append_header(("Authorization", "test_token_456"))
-            .to_srv_request();
+            .to_http_request();
 
         let token = extract_admin_token(&req);
         assert_eq!(token, Some("test_token_456".to_string()));
@@ -177,7 +288,7 @@ mod tests {
     async fn test_extract_admin_token_from_query() {
         let req = test::TestRequest::default()
             .uri("/test?admin_token=query_token_789&other=param")
-            .to_srv_request();
+            .to_http_request();
 
         let token = extract_admin_token(&req);
         assert_eq!(token, Some("query_token_789".to_string()));
@@ -185,7 +296,7 @@ mod tests {
 
     #[actix_web::test]
     async fn test_extract_admin_token_none() {
-        let req = test::TestRequest::default().uri("/test").to_srv_request();
+        let req = test::TestRequest::default().uri("/test").to_http_request();
 
         let token = extract_admin_token(&req);
         assert_eq!(token, None);
@@ -1,18 +1,391 @@
+//! Session cookies and the key that encrypts them.
+//!
+//! The key comes from `SESSION_KEY` (base64 of 64 bytes) or else from
+//! `.session-key` in the root dir (or `SESSION_KEY_FILE`), which is generated
+//! on first run. Replicas share sessions by sharing either one. After a
+//! rotation the old key, from `SESSION_KEY_PREVIOUS` or `.session-key.previous`,
+//! still opens existing cookies.
+
 use actix_session::{SessionMiddleware, config::PersistentSession, storage::CookieSessionStore};
-use actix_web::cookie::Key;
-use actix_web::cookie::{SameSite, time::Duration};
+use actix_web::{
+    Error,
+    cookie::{Cookie, CookieJar, Key, SameSite, time::Duration},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{COOKIE, HeaderValue},
+};
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use futures_util::future::{LocalBoxFuture, Ready, ok};
+use shared::utils::root_dir::root_dir;
 use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{debug, info};
 
-/// Configure session & identity middleware
-pub fn session_middleware() -> SessionMiddleware<CookieSessionStore> {
-    // Generate or load your 64-byte key for session encryption
-    let secret_key = Key::generate();
+const SESSION_COOKIE: &str = "vtt-maps.dnd-apps.dev";
+const SESSION_KEY_FILE: &str = ".session-key";
+
+/// Distinguishes concurrent writers of the same key file
+static WRITE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// The key sessions are sealed with and the one it replaced
+#[derive(Clone)]
+pub struct SessionKeys {
+    pub current: Key,
+    pub previous: Option<Key>,
+}
+
+fn encode_key(key: &Key) -> String {
+    STANDARD.encode(key.master())
+}
+
+fn decode_key(encoded: &str) -> Result<Key> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .context("Session key is not valid base64")?;
+    Key::try_from(bytes.as_slice()).context("Session key must be at least 64 bytes")
+}
+
+fn key_path() -> Result<PathBuf> {
+    if let Ok(path) = env::var("SESSION_KEY_FILE")
+        && !path.trim().is_empty()
+    {
+        return Ok(PathBuf::from(path));
+    }
+    Ok(root_dir()?.join(SESSION_KEY_FILE))
+}
+
+fn previous_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".previous");
+    PathBuf::from(name)
+}
+
+fn env_key(var: &str) -> Result<Option<Key>> {
+    match env::var(var) {
+        Ok(value) if !value.trim().is_empty() => decode_key(&value)
+            .with_context(|| format!("Invalid {var}"))
+            .map(Some),
+        _ => Ok(None),
+    }
+}
 
-    SessionMiddleware::builder(CookieSessionStore::default(), secret_key.clone())
-        .cookie_name("vtt-maps.dnd-apps.dev".to_string())
+fn read_key(path: &Path) -> Result<Option<Key>> {
+    match fs::read_to_string(path) {
+        Ok(encoded) => decode_key(&encoded)
+            .with_context(|| format!("Invalid session key in {}", path.display()))
+            .map(Some),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Write a key readable only by the owner; `create_new` refuses to replace one.
+/// The key is written to a staging file first and then linked or renamed into
+/// place, so readers never see a partial key.
+fn write_key(path: &Path, key: &Key, create_new: bool) -> std::io::Result<()> {
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(format!(
+        ".tmp-{}-{}",
+        std::process::id(),
+        WRITE_SEQ.fetch_add(1, Ordering::Relaxed)
+    ));
+    let staging = PathBuf::from(staging);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options.open(&staging).and_then(|mut file| {
+        file.write_all(encode_key(key).as_bytes())?;
+        file.sync_all()
+    });
+    let placed = written.and_then(|()| {
+        if create_new {
+            // Unlike a rename, linking fails if another process got there first
+            fs::hard_link(&staging, path)
+        } else {
+            fs::rename(&staging, path)
+        }
+    });
+    let _ = fs::remove_file(&staging);
+    placed
+}
+
+/// Load the key from `path`, generating it if missing. Replicas starting
+/// together on a shared volume all end up with whichever key was written first.
+fn load_or_create(path: &Path) -> Result<Key> {
+    if let Some(key) = read_key(path)? {
+        return Ok(key);
+    }
+    let key = Key::generate();
+    match write_key(path, &key, true) {
+        Ok(()) => {
+            info!("🔑 Generated session key at {}", path.display());
+            Ok(key)
+        }
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            read_key(path)?.context("Session key file vanished while loading it")
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to write {}", path.display())),
+    }
+}
+
+/// Load the session keys from the environment or the key files.
+///
+/// # Errors
+/// Returns an error if a configured key is invalid or the key file cannot be
+/// read or created.
+pub fn load_session_keys() -> Result<SessionKeys> {
+    if let Some(current) = env_key("SESSION_KEY")? {
+        return Ok(SessionKeys {
+            current,
+            previous: env_key("SESSION_KEY_PREVIOUS")?,
+        });
+    }
+    let path = key_path()?;
+    Ok(SessionKeys {
+        current: load_or_create(&path)?,
+        previous: read_key(&previous_path(&path))?,
+    })
+}
+
+/// Replace the key file's key, keeping the old one as the previous key.
+/// Running servers pick up the new key when restarted.
+///
+/// # Errors
+/// Returns an error if the keys come from `SESSION_KEY` or cannot be written.
+pub fn rotate_session_key() -> Result<PathBuf> {
+    if env::var("SESSION_KEY").is_ok_and(|v| !v.trim().is_empty()) {
+        bail!(
+            "SESSION_KEY is set; rotate it where it is configured and move the old value to SESSION_KEY_PREVIOUS"
+        );
+    }
+    let path = key_path()?;
+    rotate_at(&path)?;
+    Ok(path)
+}
+
+fn rotate_at(path: &Path) -> Result<()> {
+    let previous = previous_path(path);
+    if let Some(current) = read_key(path)? {
+        write_key(&previous, &current, false)
+            .with_context(|| format!("Failed to write {}", previous.display()))?;
+    }
+    write_key(path, &Key::generate(), false)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Configure session & identity middleware
+pub fn session_middleware(keys: &SessionKeys) -> SessionMiddleware<CookieSessionStore> {
+    SessionMiddleware::builder(CookieSessionStore::default(), keys.current.clone())
+        .cookie_name(SESSION_COOKIE.to_string())
         .cookie_http_only(true)
         .cookie_secure(env::var("COOKIE_SECURE").is_ok())
-        .cookie_same_site(SameSite::Strict)
+        // Lax so the session survives the redirect back from the identity
+        // provider; admin writes still need the session's CSRF token
+        .cookie_same_site(SameSite::Lax)
         .session_lifecycle(PersistentSession::default().session_ttl(Duration::minutes(30)))
         .build()
 }
+
+/// Cookies in a request header, percent-decoded as actix does
+fn parse_cookies(header: &str) -> impl Iterator<Item = Cookie<'_>> {
+    header
+        .split(';')
+        .map(str::trim)
+        .filter_map(|pair| Cookie::parse_encoded(pair).ok())
+}
+
+/// Re-encrypt a session cookie sealed with the previous key under the current
+/// one, returning the rewritten `Cookie` header
+fn reseal(header: &str, keys: &SessionKeys) -> Option<String> {
+    let previous = keys.previous.as_ref()?;
+    let mut changed = false;
+    let cookies = parse_cookies(header)
+        .map(|cookie| {
+            if cookie.name() != SESSION_COOKIE {
+                return cookie.stripped().encoded().to_string();
+            }
+            let mut jar = CookieJar::new();
+            jar.add_original(cookie.clone().into_owned());
+            if jar.private(&keys.current).get(SESSION_COOKIE).is_some() {
+                return cookie.stripped().encoded().to_string();
+            }
+            let Some(plain) = jar.private(previous).get(SESSION_COOKIE) else {
+                return cookie.stripped().encoded().to_string();
+            };
+            let mut sealed = CookieJar::new();
+            sealed.private_mut(&keys.current).add(plain);
+            changed = true;
+            sealed
+                .get(SESSION_COOKIE)
+                .map_or_else(String::new, |c| c.stripped().encoded().to_string())
+        })
+        .collect::<Vec<_>>();
+    changed.then(|| cookies.join("; "))
+}
+
+/// Middleware letting sessions sealed with the previous key survive a
+/// rotation; wrap it outside [`session_middleware`]
+pub struct PreviousSessionKey {
+    keys: SessionKeys,
+}
+
+impl PreviousSessionKey {
+    pub fn new(keys: &SessionKeys) -> Self {
+        Self { keys: keys.clone() }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for PreviousSessionKey
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: actix_web::body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = PreviousSessionKeyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(PreviousSessionKeyMiddleware {
+            service: Rc::new(service),
+            keys: self.keys.clone(),
+        })
+    }
+}
+
+pub struct PreviousSessionKeyMiddleware<S> {
+    service: Rc<S>,
+    keys: SessionKeys,
+}
+
+impl<S, B> Service<ServiceRequest> for PreviousSessionKeyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: actix_web::body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        if self.keys.previous.is_some() {
+            // Read the raw header: `req.cookies()` would cache the stale cookie
+            let header = req
+                .headers()
+                .get_all(COOKIE)
+                .filter_map(|v| v.to_str().ok())
+                .collect::<Vec<_>>()
+                .join("; ");
+            if let Some(resealed) = reseal(&header, &self.keys)
+                && let Ok(value) = HeaderValue::from_str(&resealed)
+            {
+                debug!("Resealed a session cookie from the previous key");
+                req.headers_mut().insert(COOKIE, value);
+            }
+        }
+
+        let srv = Rc::clone(&self.service);
+        Box::pin(async move { srv.call(req).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sealed(key: &Key, value: &str) -> String {
+        let mut jar = CookieJar::new();
+        jar.private_mut(key)
+            .add(Cookie::new(SESSION_COOKIE, value.to_string()));
+        jar.get(SESSION_COOKIE)
+            .unwrap()
+            .stripped()
+            .encoded()
+            .to_string()
+    }
+
+    fn open(header: &str, key: &Key) -> Option<String> {
+        let mut jar = CookieJar::new();
+        for cookie in parse_cookies(header) {
+            jar.add_original(cookie.into_owned());
+        }
+        jar.private(key)
+            .get(SESSION_COOKIE)
+            .map(|c| c.value().to_string())
+    }
+
+    #[test]
+    fn test_reseals_cookies_from_previous_key() {
+        let keys = SessionKeys {
+            current: Key::generate(),
+            previous: Some(Key::generate()),
+        };
+        let old = format!(
+            "theme=dark; {}",
+            sealed(keys.previous.as_ref().unwrap(), "state")
+        );
+        let resealed = reseal(&old, &keys).unwrap();
+        assert!(resealed.starts_with("theme=dark; "));
+        assert_eq!(open(&resealed, &keys.current).as_deref(), Some("state"));
+
+        // Cookies already under the current key, or under no known key, pass through
+        assert_eq!(reseal(&sealed(&keys.current, "state"), &keys), None);
+        assert_eq!(reseal(&sealed(&Key::generate(), "state"), &keys), None);
+    }
+
+    #[test]
+    fn test_key_file_is_created_once_and_rotated() {
+        let dir = std::env::temp_dir().join(format!("session-key-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(SESSION_KEY_FILE);
+
+        let encoded = |key: Option<Key>| key.as_ref().map(encode_key);
+        let first = encoded(Some(load_or_create(&path).unwrap()));
+        assert_eq!(encoded(Some(load_or_create(&path).unwrap())), first);
+
+        rotate_at(&path).unwrap();
+        assert_ne!(encoded(read_key(&path).unwrap()), first);
+        assert_eq!(encoded(read_key(&previous_path(&path)).unwrap()), first);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_callers_share_one_key() {
+        let dir = std::env::temp_dir().join(format!("session-key-race-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for attempt in 0..20 {
+            let path = dir.join(format!("{SESSION_KEY_FILE}-{attempt}"));
+            let start = std::sync::Barrier::new(2);
+            let keys = std::thread::scope(|scope| {
+                let callers = [(); 2].map(|()| {
+                    scope.spawn(|| {
+                        start.wait();
+                        encode_key(&load_or_create(&path).unwrap())
+                    })
+                });
+                callers.map(|caller| caller.join().unwrap())
+            });
+            assert_eq!(keys[0], keys[1]);
+            assert_eq!(encode_key(&read_key(&path).unwrap().unwrap()), keys[0]);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,18 +1,107 @@
+use crate::hooks::telemetry::otel_layer;
+use crate::metrics::SpanMetricsLayer;
+use std::env;
 use tracing::level_filters::LevelFilter;
-use tracing_subscriber::filter::Targets;
-use tracing_subscriber::fmt;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry, fmt};
+
+/// Targets silenced unless `RUST_LOG` mentions them explicitly
+const NOISY_TARGETS: [&str; 2] = ["html5ever", "markup5ever"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    /// Read from `LOG_FORMAT` (`json` or `text`, defaults to text)
+    fn from_env() -> Self {
+        match env::var("LOG_FORMAT") {
+            Ok(value) if value.eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
+/// Whether a `target[span]=level` directive in `directives` names `target`
+fn mentions_target(directives: &str, target: &str) -> bool {
+    directives.split(',').any(|directive| {
+        let end = directive.find(['[', '=']).unwrap_or(directive.len());
+        directive[..end].trim() == target
+    })
+}
+
+/// Build the filter from `RUST_LOG` style directives, defaulting to INFO
+fn build_env_filter(directives: &str) -> EnvFilter {
+    let mut filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .parse_lossy(directives);
+
+    for target in NOISY_TARGETS {
+        if !mentions_target(directives, target)
+            && let Ok(directive) = format!("{target}=off").parse()
+        {
+            filter = filter.add_directive(directive);
+        }
+    }
+    filter
+}
+
+fn fmt_layer(format: LogFormat) -> Box<dyn Layer<Registry> + Send + Sync> {
+    match format {
+        LogFormat::Text => fmt::layer().boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
 
 /// Initialize the global tracing subscriber with filters
+///
+/// Honors `RUST_LOG`, `LOG_FORMAT` and, when an OTLP endpoint is configured,
+/// exports spans to the collector.
 pub fn setup_logger() {
-    let filter = Targets::new()
-        .with_target("html5ever", LevelFilter::OFF)
-        .with_target("markup5ever", LevelFilter::OFF)
-        .with_default(LevelFilter::INFO);
+    let directives = env::var(EnvFilter::DEFAULT_ENV).unwrap_or_default();
 
     tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(filter)
+        .with(fmt_layer(LogFormat::from_env()))
+        .with(otel_layer())
+        .with(SpanMetricsLayer)
+        .with(build_env_filter(&directives))
         .init();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_filter_defaults_to_info_and_silences_html_parsers() {
+        let filter = build_env_filter("").to_string();
+        assert!(filter.contains("info"));
+        assert!(filter.contains("html5ever=off"));
+        assert!(filter.contains("markup5ever=off"));
+    }
+
+    #[test]
+    fn test_env_filter_keeps_explicit_directives() {
+        let filter = build_env_filter("debug,html5ever=warn").to_string();
+        assert!(filter.contains("debug"));
+        assert!(filter.contains("html5ever=warn"));
+        assert!(!filter.contains("html5ever=off"));
+    }
+
+    #[test]
+    fn test_env_filter_matches_targets_exactly() {
+        let filter = build_env_filter("markup5ever_rcdom=debug,app[html5ever]=info").to_string();
+        assert!(filter.contains("markup5ever=off"));
+        assert!(filter.contains("html5ever=off"));
+
+        let filter = build_env_filter("info, markup5ever[parse]=trace").to_string();
+        assert!(!filter.contains("markup5ever=off"));
+    }
+}
@@ -2,4 +2,6 @@ pub mod admin_auth;
 pub mod cors;
 pub mod identity;
 pub mod logger;
+pub mod rate_limit;
 pub mod security;
+pub mod telemetry;
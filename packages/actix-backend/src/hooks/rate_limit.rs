@@ -0,0 +1,467 @@
+//! Per-client token bucket rate limiting for expensive routes.
+//!
+//! Each [`Budget`] is configured with `RATE_LIMIT_<BUDGET>=<requests>/<seconds>`,
+//! such as `RATE_LIMIT_IMAGES=30/60`, or `off` to disable it. A client may
+//! burst up to `requests` and then refills evenly over `seconds`.
+//!
+//! Clients are keyed by their admin token's name when they present a valid
+//! one, otherwise by IP, with IPv6 clients grouped by /64. `X-Forwarded-For`
+//! is only trusted when the peer is listed in `TRUSTED_PROXIES` (comma
+//! separated IPs).
+
+use crate::api::ApiError;
+use crate::hooks::admin_auth::extract_admin_token;
+use crate::metrics::registry::RATE_LIMITED;
+use crate::utils::admin_token::authenticate;
+use actix_web::{
+    Error, HttpRequest,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+};
+use futures_util::future::{LocalBoxFuture, Ready, ok};
+use serde_json::json;
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::sync::{LazyLock, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Most buckets kept across all budgets
+const MAX_CLIENTS: usize = 10_000;
+/// Buckets left after an eviction, so evictions run once per batch of new
+/// clients rather than on every request
+const EVICT_TO: usize = MAX_CLIENTS / 10 * 9;
+
+type Buckets = HashMap<(Budget, String), Bucket>;
+
+static BUCKETS: LazyLock<Mutex<Buckets>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub(crate) static TRUSTED_PROXIES: LazyLock<Vec<IpAddr>> = LazyLock::new(|| {
+    env::var("TRUSTED_PROXIES")
+        .map(|v| parse_proxies(&v))
+        .unwrap_or_default()
+});
+
+/// Independent allowance for one class of expensive routes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Budget {
+    /// Routes that decode a whole map image
+    Images,
+    Downloads,
+    Search,
+    Admin,
+}
+
+impl Budget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Budget::Images => "images",
+            Budget::Downloads => "downloads",
+            Budget::Search => "search",
+            Budget::Admin => "admin",
+        }
+    }
+
+    fn default_limit(self) -> Limit {
+        let requests = match self {
+            Budget::Images => 30,
+            Budget::Downloads => 20,
+            Budget::Search => 120,
+            Budget::Admin => 20,
+        };
+        Limit {
+            requests,
+            per: Duration::from_secs(60),
+        }
+    }
+
+    /// The configured limit, or `None` when disabled
+    fn limit(self) -> Option<Limit> {
+        let var = format!("RATE_LIMIT_{}", self.as_str().to_uppercase());
+        match env::var(&var) {
+            Ok(value) if value.eq_ignore_ascii_case("off") => None,
+            Ok(value) => Some(Limit::parse(&value).unwrap_or_else(|| {
+                warn!(
+                    "⚠️ Ignoring invalid {}={:?}, expected <requests>/<seconds>",
+                    var, value
+                );
+                self.default_limit()
+            })),
+            Err(_) => Some(self.default_limit()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    requests: u32,
+    per: Duration,
+}
+
+impl Limit {
+    fn parse(value: &str) -> Option<Self> {
+        let (requests, seconds) = value.split_once('/')?;
+        let requests = requests.trim().parse().ok().filter(|&r| r > 0)?;
+        let seconds = seconds.trim().parse().ok().filter(|&s| s > 0)?;
+        Some(Self {
+            requests,
+            per: Duration::from_secs(seconds),
+        })
+    }
+
+    fn per_token(&self) -> Duration {
+        self.per / self.requests
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// The budget's refill period, after which the bucket is full again
+    per: Duration,
+}
+
+impl Bucket {
+    fn full(limit: &Limit, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(limit.requests),
+            updated: now,
+            per: limit.per,
+        }
+    }
+
+    /// Take one token, or return how long until one is available
+    fn take(&mut self, limit: &Limit, now: Instant) -> Result<(), Duration> {
+        let refilled =
+            now.duration_since(self.updated).as_secs_f64() / limit.per_token().as_secs_f64();
+        self.tokens = (self.tokens + refilled).min(f64::from(limit.requests));
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(limit.per_token().mul_f64(1.0 - self.tokens))
+        }
+    }
+
+    /// Whether the bucket has refilled completely and can be forgotten
+    fn is_idle(&self, now: Instant) -> bool {
+        now.duration_since(self.updated) >= self.per
+    }
+}
+
+fn parse_proxies(value: &str) -> Vec<IpAddr> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .filter_map(|p| {
+            p.parse()
+                .inspect_err(|_| warn!("⚠️ Ignoring invalid trusted proxy {:?}", p))
+                .ok()
+        })
+        .collect()
+}
+
+/// The client's IP, walking `X-Forwarded-For` from the right past trusted proxies
+pub(crate) fn client_ip(req: &HttpRequest, trusted: &[IpAddr]) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    if !trusted.contains(&peer) {
+        return Some(peer);
+    }
+    let forwarded = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+    forwarded
+        .iter()
+        .rev()
+        .find(|ip| !trusted.contains(ip))
+        .or_else(|| forwarded.first())
+        .copied()
+        .or(Some(peer))
+}
+
+/// Key for an IP; IPv6 clients usually hold a whole /64, so they share one
+fn ip_key(ip: IpAddr) -> String {
+    match ip.to_canonical() {
+        IpAddr::V6(v6) => {
+            let [a, b, c, d, ..] = v6.segments();
+            format!("ip:{a:x}:{b:x}:{c:x}:{d:x}::/64")
+        }
+        ip => format!("ip:{ip}"),
+    }
+}
+
+/// Bucket key for the request: a valid admin token's name, otherwise the IP
+fn client_key(req: &HttpRequest, trusted: &[IpAddr]) -> String {
+    if let Some(secret) = extract_admin_token(req)
+        && let Ok(Some(token)) = authenticate(&secret)
+    {
+        return format!("token:{}", token.name);
+    }
+    client_ip(req, trusted).map_or_else(|| "ip:unknown".to_string(), ip_key)
+}
+
+/// Make room for new clients: drop idle buckets, then the least recently
+/// used ones, down to [`EVICT_TO`]
+fn evict(buckets: &mut Buckets, now: Instant) {
+    buckets.retain(|_, bucket| !bucket.is_idle(now));
+    let excess = buckets.len().saturating_sub(EVICT_TO);
+    if excess == 0 {
+        return;
+    }
+    warn!(
+        "🚦 Rate limiter is full, forgetting {} active clients",
+        excess
+    );
+    let mut oldest = buckets
+        .iter()
+        .map(|(key, bucket)| (bucket.updated, key.clone()))
+        .collect::<Vec<_>>();
+    oldest.select_nth_unstable_by_key(excess - 1, |(updated, _)| *updated);
+    for (_, key) in oldest.into_iter().take(excess) {
+        buckets.remove(&key);
+    }
+}
+
+fn take(
+    buckets: &mut Buckets,
+    budget: Budget,
+    limit: &Limit,
+    key: String,
+    now: Instant,
+) -> Result<(), Duration> {
+    let key = (budget, key);
+    if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(&key) {
+        evict(buckets, now);
+    }
+    buckets
+        .entry(key)
+        .or_insert_with(|| Bucket::full(limit, now))
+        .take(limit, now)
+}
+
+fn check(budget: Budget, limit: &Limit, key: String, now: Instant) -> Result<(), Duration> {
+    let mut buckets = BUCKETS.lock().unwrap_or_else(PoisonError::into_inner);
+    take(&mut buckets, budget, limit, key, now)
+}
+
+/// Take one request from the client's `budget`, or fail with a 429
+fn limit_request(req: &HttpRequest, budget: Budget, limit: &Limit) -> Result<(), ApiError> {
+    let key = client_key(req, &TRUSTED_PROXIES);
+    let Err(wait) = check(budget, limit, key.clone(), Instant::now()) else {
+        return Ok(());
+    };
+    let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    warn!(
+        "🚦 Rate limited {} on {} budget, retry in {}s",
+        key,
+        budget.as_str(),
+        retry_after
+    );
+    RATE_LIMITED.with_label_values(&[budget.as_str()]).inc();
+    Err(ApiError::too_many_requests(
+        format!("Too many {} requests", budget.as_str()),
+        retry_after,
+    )
+    .with_details(json!({
+        "budget": budget.as_str(),
+        "retry_after": retry_after,
+    })))
+}
+
+/// Charge `budget` from inside a handler, for work that is only sometimes
+/// expensive, such as the request that generates a cached asset
+///
+/// # Errors
+/// Returns a 429 [`ApiError`] when the client has run out of requests.
+pub fn charge(req: &HttpRequest, budget: Budget) -> Result<(), ApiError> {
+    match budget.limit() {
+        Some(limit) => limit_request(req, budget, &limit),
+        None => Ok(()),
+    }
+}
+
+/// Middleware limiting each client to a [`Budget`], shared by every route
+/// wrapped with the same budget
+pub struct RateLimit {
+    budget: Budget,
+    limit: Option<Limit>,
+}
+
+impl RateLimit {
+    pub fn new(budget: Budget) -> Self {
+        Self {
+            budget,
+            limit: budget.limit(),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: actix_web::body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            budget: self.budget,
+            limit: self.limit,
+        })
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    budget: Budget,
+    limit: Option<Limit>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: actix_web::body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let srv = Rc::clone(&self.service);
+        let budget = self.budget;
+        let limit = self.limit;
+
+        Box::pin(async move {
+            if let Some(limit) = limit {
+                limit_request(req.request(), budget, &limit)?;
+            }
+            srv.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, HttpResponse, http::StatusCode, test::TestRequest, web};
+
+    #[test]
+    fn test_bucket_refills_evenly() {
+        let limit = Limit::parse("2/10").unwrap();
+        let start = Instant::now();
+        let mut bucket = Bucket::full(&limit, start);
+        assert!(bucket.take(&limit, start).is_ok());
+        assert!(bucket.take(&limit, start).is_ok());
+        assert_eq!(bucket.take(&limit, start), Err(Duration::from_secs(5)));
+
+        let later = start + Duration::from_secs(5);
+        assert!(bucket.take(&limit, later).is_ok());
+        assert!(bucket.take(&limit, later).is_err());
+        assert!(bucket.is_idle(later + Duration::from_secs(10)));
+
+        assert_eq!(Limit::parse("0/10"), None);
+        assert_eq!(Limit::parse("ten"), None);
+    }
+
+    #[test]
+    fn test_client_ip_trusts_only_listed_proxies() {
+        let proxies = parse_proxies("10.0.0.1, 10.0.0.2, bogus");
+        assert_eq!(proxies.len(), 2);
+        let req = |peer: &str| {
+            TestRequest::default()
+                .peer_addr(format!("{peer}:4000").parse().unwrap())
+                .insert_header(("X-Forwarded-For", "6.6.6.6, 203.0.113.7, 10.0.0.2"))
+                .to_http_request()
+        };
+
+        // A spoofed left-most entry is ignored behind trusted proxies
+        assert_eq!(
+            client_ip(&req("10.0.0.1"), &proxies),
+            "203.0.113.7".parse().ok()
+        );
+        // Untrusted peers cannot pick their own address
+        assert_eq!(
+            client_ip(&req("198.51.100.1"), &proxies),
+            "198.51.100.1".parse().ok()
+        );
+    }
+
+    #[test]
+    fn test_ipv6_clients_are_keyed_by_prefix() {
+        let key = |ip: &str| ip_key(ip.parse().unwrap());
+        assert_eq!(key("2001:db8:1:2:aaaa::1"), "ip:2001:db8:1:2::/64");
+        assert_eq!(key("2001:db8:1:2::ffff"), key("2001:db8:1:2:aaaa::1"));
+        assert_eq!(key("::ffff:192.0.2.1"), "ip:192.0.2.1");
+        assert_eq!(key("192.0.2.1"), "ip:192.0.2.1");
+    }
+
+    #[test]
+    fn test_full_limiter_evicts_least_recently_used() {
+        let limit = Limit::parse("10/60").unwrap();
+        let start = Instant::now();
+        let mut buckets = Buckets::new();
+        for i in 0..MAX_CLIENTS {
+            let now = start + Duration::from_millis(i as u64);
+            take(&mut buckets, Budget::Images, &limit, format!("ip:{i}"), now).unwrap();
+        }
+        assert_eq!(buckets.len(), MAX_CLIENTS);
+
+        let now = start + Duration::from_secs(30);
+        take(&mut buckets, Budget::Images, &limit, "ip:new".into(), now).unwrap();
+        assert_eq!(buckets.len(), EVICT_TO + 1);
+        assert!(!buckets.contains_key(&(Budget::Images, "ip:0".into())));
+        let newest = format!("ip:{}", MAX_CLIENTS - 1);
+        assert!(buckets.contains_key(&(Budget::Images, newest)));
+    }
+
+    #[actix_web::test]
+    async fn test_returns_429_with_retry_after() {
+        let app = actix_web::test::init_service(
+            App::new().service(
+                web::resource("/tiled")
+                    .wrap(RateLimit {
+                        budget: Budget::Images,
+                        limit: Limit::parse("1/60"),
+                    })
+                    .to(HttpResponse::Ok),
+            ),
+        )
+        .await;
+        let req = || {
+            TestRequest::get()
+                .uri("/tiled")
+                .peer_addr("192.0.2.47:4000".parse().unwrap())
+                .to_request()
+        };
+
+        let res = actix_web::test::call_service(&app, req()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let err = actix_web::test::try_call_service(&app, req())
+            .await
+            .unwrap_err();
+        let res = err.error_response();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get("retry-after").unwrap(), "60");
+    }
+}
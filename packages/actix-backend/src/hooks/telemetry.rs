@@ -0,0 +1,145 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use std::env;
+use std::sync::OnceLock;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+const DEFAULT_SERVICE_NAME: &str = "vtt-maps";
+const ENDPOINT_VARS: [&str; 2] = [
+    "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+];
+
+/// Provider kept so buffered spans can be flushed on shutdown
+static TRACER_PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// OTLP export is enabled when either standard endpoint variable is set
+pub fn otlp_enabled() -> bool {
+    ENDPOINT_VARS
+        .iter()
+        .any(|var| env::var(var).is_ok_and(|v| !v.trim().is_empty()))
+}
+
+/// Build a tracer provider exporting spans over OTLP/HTTP (protobuf).
+///
+/// With `endpoint` unset the exporter resolves it from the standard
+/// `OTEL_EXPORTER_OTLP_*` environment variables.
+pub fn build_tracer_provider(
+    endpoint: Option<&str>,
+) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let mut builder = SpanExporter::builder().with_http();
+    if let Some(endpoint) = endpoint {
+        builder = builder.with_endpoint(endpoint);
+    }
+    let exporter = builder.build()?;
+
+    let service_name =
+        env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string());
+    let resource = Resource::builder().with_service_name(service_name).build();
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build())
+}
+
+/// Tracing layer forwarding spans to the OTLP collector, if configured
+pub fn otel_layer<S>() -> Option<OpenTelemetryLayer<S, SdkTracer>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    if !otlp_enabled() {
+        return None;
+    }
+
+    match build_tracer_provider(None) {
+        Ok(provider) => {
+            let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+            let _ = TRACER_PROVIDER.set(provider);
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+        Err(e) => {
+            // The subscriber is not installed yet, so report directly
+            eprintln!("Failed to configure OTLP trace exporter: {e}");
+            None
+        }
+    }
+}
+
+/// Flush and stop the OTLP exporter
+pub fn shutdown_tracer() {
+    if let Some(provider) = TRACER_PROVIDER.get()
+        && let Err(e) = provider.shutdown()
+    {
+        eprintln!("Failed to shut down OTLP trace exporter: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Accept one HTTP request, reply 200 and report its request line
+    fn spawn_collector() -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut stream = reader.into_inner();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+            tx.send(request_line).unwrap();
+        });
+
+        (format!("http://{addr}/v1/traces"), rx)
+    }
+
+    #[test]
+    fn test_spans_are_exported_to_collector() {
+        let (endpoint, received) = spawn_collector();
+        let provider = build_tracer_provider(Some(&endpoint)).unwrap();
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("test"));
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("exported_span").in_scope(|| {});
+        });
+        provider.shutdown().unwrap();
+
+        let request_line = received.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(request_line.starts_with("POST /v1/traces"));
+    }
+}
@@ -1,22 +1,36 @@
+mod api;
+mod cli;
 mod clients;
 mod docs;
+mod export;
 mod health;
 mod hooks;
 mod maps;
+mod metrics;
 mod services;
+mod users;
 mod utils;
 mod wrappers;
 
 use actix_files::Files;
 use actix_web::dev::Service;
-use actix_web::{App, HttpServer, http::header::CONTENT_TYPE, web};
+use actix_web::{
+    App, HttpServer,
+    http::{StatusCode, header::CACHE_CONTROL},
+    middleware::from_fn,
+    web,
+};
 use tracing::{error, info};
 
-use crate::hooks::{cors, identity, logger::setup_logger, security};
+use crate::cli::{Cli, Command};
+use crate::hooks::{cors, identity, logger::setup_logger, security, telemetry};
+use crate::maps::cache::immutable;
 use crate::maps::rebuild_maps_init;
+use crate::metrics::HttpMetrics;
 use crate::services::file_service::file_service;
 use crate::wrappers::seo::SeoMetadata;
 use actix_identity::IdentityMiddleware;
+use clap::Parser;
 use shared::utils::root_dir::root_dir;
 use std::env;
 use tracing_actix_web::TracingLogger;
@@ -27,6 +41,44 @@ use utils::setup::setup_folders;
 async fn main() -> std::io::Result<()> {
     // Initialize tracing subscriber
     setup_logger();
+    metrics::registry::init();
+
+    // Subcommands run once and exit; without one, serve the catalog
+    match Cli::parse().command {
+        Some(Command::Export { out }) => {
+            match export::export_site(&out) {
+                Ok(count) => info!("✅ Static site export completed: {} maps", count),
+                Err(e) => {
+                    error!("❌ Static site export failed: {:?}", e);
+                    eprintln!("Static site export failed: {e:?}");
+                    std::process::exit(1);
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Tokens(command)) => {
+            if let Err(e) = cli::run_tokens(command) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::SessionKey(command)) => {
+            if let Err(e) = cli::run_session_key(command) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Users(command)) => {
+            if let Err(e) = cli::run_users(command) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        None => {}
+    }
 
     let address = env::var("ADDRESS").unwrap_or_else(|_| "0.0.0.0".to_string());
     let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
@@ -43,8 +95,8 @@ async fn main() -> std::io::Result<()> {
     info!("Operating out of directory: {}", root.display());
 
     // Initialize admin token system
-    match utils::admin_token::get_or_create_admin_token() {
-        Ok(_) => info!("🔐 Admin token system initialized"),
+    match utils::admin_token::init_admin_tokens() {
+        Ok(()) => info!("🔐 Admin token system initialized"),
         Err(e) => {
             error!("❌ Failed to initialize admin token system: {:?}", e);
             eprintln!("Admin token initialization failed: {e:?}");
@@ -52,6 +104,15 @@ async fn main() -> std::io::Result<()> {
         }
     }
 
+    let session_keys = match identity::load_session_keys() {
+        Ok(keys) => keys,
+        Err(e) => {
+            error!("❌ Failed to load the session key: {:?}", e);
+            eprintln!("Session key initialization failed: {e:?}");
+            std::process::exit(1);
+        }
+    };
+
     // Run map rebuild process during initialization
     info!("🔧 Initializing maps rebuild process...");
     match rebuild_maps_init().await {
@@ -73,60 +134,51 @@ async fn main() -> std::io::Result<()> {
 
     info!("Listening on {}:{}", &address, &port);
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
             // Register middleware via configure hooks
+            .wrap(HttpMetrics)
             .wrap(TracingLogger::default())
             .wrap(IdentityMiddleware::default())
-            .wrap(identity::session_middleware())
+            .wrap(identity::session_middleware(&session_keys))
+            .wrap(identity::PreviousSessionKey::new(&session_keys))
             .wrap(cors::cors())
             .wrap(security::security())
             // SEO wrapper
             .wrap(SeoMetadata)
-            // Static thumbnails
-            .service(Files::new("/assets/thumbnails", thumb_dir.clone()).use_last_modified(true))
-            // API routes
+            // Static thumbnails, keyed by map hash so they never change
             .service(
-                web::scope("/api")
+                web::scope("/assets/thumbnails")
                     .wrap_fn(|req, srv| {
                         let fut = srv.call(req);
                         async move {
                             let mut res = fut.await?;
-                            if !res.headers().contains_key(CONTENT_TYPE) {
-                                res.headers_mut()
-                                    .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+                            if res.status().is_success() || res.status() == StatusCode::NOT_MODIFIED
+                            {
+                                res.headers_mut().insert(
+                                    CACHE_CONTROL,
+                                    immutable().to_string().parse().unwrap(),
+                                );
                             }
                             Ok(res)
                         }
                     })
-                    .service(
-                        web::scope("/maps")
-                            .route("/all", web::get().to(maps::maps_all))
-                            .route("/{id}", web::get().to(maps::map_detail))
-                            .service(
-                                web::resource("/rebuild")
-                                    .wrap(hooks::admin_auth::AdminAuth)
-                                    .route(web::post().to(maps::maps_rebuild)),
-                            )
-                            .route("/rebuild/status", web::get().to(maps::rebuild_status))
-                            .service(
-                                web::resource("/rebuild/clear")
-                                    .wrap(hooks::admin_auth::AdminAuth)
-                                    .route(web::delete().to(maps::clear_rebuild_lock)),
-                            )
-                            .route("/download/{id}", web::get().to(maps::download_map))
-                            .route("/tiled/{id}", web::get().to(maps::tiled_map))
-                            .route("/content/{id}", web::get().to(maps::map_content)),
-                    )
-                    .service(
-                        web::scope("/docs")
-                            .route("/readme", web::get().to(docs::docs_readme))
-                            .route("/license", web::get().to(docs::docs_license)),
-                    )
-                    .service(web::scope("/admin").route(
-                        "/token/info",
-                        web::get().to(utils::admin_info::get_admin_token_info),
-                    )),
+                    .service(Files::new("", thumb_dir.clone()).use_last_modified(true)),
+            )
+            // OpenAPI document and Swagger UI, ahead of the `/api` scopes
+            .service(docs::openapi::swagger_ui())
+            // API routes, with errors rendered as `ApiError`
+            .service(
+                web::scope(api::API_V1)
+                    .wrap(from_fn(api::error_model))
+                    .configure(api::routes),
+            )
+            // Unversioned paths from before `/api/v1`, kept for existing clients
+            .service(
+                web::scope("/api")
+                    .wrap(from_fn(api::error_model))
+                    .wrap(from_fn(api::deprecated_alias))
+                    .configure(api::routes),
             )
             // Health checks
             .service(
@@ -134,10 +186,15 @@ async fn main() -> std::io::Result<()> {
                     .route("/liveness", web::get().to(health::liveness))
                     .route("/readiness", web::get().to(health::readiness)),
             )
+            // Prometheus scrape endpoint
+            .route("/metrics", web::get().to(metrics::metrics))
             // SPA file service with fallback
             .configure(file_service)
     })
     .bind(format!("{address}:{port}"))?
     .run()
-    .await
+    .await;
+
+    telemetry::shutdown_tracer();
+    server
 }
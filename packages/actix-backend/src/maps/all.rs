@@ -1,9 +1,12 @@
+use crate::api::ApiError;
 use crate::clients::meilisearch::meilisearch_index;
+use crate::metrics::observe_meilisearch;
 use actix_web::HttpResponse;
 use actix_web::web::Query;
 use serde::Deserialize;
 use shared::types::map_document::MapDocument as MapDoc;
 use tracing::debug;
+use utoipa::IntoParams;
 
 fn default_limit() -> usize {
     10
@@ -13,14 +16,30 @@ fn default_offset() -> usize {
     0
 }
 
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Deserialize, Clone, Copy, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct PaginationParams {
+    /// Maps per page
     #[serde(default = "default_limit")]
+    #[param(default = 10)]
     pub limit: usize,
+    /// Maps to skip
     #[serde(default = "default_offset")]
+    #[param(default = 0)]
     pub offset: usize,
 }
 
+/// List the maps in the catalog
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/all",
+    tag = "maps",
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "One page of maps", body = [MapDoc]),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
 pub async fn maps_all(query: Query<PaginationParams>) -> Result<HttpResponse, actix_web::Error> {
     let PaginationParams { limit, offset } = query.into_inner();
     debug!(
@@ -29,13 +48,16 @@ pub async fn maps_all(query: Query<PaginationParams>) -> Result<HttpResponse, ac
     );
     let index = meilisearch_index("maps")?;
     debug!("Executing search with limit: {}, offset: {}", limit, offset);
-    let search = index
-        .search()
-        .with_limit(limit)
-        .with_offset(offset)
-        .execute::<MapDoc>()
-        .await
-        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let search = observe_meilisearch(
+        "search",
+        index
+            .search()
+            .with_limit(limit)
+            .with_offset(offset)
+            .execute::<MapDoc>(),
+    )
+    .await
+    .map_err(ApiError::internal)?;
 
     let docs: Vec<MapDoc> = search.hits.into_iter().map(|h| h.result).collect();
     debug!("Found {} maps", docs.len());
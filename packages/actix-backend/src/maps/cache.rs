@@ -0,0 +1,95 @@
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse,
+    http::header::{CACHE_CONTROL, CacheControl, CacheDirective, ETAG, EntityTag, IfNoneMatch},
+};
+
+const ONE_YEAR: u32 = 31_536_000;
+
+/// Cache policy for URLs whose response can never change: map ids are the
+/// SHA-256 of the map file, so anything keyed on one is content-addressed
+pub(crate) fn immutable() -> CacheControl {
+    CacheControl(vec![
+        CacheDirective::Public,
+        CacheDirective::MaxAge(ONE_YEAR),
+        CacheDirective::Extension("immutable".to_string(), None),
+    ])
+}
+
+/// Cache policy for responses that may change under the same URL; clients
+/// keep a copy but must revalidate it with `If-None-Match`
+pub(crate) fn revalidate() -> CacheControl {
+    CacheControl(vec![CacheDirective::Public, CacheDirective::NoCache])
+}
+
+/// Strong validator for a response derived from a map, where `variant`
+/// distinguishes renditions served for the same map
+pub(crate) fn map_etag(id: &str, variant: &str) -> EntityTag {
+    if variant.is_empty() {
+        EntityTag::new_strong(id.to_string())
+    } else {
+        EntityTag::new_strong(format!("{id}-{variant}"))
+    }
+}
+
+/// Whether the client already holds the representation tagged `etag`.
+///
+/// `*` is not honored: it only means "unchanged" if the map exists, which is
+/// not known yet when this is checked.
+fn is_fresh(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req.get_header::<IfNoneMatch>() {
+        // If-None-Match uses the weak comparison (RFC 9110 §13.1.2)
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        Some(IfNoneMatch::Any) | None => false,
+    }
+}
+
+/// `304 Not Modified` if the client's cached copy is still current.
+///
+/// Checked before any lookup or rendering so repeat visits cost nothing.
+pub(crate) fn not_modified(
+    req: &HttpRequest,
+    etag: &EntityTag,
+    cache_control: CacheControl,
+) -> Option<HttpResponse> {
+    is_fresh(req, etag).then(|| {
+        HttpResponse::NotModified()
+            .insert_header((ETAG, etag.clone()))
+            .insert_header((CACHE_CONTROL, cache_control))
+            .finish()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::header::IF_NONE_MATCH, test::TestRequest};
+
+    #[actix_web::test]
+    async fn test_not_modified_matches_if_none_match() {
+        let etag = map_etag("abc123", "w640-h0-q0.webp");
+        assert_eq!(etag.to_string(), "\"abc123-w640-h0-q0.webp\"");
+
+        let req = TestRequest::get()
+            .insert_header((IF_NONE_MATCH, "\"other\", W/\"abc123-w640-h0-q0.webp\""))
+            .to_http_request();
+        let res = not_modified(&req, &etag, immutable()).unwrap();
+        assert_eq!(res.status(), 304);
+        assert_eq!(
+            res.headers().get(ETAG).unwrap(),
+            "\"abc123-w640-h0-q0.webp\""
+        );
+
+        let req = TestRequest::get()
+            .insert_header((IF_NONE_MATCH, "\"abc123\""))
+            .to_http_request();
+        assert!(not_modified(&req, &etag, immutable()).is_none());
+
+        // `*` would answer 304 for ids that match no map
+        let req = TestRequest::get()
+            .insert_header((IF_NONE_MATCH, "*"))
+            .to_http_request();
+        assert!(not_modified(&req, &etag, immutable()).is_none());
+
+        assert!(not_modified(&TestRequest::get().to_http_request(), &etag, immutable()).is_none());
+    }
+}
@@ -1,21 +1,44 @@
+use crate::api::ApiError;
 use crate::clients::meilisearch::meilisearch_index;
-use crate::docs::serve_markdown_file;
-use actix_web::{Error, HttpResponse, web};
+use crate::maps::cache::{map_etag, not_modified, revalidate};
+use crate::metrics::observe_meilisearch;
+use crate::utils::markdown::markdown_to_html;
+use actix_web::{
+    Error, HttpRequest, HttpResponse,
+    http::header::{CACHE_CONTROL, ETAG},
+    web,
+};
+use sha2::{Digest, Sha256};
 use shared::types::map_document::MapDocument as MapDoc;
 use shared::utils::root_dir::root_dir;
 use std::path::Path;
-use tracing::debug;
+use tokio::fs;
+use tracing::{debug, error};
 
-pub async fn map_content(id: web::Path<String>) -> Result<HttpResponse, Error> {
+/// The map's notes, rendered from markdown
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/content/{id}",
+    tag = "maps",
+    params(("id" = String, Path, description = "Map id (content hash)")),
+    responses(
+        (status = 200, description = "Rendered notes", body = String, content_type = "text/html"),
+        (status = 304, description = "Notes unchanged since the `If-None-Match` ETag"),
+        (status = 404, description = "No map with this id, or the map has no notes", body = ApiError),
+    )
+)]
+pub async fn map_content(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error> {
     let id = id.into_inner();
     debug!("Request for map detail with id: {}", id);
     let index = meilisearch_index("maps")?;
 
     debug!("Fetching document with id: {}", id);
-    let doc: MapDoc = index.get_document::<MapDoc>(&id).await.map_err(|e| {
-        debug!("Document not found: {}", e);
-        actix_web::error::ErrorNotFound(e)
-    })?;
+    let doc: MapDoc = observe_meilisearch("get_document", index.get_document::<MapDoc>(&id))
+        .await
+        .map_err(|e| {
+            debug!("Document not found: {}", e);
+            ApiError::not_found(e)
+        })?;
     if let Some(content) = doc.content.clone() {
         debug!("Document found: {}", doc.name);
 
@@ -24,13 +47,29 @@ pub async fn map_content(id: web::Path<String>) -> Result<HttpResponse, Error> {
         let content_path = Path::new(&content_full_path);
         debug!("Content path: {:?}", content_path);
         if content_path.exists() {
-            serve_markdown_file(content_path).await
+            let md = fs::read_to_string(content_path).await.map_err(|e| {
+                error!("Failed to read {}: {}", content_path.display(), e);
+                ApiError::internal("Failed to read markdown file")
+            })?;
+
+            // Notes are edited independently of the map, so the URL is not
+            // content-addressed; validate against a hash of the notes instead
+            let etag = map_etag(&id, &format!("{:x}", Sha256::digest(md.as_bytes())));
+            if let Some(res) = not_modified(&req, &etag, revalidate()) {
+                return Ok(res);
+            }
+
+            Ok(HttpResponse::Ok()
+                .content_type("text/html; charset=utf-8")
+                .insert_header((CACHE_CONTROL, revalidate()))
+                .insert_header((ETAG, etag))
+                .body(markdown_to_html(&md)))
         } else {
             debug!("Content file not found: {}", content);
-            Ok(HttpResponse::NotFound().body("Map content not found"))
+            Err(ApiError::not_found("Map content not found").into())
         }
     } else {
         debug!("No content found for document: {}", doc.name);
-        Ok(HttpResponse::NotFound().body("Map content not found"))
+        Err(ApiError::not_found("Map content not found").into())
     }
 }
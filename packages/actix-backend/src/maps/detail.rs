@@ -1,8 +1,21 @@
+use crate::api::ApiError;
 use crate::clients::meilisearch::meilisearch_index;
+use crate::metrics::observe_meilisearch;
 use actix_web::{Error, HttpResponse, web};
 use shared::types::map_document::MapDocument as MapDoc;
 use tracing::debug;
 
+/// A single map's catalog entry
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/{id}",
+    tag = "maps",
+    params(("id" = String, Path, description = "Map id (content hash)")),
+    responses(
+        (status = 200, description = "The map", body = MapDoc),
+        (status = 404, description = "No map with this id", body = ApiError),
+    )
+)]
 pub async fn map_detail(id: web::Path<String>) -> Result<HttpResponse, Error> {
     let id = id.into_inner();
     debug!("Request for map detail with id: {}", id);
@@ -10,10 +23,12 @@ pub async fn map_detail(id: web::Path<String>) -> Result<HttpResponse, Error> {
     let index = meilisearch_index("maps")?;
 
     debug!("Fetching document with id: {}", id);
-    let doc = index.get_document::<MapDoc>(&id).await.map_err(|e| {
-        debug!("Document not found: {}", e);
-        actix_web::error::ErrorNotFound(e)
-    })?;
+    let doc = observe_meilisearch("get_document", index.get_document::<MapDoc>(&id))
+        .await
+        .map_err(|e| {
+            debug!("Document not found: {}", e);
+            ApiError::not_found(e)
+        })?;
 
     debug!("Found map: {}", doc.name);
     Ok(HttpResponse::Ok().json(doc))
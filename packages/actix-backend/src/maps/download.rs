@@ -1,44 +1,32 @@
-use crate::clients::meilisearch::meilisearch_index;
+use crate::api::ApiError;
+use crate::maps::cache::{immutable, map_etag, not_modified};
+use crate::maps::source::{find_map_document, map_file_path};
+use crate::metrics::registry::BYTES_SERVED;
+use actix_files::NamedFile;
 use actix_web::{
-    Error, HttpResponse,
-    error::{ErrorInternalServerError, ErrorNotFound},
-    web,
+    Error, HttpMessage, HttpRequest, HttpResponse, ResponseError,
+    body::{BodySize, BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        StatusCode,
+        header::{
+            CACHE_CONTROL, ContentDisposition, DispositionParam, DispositionType, ETAG, EntityTag,
+            IF_MATCH, IF_RANGE, IfMatch, IfRange, RANGE, TryIntoHeaderValue,
+        },
+    },
+    middleware::Next,
+    mime, web,
 };
-use shared::types::map_document::MapDocument as MapDoc;
-use shared::utils::root_dir::root_dir;
-use std::path::{Path, PathBuf};
-use tokio::fs;
-use tracing::{debug, error};
-
-async fn retrieve_map_document(id: &str) -> Result<MapDoc, Error> {
-    let index = meilisearch_index("maps")?;
-
-    index.get_document::<MapDoc>(id).await.map_err(|e| {
-        debug!("Document metadata not found: {}", e);
-        ErrorNotFound("Map metadata not found")
-    })
-}
-
-async fn construct_file_path(doc: &MapDoc) -> Result<PathBuf, Error> {
-    let root_path = root_dir().map_err(ErrorInternalServerError)?;
-    let file_path = root_path.join(doc.path.trim_start_matches('/'));
-
-    fs::canonicalize(&file_path).await.map_err(|e| {
-        error!(
-            "Failed to canonicalize file path: {}\n{:?}/{}",
-            e, root_path, doc.path
-        );
-        ErrorInternalServerError("Failed to canonicalize file path of request map.")
-    })
-}
+use std::path::Path;
+use tracing::debug;
 
-async fn read_map_file(path: &Path) -> Result<Vec<u8>, Error> {
-    fs::read(path).await.map_err(|e| {
+async fn open_map_file(path: &Path) -> Result<NamedFile, Error> {
+    NamedFile::open_async(path).await.map_err(|e| {
         debug!(
             "Map file not found or unreadable: {}\n\tFile Path: {:?}",
             e, path
         );
-        ErrorNotFound("Map file not found")
+        ApiError::not_found("Map file not found").into()
     })
 }
 
@@ -48,26 +36,246 @@ fn extract_filename(path: &Path) -> &str {
         .unwrap_or("map.dd2vtt")
 }
 
-fn create_download_response(data: Vec<u8>, filename: &str) -> HttpResponse {
-    HttpResponse::Ok()
-        .content_type("application/octet-stream")
-        .append_header((
-            "Content-Disposition",
-            format!("attachment; filename=\"{filename}\""),
-        ))
-        .body(data)
+/// Evaluate `If-Match` and `If-Range` against the map's ETag, then hide them
+/// from `NamedFile`: it only knows its own inode-based ETag, so it would fail
+/// a matching `If-Match` with 412 and never check `If-Range`
+pub async fn download_preconditions(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let etag = map_etag(req.match_info().get("id").unwrap_or_default(), "");
+
+    if let Some(IfMatch::Items(tags)) = req.get_header::<IfMatch>()
+        && !tags.iter().any(|tag| tag.strong_eq(&etag))
+    {
+        let error = ApiError::new(
+            StatusCode::PRECONDITION_FAILED,
+            "Map does not match If-Match",
+        );
+        return Ok(req.into_response(error.error_response()));
+    }
+
+    // Resume only the representation the client has part of; dates are weak
+    // validators, which can't guard a range (RFC 9110 §13.1.5)
+    let range_valid = match req.get_header::<IfRange>() {
+        Some(IfRange::EntityTag(tag)) => tag.strong_eq(&etag),
+        Some(IfRange::Date(_)) => false,
+        None => true,
+    };
+    let headers = req.headers_mut();
+    headers.remove(IF_MATCH);
+    headers.remove(IF_RANGE);
+    if !range_valid {
+        headers.remove(RANGE);
+    }
+
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+/// Stream the file from disk, answering `Range` requests with 206 Partial
+/// Content so interrupted downloads can resume. Expects
+/// [`download_preconditions`] to have handled the conditional headers.
+fn create_download_response(
+    req: &HttpRequest,
+    file: NamedFile,
+    filename: &str,
+    etag: &EntityTag,
+) -> HttpResponse {
+    // The file's own ETag is inode based; ours is derived from its content
+    let mut res = file
+        .use_etag(false)
+        .set_content_type(mime::APPLICATION_OCTET_STREAM)
+        .set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename.to_string())],
+        })
+        .into_response(req);
+
+    let headers = res.headers_mut();
+    if let Ok(value) = immutable().try_into_value() {
+        headers.insert(CACHE_CONTROL, value);
+    }
+    if let Ok(value) = etag.to_string().parse() {
+        headers.insert(ETAG, value);
+    }
+    res
 }
 
-pub async fn download_map(id: web::Path<String>) -> Result<HttpResponse, Error> {
+/// The original `.dd2vtt` file, with `Range`, `If-Range` and `If-Match` support
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/download/{id}",
+    tag = "maps",
+    params(("id" = String, Path, description = "Map id (content hash)")),
+    responses(
+        (status = 200, description = "The map file", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 206, description = "The requested byte range", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 404, description = "No map with this id", body = ApiError),
+        (status = 412, description = "`If-Match` names a different ETag", body = ApiError),
+        (status = 416, description = "Range not satisfiable", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn download_map(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error> {
     let id = id.into_inner();
     debug!("Request for map download with id: {}", id);
 
-    let doc = retrieve_map_document(&id).await?;
+    // The id is the file's SHA-256, so it is the download's strong validator
+    let etag = map_etag(&id, "");
+    if let Some(res) = not_modified(&req, &etag, immutable()) {
+        return Ok(res);
+    }
+
+    let doc = find_map_document(&id).await?;
     debug!("Found map metadata: {:?}", &doc);
 
-    let canonical_path = construct_file_path(&doc).await?;
-    let data = read_map_file(&canonical_path).await?;
+    let canonical_path = map_file_path(&doc).await?;
+    let file = open_map_file(&canonical_path).await?;
     let filename = extract_filename(&canonical_path);
 
-    Ok(create_download_response(data, filename))
+    let res = create_download_response(&req, file, filename, &etag);
+    if let BodySize::Sized(len) = res.body().size() {
+        BYTES_SERVED
+            .with_label_values(&["download_map"])
+            .inc_by(len);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{API_V1, error_model};
+    use actix_web::{
+        App,
+        body::to_bytes,
+        http::header::{CONTENT_DISPOSITION, CONTENT_RANGE},
+        middleware::from_fn,
+        test::{self, TestRequest},
+    };
+    use std::path::PathBuf;
+
+    /// A map file in a directory of its own, so parallel tests don't
+    /// truncate each other's
+    fn map_file(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("download-{test}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("map.dd2vtt");
+        std::fs::write(&path, b"0123456789").unwrap();
+        path
+    }
+
+    fn remove(path: &Path) {
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    async fn download(path: &Path, req: &HttpRequest) -> HttpResponse {
+        let file = open_map_file(path).await.unwrap();
+        create_download_response(req, file, "map.dd2vtt", &map_etag("abc", ""))
+    }
+
+    #[actix_web::test]
+    async fn test_download_serves_full_file() {
+        let path = map_file("full");
+        let res = download(&path, &TestRequest::get().to_http_request()).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers().get(ETAG).unwrap(), "\"abc\"");
+        assert_eq!(
+            res.headers().get(CONTENT_DISPOSITION).unwrap(),
+            "attachment; filename=\"map.dd2vtt\""
+        );
+        assert_eq!(to_bytes(res.into_body()).await.unwrap(), "0123456789");
+        remove(&path);
+    }
+
+    #[actix_web::test]
+    async fn test_download_serves_range() {
+        let path = map_file("range");
+        let ranged = |range: &str| {
+            TestRequest::get()
+                .insert_header((RANGE, range))
+                .to_http_request()
+        };
+
+        let res = download(&path, &ranged("bytes=4-")).await;
+        assert_eq!(res.status(), 206);
+        assert_eq!(res.headers().get(CONTENT_RANGE).unwrap(), "bytes 4-9/10");
+        assert_eq!(to_bytes(res.into_body()).await.unwrap(), "456789");
+
+        let res = download(&path, &ranged("bytes=20-")).await;
+        assert_eq!(res.status(), 416);
+        remove(&path);
+    }
+
+    #[actix_web::test]
+    async fn test_preconditions_use_map_etag() {
+        let path = map_file("preconditions");
+        let served = path.clone();
+        let app = test::init_service(
+            App::new().service(
+                web::resource("/download/{id}")
+                    .wrap(from_fn(download_preconditions))
+                    .route(web::get().to(move |req: HttpRequest| {
+                        let path = served.clone();
+                        async move { download(&path, &req).await }
+                    })),
+            ),
+        )
+        .await;
+        let call = |headers: &[(&'static str, &'static str)]| {
+            let mut req = TestRequest::get().uri("/download/abc");
+            for header in headers {
+                req = req.insert_header(*header);
+            }
+            test::call_service(&app, req.to_request())
+        };
+
+        assert_eq!(call(&[("if-match", "\"abc\"")]).await.status(), 200);
+        assert_eq!(call(&[("if-match", "\"other\"")]).await.status(), 412);
+
+        let res = call(&[("if-range", "\"abc\""), ("range", "bytes=4-")]).await;
+        assert_eq!(res.status(), 206);
+        let res = call(&[("if-range", "\"stale\""), ("range", "bytes=4-")]).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(test::read_body(res).await, "0123456789");
+        remove(&path);
+    }
+
+    #[actix_web::test]
+    async fn test_error_model_keeps_range_headers() {
+        let path = map_file("error-model");
+        let served = path.clone();
+        let app = test::init_service(
+            App::new().service(
+                web::scope(API_V1).wrap(from_fn(error_model)).service(
+                    web::resource("/maps/download/{id}")
+                        .wrap(from_fn(download_preconditions))
+                        .route(web::get().to(move |req: HttpRequest| {
+                            let path = served.clone();
+                            async move { download(&path, &req).await }
+                        })),
+                ),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/api/v1/maps/download/abc")
+            .insert_header((RANGE, "bytes=20-"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), 416);
+        assert_eq!(res.headers().get(CONTENT_RANGE).unwrap(), "bytes */10");
+        let body: serde_json::Value = test::read_body_json(res).await;
+        assert_eq!(body["code"], "range_not_satisfiable");
+
+        let req = TestRequest::get()
+            .uri("/api/v1/maps/download/abc")
+            .insert_header((IF_MATCH, "\"other\""))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["code"], "precondition_failed");
+        remove(&path);
+    }
 }
@@ -0,0 +1,194 @@
+use crate::maps::variant_cache;
+use crate::utils::folders::{thumbnails_dir, tiles_dir, variants_dir};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::env;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+
+/// What to do with derived assets whose source map is gone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GcMode {
+    #[default]
+    Delete,
+    /// Report what would be removed without touching anything
+    DryRun,
+    Off,
+}
+
+impl GcMode {
+    /// Mode configured with `ASSET_GC` (`delete`, `dry-run` or `off`)
+    pub fn from_env() -> Self {
+        env::var("ASSET_GC")
+            .ok()
+            .and_then(|value| {
+                value
+                    .parse()
+                    .inspect_err(|e| warn!("⚠️  Ignoring ASSET_GC: {}", e))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for GcMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "delete" | "on" | "true" | "1" => Ok(GcMode::Delete),
+            "dry-run" | "dry_run" | "dryrun" => Ok(GcMode::DryRun),
+            "off" | "false" | "0" => Ok(GcMode::Off),
+            other => Err(format!("Unknown garbage collection mode: {other}")),
+        }
+    }
+}
+
+/// Orphaned entries found in one asset directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GcStats {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// Result of a garbage collection pass, kept in the rebuild result
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GcSummary {
+    pub dry_run: bool,
+    pub thumbnails: GcStats,
+    pub tiles: GcStats,
+    pub variants: GcStats,
+    pub reclaimed_bytes: u64,
+}
+
+fn disk_usage(path: &Path) -> io::Result<u64> {
+    let meta = std::fs::symlink_metadata(path)?;
+    if !meta.is_dir() {
+        return Ok(meta.len());
+    }
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        total += disk_usage(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+/// Remove top-level entries of `dir` not keyed by a live map hash.
+///
+/// Entries are `{hash}` or `{hash}.{suffix}` (staging directories); hidden
+/// files such as the rebuild lock are never touched.
+fn sweep(dir: &Path, live: &HashSet<String>, dry_run: bool) -> io::Result<GcStats> {
+    let mut stats = GcStats::default();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let key = name.split('.').next().unwrap_or_default();
+        if name.starts_with('.') || live.contains(key) {
+            continue;
+        }
+
+        let path = entry.path();
+        let bytes = disk_usage(&path)?;
+        debug!("🗑️  Orphaned asset: {} ({} bytes)", path.display(), bytes);
+        if !dry_run {
+            if entry.file_type()?.is_dir() {
+                std::fs::remove_dir_all(&path)?;
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        stats.entries += 1;
+        stats.bytes += bytes;
+    }
+    Ok(stats)
+}
+
+/// Remove thumbnails, tiles and cached variants of maps that no longer exist
+///
+/// # Errors
+/// Returns an error if an asset directory cannot be read or an entry removed.
+pub fn collect_garbage(live: &HashSet<String>, mode: GcMode) -> io::Result<Option<GcSummary>> {
+    if mode == GcMode::Off {
+        return Ok(None);
+    }
+    let dry_run = mode == GcMode::DryRun;
+
+    let thumbnails = sweep(&thumbnails_dir()?, live, dry_run)?;
+    let tiles = sweep(&tiles_dir()?, live, dry_run)?;
+    let variants = sweep(&variants_dir()?, live, dry_run)?;
+    if !dry_run {
+        variant_cache::record_removal(variants.bytes);
+    }
+    let summary = GcSummary {
+        dry_run,
+        reclaimed_bytes: thumbnails.bytes + tiles.bytes + variants.bytes,
+        thumbnails,
+        tiles,
+        variants,
+    };
+
+    info!(
+        "🧹 {} {} orphaned assets ({} bytes)",
+        if dry_run { "Found" } else { "Removed" },
+        summary.thumbnails.entries + summary.tiles.entries + summary.variants.entries,
+        summary.reclaimed_bytes
+    );
+    Ok(Some(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = env::temp_dir().join(format!("gc-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("live/0")).unwrap();
+        std::fs::create_dir_all(dir.join("gone/0")).unwrap();
+        std::fs::create_dir_all(dir.join("live.partial")).unwrap();
+        std::fs::write(dir.join("live/0/0.webp"), b"keep").unwrap();
+        std::fs::write(dir.join("gone/0/0.webp"), b"remove").unwrap();
+        std::fs::write(dir.join("legacy.png"), b"old").unwrap();
+        std::fs::write(dir.join(".map_rebuild_lock.json"), b"{}").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_sweep_removes_orphans_only() {
+        let dir = scratch_dir("sweep");
+        let live = HashSet::from(["live".to_string()]);
+
+        let stats = sweep(&dir, &live, false).unwrap();
+        assert_eq!(
+            stats,
+            GcStats {
+                entries: 2,
+                bytes: 9
+            }
+        );
+        assert!(dir.join("live/0/0.webp").exists());
+        assert!(dir.join("live.partial").exists());
+        assert!(dir.join(".map_rebuild_lock.json").exists());
+        assert!(!dir.join("gone").exists());
+        assert!(!dir.join("legacy.png").exists());
+    }
+
+    #[test]
+    fn test_dry_run_keeps_files() {
+        let dir = scratch_dir("dry-run");
+        let stats = sweep(&dir, &HashSet::new(), true).unwrap();
+        assert_eq!(stats.entries, 4);
+        assert!(dir.join("gone/0/0.webp").exists());
+    }
+
+    #[test]
+    fn test_gc_mode_parses() {
+        assert_eq!("dry-run".parse(), Ok(GcMode::DryRun));
+        assert_eq!("OFF".parse(), Ok(GcMode::Off));
+        assert!("sometimes".parse::<GcMode>().is_err());
+    }
+}
@@ -0,0 +1,331 @@
+use crate::api::ApiError;
+use crate::maps::cache::{immutable, map_etag, not_modified};
+use crate::maps::single_flight::SingleFlight;
+use crate::maps::source::{find_map_document, map_file_path};
+use crate::maps::variant_cache;
+use crate::metrics::registry::BYTES_SERVED;
+use crate::utils::folders::variants_dir;
+use actix_web::{
+    Error, HttpRequest, HttpResponse,
+    http::header::{ACCEPT, CACHE_CONTROL, ETAG, HeaderValue, VARY},
+    web,
+};
+use serde::Deserialize;
+use shared::render::grid::{GridStyle, parse_hex_color};
+use shared::render::overlay::OverlayOptions;
+use shared::render::variant::{DEFAULT_QUALITY, OutputFormat, VariantSpec, render_map_variant};
+use shared::types::dd2vtt::DD2VTTFile;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use tokio::fs;
+use tracing::{debug, error, info, instrument};
+use utoipa::IntoParams;
+
+/// Distinguishes concurrent writers of the same variant
+static WRITE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// One render at a time per variant
+pub(crate) static RENDERS: LazyLock<SingleFlight> = LazyLock::new(SingleFlight::default);
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImageQuery {
+    /// Maximum width in pixels, rounded up to the next standard edge
+    /// (64, 128, 256, ... 8192)
+    w: Option<u32>,
+    /// Maximum height in pixels, rounded like `w`
+    h: Option<u32>,
+    /// `webp`, `avif`, `png` or `jpeg`; negotiated from `Accept` when omitted
+    format: Option<String>,
+    /// Encoder quality for lossy formats (10-100, rounded to a multiple of 10)
+    quality: Option<u8>,
+}
+
+/// Overlay options shared by the map image endpoints:
+/// `?grid=1&grid_color=ff0000&grid_opacity=0.5&grid_width=2&grid_labels=1`
+/// burns in a grid, `?overlay=all` or `?overlay=walls,objects,doors,lights`
+/// draws the map's geometry for review
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OverlayQuery {
+    /// Geometry to draw: `all` or a list of `walls`, `objects`, `doors`, `lights`
+    overlay: Option<String>,
+    /// Burn in the grid when `1`, `true`, `yes` or `on`
+    grid: Option<String>,
+    /// Grid line color as hex, e.g. `ff0000`
+    grid_color: Option<String>,
+    /// Grid line opacity from 0 to 1
+    grid_opacity: Option<f32>,
+    /// Grid line width in pixels
+    grid_width: Option<u32>,
+    /// Label grid columns and rows when truthy
+    grid_labels: Option<String>,
+}
+
+fn is_truthy(value: Option<&str>) -> bool {
+    matches!(value, Some("1" | "true" | "yes" | "on"))
+}
+
+impl OverlayQuery {
+    /// Requested geometry layers, if any
+    pub fn layers(&self) -> Result<Option<OverlayOptions>, Error> {
+        self.overlay
+            .as_deref()
+            .filter(|value| !matches!(*value, "0" | "false"))
+            .map(OverlayOptions::parse)
+            .transpose()
+            .map_err(|e| ApiError::bad_request(e).into())
+    }
+
+    /// Requested grid style, if the grid is enabled
+    pub fn style(&self) -> Result<Option<GridStyle>, Error> {
+        if !is_truthy(self.grid.as_deref()) {
+            return Ok(None);
+        }
+        let defaults = GridStyle::default();
+        let color = match self.grid_color.as_deref() {
+            Some(value) => parse_hex_color(value)
+                .ok_or_else(|| ApiError::bad_request(format!("Invalid grid color: {value}")))?,
+            None => defaults.color,
+        };
+
+        Ok(Some(
+            GridStyle {
+                color,
+                opacity: self.grid_opacity.unwrap_or(defaults.opacity),
+                line_width: self.grid_width.unwrap_or(defaults.line_width),
+                labels: is_truthy(self.grid_labels.as_deref()),
+            }
+            .clamped(),
+        ))
+    }
+}
+
+/// Resolve the requested variant, negotiating the format from `Accept` when
+/// the query does not name one. Sizes, quality and grid style are quantized
+/// so the variant cache stays bounded.
+fn variant_spec(
+    query: ImageQuery,
+    overlays: &OverlayQuery,
+    accept: &str,
+) -> Result<VariantSpec, Error> {
+    let format = match query.format.as_deref() {
+        Some(name) => name
+            .parse::<OutputFormat>()
+            .map_err(ApiError::bad_request)?,
+        None => OutputFormat::negotiate(accept),
+    };
+
+    Ok(VariantSpec {
+        width: query.w,
+        height: query.h,
+        format,
+        quality: query.quality.unwrap_or(DEFAULT_QUALITY),
+        grid: overlays.style()?,
+        overlay: overlays.layers()?,
+    }
+    .quantized())
+}
+
+pub(crate) fn variant_dir(id: &str) -> Result<PathBuf, Error> {
+    // Ids are hex content hashes; reject anything that could escape the cache
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(ApiError::not_found("Map not found").into());
+    }
+    Ok(variants_dir().map_err(ApiError::internal)?.join(id))
+}
+
+/// Write a rendered asset into the cache, never exposing a partial file
+pub(crate) fn store_variant(target: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let staging = target.with_extension(format!(
+        "partial-{}",
+        WRITE_SEQ.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&staging, bytes)?;
+    std::fs::rename(&staging, target)
+}
+
+/// The cached asset `key` of map `id`, rendered from the map file by `render`
+/// on a miss. Only one render runs at a time per asset; requests arriving
+/// meanwhile wait and then read the result from the cache.
+pub(crate) async fn cached_variant<F>(id: &str, key: &str, render: F) -> Result<Vec<u8>, Error>
+where
+    F: FnOnce(&Path) -> anyhow::Result<Vec<u8>> + Send + 'static,
+{
+    let path = variant_dir(id)?.join(key);
+    if let Ok(data) = fs::read(&path).await {
+        variant_cache::touch(path);
+        return Ok(data);
+    }
+
+    let doc = find_map_document(id).await?;
+    let source = map_file_path(&doc).await?;
+    let _flight = RENDERS.lock(&path.to_string_lossy()).await;
+
+    // Another request may have rendered it while we waited
+    if let Ok(data) = fs::read(&path).await {
+        return Ok(data);
+    }
+    let rendered = web::block(move || {
+        let bytes = render(&source)?;
+        store_variant(&path, &bytes)?;
+        variant_cache::record_write(bytes.len() as u64);
+        anyhow::Ok(bytes)
+    })
+    .await?;
+    rendered.map_err(|e| {
+        error!("Failed to render {} for map {}: {:?}", key, id, e);
+        ApiError::internal("Failed to render map image").into()
+    })
+}
+
+/// Decode the map and render the variant
+#[instrument(level = "info", skip_all, fields(file = %source.display(), variant = %spec.cache_key()))]
+fn build_variant(source: &Path, spec: &VariantSpec) -> anyhow::Result<Vec<u8>> {
+    let start = Instant::now();
+    let bytes = render_map_variant(&DD2VTTFile::load(source)?, spec)?;
+
+    info!(
+        "🖼️ Rendered {} variant ({} bytes) in {:?}",
+        spec.format,
+        bytes.len(),
+        start.elapsed()
+    );
+    Ok(bytes)
+}
+
+/// Serve a resized/transcoded rendition of a map image at
+/// `/api/maps/image/{id}?w=&h=&format=webp|avif|png|jpeg&quality=`, with an
+/// optional grid overlay (see [`OverlayQuery`])
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/image/{id}",
+    tag = "images",
+    params(("id" = String, Path, description = "Map id (content hash)"), ImageQuery, OverlayQuery),
+    responses(
+        (status = 200, description = "The rendered image", content(
+            (Vec<u8> = "image/webp"),
+            (Vec<u8> = "image/avif"),
+            (Vec<u8> = "image/png"),
+            (Vec<u8> = "image/jpeg"),
+        )),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Invalid format, size or overlay option", body = ApiError),
+        (status = 404, description = "No map with this id", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn map_image(
+    req: HttpRequest,
+    id: web::Path<String>,
+    query: web::Query<ImageQuery>,
+    overlays: web::Query<OverlayQuery>,
+) -> Result<HttpResponse, Error> {
+    let id = id.into_inner();
+    let accept = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let spec = variant_spec(query.into_inner(), &overlays, accept)?;
+    debug!("Request for image variant {:?} of map {}", spec, id);
+
+    let etag = map_etag(&id, &spec.cache_key());
+    if let Some(mut res) = not_modified(&req, &etag, immutable()) {
+        res.headers_mut()
+            .insert(VARY, HeaderValue::from_static("Accept"));
+        return Ok(res);
+    }
+
+    let render_spec = spec.clone();
+    let data = cached_variant(&id, &spec.cache_key(), move |source| {
+        build_variant(source, &render_spec)
+    })
+    .await?;
+
+    BYTES_SERVED
+        .with_label_values(&["map_image"])
+        .inc_by(data.len() as u64);
+
+    Ok(HttpResponse::Ok()
+        .content_type(spec.format.mime_type())
+        .insert_header((CACHE_CONTROL, immutable()))
+        .insert_header((ETAG, etag))
+        .insert_header((VARY, "Accept"))
+        .body(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_explicit_format_overrides_accept() {
+        let query = ImageQuery {
+            format: Some("png".to_string()),
+            ..ImageQuery::default()
+        };
+        let spec = variant_spec(query, &OverlayQuery::default(), "image/avif,image/webp").unwrap();
+        assert_eq!(spec.format, OutputFormat::Png);
+
+        let spec = variant_spec(
+            ImageQuery::default(),
+            &OverlayQuery::default(),
+            "image/webp,*/*",
+        )
+        .unwrap();
+        assert_eq!(spec.format, OutputFormat::Webp);
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_variant_requests_are_rejected() {
+        let query = ImageQuery {
+            format: Some("gif".to_string()),
+            ..ImageQuery::default()
+        };
+        assert!(variant_spec(query, &OverlayQuery::default(), "").is_err());
+
+        let grid = OverlayQuery {
+            grid: Some("1".to_string()),
+            grid_color: Some("red".to_string()),
+            ..OverlayQuery::default()
+        };
+        assert!(grid.style().is_err());
+
+        let overlay = OverlayQuery {
+            overlay: Some("walls,furniture".to_string()),
+            ..OverlayQuery::default()
+        };
+        assert!(overlay.layers().is_err());
+        assert!(variant_dir("../etc").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_variant_size_and_quality_are_quantized() {
+        let query = ImageQuery {
+            w: Some(100_000),
+            h: Some(0),
+            quality: Some(0),
+            ..ImageQuery::default()
+        };
+        let grid = OverlayQuery {
+            grid: Some("true".to_string()),
+            grid_opacity: Some(3.0),
+            grid_width: Some(0),
+            ..OverlayQuery::default()
+        };
+        let spec = variant_spec(query, &grid, "").unwrap();
+        assert_eq!(spec.width, Some(8192));
+        assert_eq!(spec.height, None);
+        assert_eq!(spec.quality, 10);
+
+        let style = spec.grid.unwrap();
+        assert!((style.opacity - 1.0).abs() < f32::EPSILON);
+        assert_eq!(style.line_width, 1);
+    }
+}
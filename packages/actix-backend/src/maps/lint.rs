@@ -0,0 +1,60 @@
+use crate::api::ApiError;
+use crate::maps::source::{find_map_document, map_file_path};
+use actix_web::{Error, HttpResponse, web};
+use serde::{Deserialize, Serialize};
+use shared::types::dd2vtt::DD2VTTFile;
+use shared::validate::{LintIssue, LintReport, lint_map};
+use tracing::{debug, error};
+use utoipa::ToSchema;
+
+/// Lint findings for one map, as returned by the API and kept in rebuild results
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MapLint {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<LintIssue>,
+}
+
+impl MapLint {
+    #[must_use]
+    pub fn new(id: String, name: String, path: String, report: LintReport) -> Self {
+        Self {
+            id,
+            name,
+            path,
+            errors: report.errors(),
+            warnings: report.warnings(),
+            issues: report.issues,
+        }
+    }
+}
+
+/// Validate a map's UVTT data: `/api/maps/{id}/lint`
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/{id}/lint",
+    tag = "maps",
+    params(("id" = String, Path, description = "Map id (content hash)")),
+    responses(
+        (status = 200, description = "Lint findings, errors first", body = MapLint),
+        (status = 404, description = "No map with this id", body = ApiError),
+    )
+)]
+pub async fn map_lint(id: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = id.into_inner();
+    debug!("Request for lint of map {}", id);
+
+    let doc = find_map_document(&id).await?;
+    let source = map_file_path(&doc).await?;
+    let report = web::block(move || DD2VTTFile::load(&source).map(|map| lint_map(&map)))
+        .await?
+        .map_err(|e| {
+            error!("Failed to lint map {}: {:?}", id, e);
+            ApiError::internal("Failed to read map file")
+        })?;
+
+    Ok(HttpResponse::Ok().json(MapLint::new(doc.id, doc.name, doc.path, report)))
+}
@@ -1,8 +1,17 @@
 pub mod all;
+pub mod cache;
 pub mod detail;
 pub mod download;
+pub mod gc;
+pub mod image;
+pub mod lint;
+pub mod print;
 pub mod rebuild;
+pub(crate) mod single_flight;
+pub mod source;
 pub mod tiled;
+pub mod tiles;
+pub(crate) mod variant_cache;
 
 pub mod content;
 
@@ -10,5 +19,11 @@ pub use all::maps_all;
 pub use content::map_content;
 pub use detail::map_detail;
 pub use download::download_map;
-pub use rebuild::{clear_rebuild_lock, maps_rebuild, rebuild_maps_init, rebuild_status};
+pub use image::map_image;
+pub use lint::map_lint;
+pub use print::print_map;
+pub use rebuild::{
+    clear_rebuild_lock, has_completed_rebuild, maps_rebuild, rebuild_maps_init, rebuild_status,
+};
 pub use tiled::tiled_map;
+pub use tiles::{map_tile, tile_pyramid};
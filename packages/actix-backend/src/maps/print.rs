@@ -0,0 +1,187 @@
+use crate::api::ApiError;
+use crate::maps::cache::{immutable, map_etag, not_modified};
+use crate::maps::image::{RENDERS, store_variant, variant_dir};
+use crate::maps::source::{find_map_document, map_file_path};
+use crate::maps::variant_cache;
+use crate::metrics::registry::BYTES_SERVED;
+use actix_web::{
+    Error, HttpRequest, HttpResponse,
+    http::header::{CACHE_CONTROL, ETAG},
+    web,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use shared::render::print::{PaperSize, PrintOptions, SquareSize, render_print_pdf};
+use shared::types::dd2vtt::DD2VTTFile;
+use shared::utils::casing::kebabcase;
+use std::path::Path;
+use std::time::Instant;
+use tokio::fs;
+use tracing::{debug, error, info, instrument};
+use utoipa::IntoParams;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PrintQuery {
+    /// `letter` (default) or `a4`
+    paper: Option<String>,
+    /// Printed size of one grid square: `1in` (default) or `25mm`
+    square: Option<String>,
+}
+
+fn print_options(query: &PrintQuery) -> Result<PrintOptions, Error> {
+    let mut options = PrintOptions::default();
+    if let Some(paper) = &query.paper {
+        options.paper = paper.parse::<PaperSize>().map_err(ApiError::bad_request)?;
+    }
+    if let Some(square) = &query.square {
+        options.square = square
+            .parse::<SquareSize>()
+            .map_err(ApiError::bad_request)?;
+    }
+    Ok(options)
+}
+
+/// Cache file and ETag key; the map name is printed in every page header, so
+/// renaming a map must not serve the old PDF
+fn print_key(name: &str, options: &PrintOptions) -> String {
+    let name_hash = format!("{:x}", Sha256::digest(name.as_bytes()));
+    format!("{}-{}", &name_hash[..12], options.cache_key())
+}
+
+#[instrument(level = "info", skip_all, fields(file = %source.display(), paper = %options.paper))]
+fn build_print(
+    source: &Path,
+    target: &Path,
+    name: &str,
+    options: &PrintOptions,
+) -> anyhow::Result<Vec<u8>> {
+    let start = Instant::now();
+    let pdf = render_print_pdf(&DD2VTTFile::load(source)?, name, options)?;
+    store_variant(target, &pdf)?;
+    variant_cache::record_write(pdf.len() as u64);
+
+    info!(
+        "🖨️ Rendered print PDF ({} bytes) in {:?}",
+        pdf.len(),
+        start.elapsed()
+    );
+    Ok(pdf)
+}
+
+/// Printable PDF of a map at one grid square per inch (or 25 mm), tiled
+/// across pages: `/api/maps/print/{id}.pdf?paper=letter|a4&square=1in|25mm`
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/print/{id}.pdf",
+    tag = "images",
+    params(("id" = String, Path, description = "Map id (content hash)"), PrintQuery),
+    responses(
+        (status = 200, description = "Printable PDF", body = Vec<u8>, content_type = "application/pdf"),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Invalid paper or square size", body = ApiError),
+        (status = 404, description = "No map with this id", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn print_map(
+    req: HttpRequest,
+    id: web::Path<String>,
+    query: web::Query<PrintQuery>,
+) -> Result<HttpResponse, Error> {
+    let id = id.into_inner();
+    let options = print_options(&query)?;
+    debug!("Request for print PDF of map {} ({:?})", id, options);
+
+    let doc = find_map_document(&id).await?;
+    let key = print_key(&doc.name, &options);
+    let etag = map_etag(&id, &key);
+    if let Some(res) = not_modified(&req, &etag, immutable()) {
+        return Ok(res);
+    }
+
+    let path = variant_dir(&id)?.join(key);
+    let data = if let Ok(data) = fs::read(&path).await {
+        variant_cache::touch(path);
+        data
+    } else {
+        let source = map_file_path(&doc).await?;
+        let _flight = RENDERS.lock(&path.to_string_lossy()).await;
+
+        // Another request may have rendered it while we waited
+        if let Ok(data) = fs::read(&path).await {
+            data
+        } else {
+            let name = doc.name.clone();
+            web::block(move || build_print(&source, &path, &name, &options))
+                .await?
+                .map_err(|e| {
+                    error!("Failed to render print PDF for {}: {:?}", id, e);
+                    ApiError::internal("Failed to render printable map")
+                })?
+        }
+    };
+
+    BYTES_SERVED
+        .with_label_values(&["print_map"])
+        .inc_by(data.len() as u64);
+
+    let stem: String = kebabcase(&doc.name)
+        .chars()
+        .filter(|c| !matches!(c, '"' | '\\' | '/'))
+        .collect();
+    Ok(HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header((CACHE_CONTROL, immutable()))
+        .insert_header((ETAG, etag))
+        .append_header((
+            "Content-Disposition",
+            format!("inline; filename=\"{stem}-{}.pdf\"", options.paper),
+        ))
+        .body(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, test};
+
+    #[actix_web::test]
+    async fn test_print_route_strips_extension() {
+        let app = test::init_service(App::new().route(
+            "/print/{id}.pdf",
+            web::get().to(|id: web::Path<String>| async move { id.into_inner() }),
+        ))
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/print/abc123.pdf?paper=a4")
+            .to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "abc123");
+    }
+
+    #[actix_web::test]
+    async fn test_print_options_from_query() {
+        let query = PrintQuery {
+            paper: Some("a4".to_string()),
+            square: Some("25mm".to_string()),
+        };
+        let options = print_options(&query).unwrap();
+        assert_eq!(options.paper, PaperSize::A4);
+        assert_eq!(options.square, SquareSize::Millimeters25);
+
+        let query = PrintQuery {
+            paper: Some("legal".to_string()),
+            square: None,
+        };
+        assert!(print_options(&query).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_print_key_changes_with_map_name() {
+        let options = PrintOptions::default();
+        assert_eq!(print_key("Crypt", &options), print_key("Crypt", &options));
+        assert_ne!(print_key("Crypt", &options), print_key("Tomb", &options));
+        assert!(print_key("Crypt", &options).ends_with(&options.cache_key()));
+    }
+}
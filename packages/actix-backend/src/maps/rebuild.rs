@@ -1,26 +1,68 @@
-use actix_web::{HttpResponse, error::ErrorInternalServerError};
+use crate::api::ApiError;
+use actix_web::{HttpResponse, web};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::env;
 use std::{
     fs::{File, OpenOptions},
     io::{Read, Write},
     path::{Path, PathBuf},
+    sync::{
+        LazyLock,
+        atomic::{AtomicBool, Ordering},
+    },
     time::Instant,
 };
 use tokio::task;
 use tracing::{debug, error, info, instrument, warn};
-
+use utoipa::{IntoParams, ToSchema};
+
+use crate::hooks::admin_auth::AdminAuthenticated;
+use crate::maps::gc::{GcMode, GcSummary, collect_garbage};
+use crate::maps::image::store_variant;
+use crate::maps::lint::MapLint;
+use crate::metrics::observe_meilisearch;
+use crate::metrics::registry::{MAP_PROCESSING_FAILURES, MAPS_INDEXED, THUMBNAIL_CACHE};
 use crate::utils::folders::thumbnails_dir;
 use crate::utils::repo::{get_sha, update_repo};
 use glob::glob;
 use meilisearch_sdk::client::Client;
+use shared::render::thumbnail::{
+    DEFAULT_THUMBNAIL_SIZES, ThumbnailSize, parse_thumbnail_sizes, render_thumbnail,
+};
 use shared::types::map_document::MapDocument as MapDoc;
 use shared::types::{dd2vtt::DD2VTTFile, map_reference::MapReference};
 use shared::utils::casing::titlecase;
 use shared::utils::root_dir::{maps_dir, root_dir};
+use shared::validate::{LintReport, lint_map};
 
 const TASK_BATCH_SIZE: usize = 10;
 
+/// Thumbnails generated for every map, from `THUMBNAIL_SIZES`
+/// (e.g. `card=480x270:webp,list=160x90:webp,social=1200x630:png`)
+static THUMBNAIL_SIZES: LazyLock<Vec<ThumbnailSize>> = LazyLock::new(|| {
+    let configured = env::var("THUMBNAIL_SIZES").ok();
+    configured
+        .as_deref()
+        .map(parse_thumbnail_sizes)
+        .and_then(|parsed| {
+            parsed
+                .inspect_err(|e| warn!("⚠️  Ignoring THUMBNAIL_SIZES: {}", e))
+                .ok()
+        })
+        .unwrap_or_else(|| {
+            parse_thumbnail_sizes(DEFAULT_THUMBNAIL_SIZES).expect("default thumbnail sizes")
+        })
+});
+
+/// Set once any rebuild in this process has run to completion.
+static REBUILD_COMPLETED: AtomicBool = AtomicBool::new(false);
+
+/// Whether at least one rebuild has completed since startup.
+pub fn has_completed_rebuild() -> bool {
+    REBUILD_COMPLETED.load(Ordering::Acquire)
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "status", rename_all = "snake_case")]
 enum BuildLock {
@@ -32,9 +74,80 @@ enum BuildLock {
     Complete {
         maps: usize,
         sha: String,
+        #[serde(default)]
+        lint: LintSummary,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gc: Option<GcSummary>,
+    },
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RebuildQuery {
+    /// Overrides `ASSET_GC` for this rebuild: `delete`, `dry-run` or `off`
+    gc: Option<String>,
+}
+
+/// Progress or result of the latest rebuild, as reported by the API
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RebuildStatus {
+    /// No rebuild has run in this data directory
+    Idle { container_info: String },
+    Processing {
+        processed: usize,
+        total: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sha: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        container_info: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        progress_percentage: Option<usize>,
+    },
+    Complete {
+        maps: usize,
+        sha: String,
+        lint: LintSummary,
+        gc: Option<GcSummary>,
+        container_info: String,
     },
 }
 
+impl RebuildStatus {
+    /// A rebuild that has just been started or is already running
+    fn started(processed: usize, total: usize) -> Self {
+        RebuildStatus::Processing {
+            processed,
+            total,
+            sha: None,
+            container_info: None,
+            progress_percentage: None,
+        }
+    }
+}
+
+/// Validation results gathered while rebuilding; only maps with findings are listed
+#[derive(Default, Serialize, Deserialize, ToSchema)]
+pub struct LintSummary {
+    clean: usize,
+    errors: usize,
+    warnings: usize,
+    maps: Vec<MapLint>,
+}
+
+impl LintSummary {
+    fn record(&mut self, doc: &MapDoc, report: LintReport) {
+        if report.is_clean() {
+            self.clean += 1;
+            return;
+        }
+        let lint = MapLint::new(doc.id.clone(), doc.name.clone(), doc.path.clone(), report);
+        self.errors += lint.errors;
+        self.warnings += lint.warnings;
+        self.maps.push(lint);
+    }
+}
+
 fn lock_path() -> PathBuf {
     thumbnails_dir().unwrap().join(".map_rebuild_lock.json")
 }
@@ -102,19 +215,19 @@ fn is_lock_stale(_lock_data: &BuildLock) -> bool {
 /// Get container uptime information
 fn get_container_info() -> String {
     // Check container uptime via /proc/1/stat if available
-    if let Ok(stat) = std::fs::read_to_string("/proc/1/stat") {
-        if let Some(start_time) = stat.split_whitespace().nth(21) {
-            let boot_time_result = std::fs::read_to_string("/proc/stat")
-                .unwrap_or_default()
-                .lines()
-                .find(|line| line.starts_with("btime"))
-                .and_then(|line| line.split_whitespace().nth(1))
-                .and_then(|t| t.parse::<u64>().ok());
-
-            if let (Ok(start), Some(_boot_time)) = (start_time.parse::<u64>(), boot_time_result) {
-                let uptime_seconds = start / 100; // Convert from jiffies to seconds (assuming 100 Hz)
-                return format!("Container uptime: ~{uptime_seconds} seconds");
-            }
+    if let Ok(stat) = std::fs::read_to_string("/proc/1/stat")
+        && let Some(start_time) = stat.split_whitespace().nth(21)
+    {
+        let boot_time_result = std::fs::read_to_string("/proc/stat")
+            .unwrap_or_default()
+            .lines()
+            .find(|line| line.starts_with("btime"))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|t| t.parse::<u64>().ok());
+
+        if let (Ok(start), Some(_boot_time)) = (start_time.parse::<u64>(), boot_time_result) {
+            let uptime_seconds = start / 100; // Convert from jiffies to seconds (assuming 100 Hz)
+            return format!("Container uptime: ~{uptime_seconds} seconds");
         }
     }
 
@@ -128,7 +241,8 @@ fn get_container_info() -> String {
 
 // find all .dd2vtt files
 #[instrument(level = "debug")]
-fn find_dd2vtt_paths() -> Result<Vec<PathBuf>, Box<dyn std::error::Error + Send + Sync>> {
+pub(crate) fn find_dd2vtt_paths() -> Result<Vec<PathBuf>, Box<dyn std::error::Error + Send + Sync>>
+{
     let start = Instant::now();
     let base = maps_dir()?;
 
@@ -154,53 +268,87 @@ fn find_dd2vtt_paths() -> Result<Vec<PathBuf>, Box<dyn std::error::Error + Send
     Ok(out)
 }
 
+/// URL of a map's thumbnail in one size
+fn thumbnail_url(hash: &str, size: &ThumbnailSize) -> String {
+    format!("/assets/thumbnails/{hash}/{}", size.file_name())
+}
+
+/// Generate any of the map's thumbnails missing from its hash-keyed directory.
+///
+/// Thumbnails live under the map's content hash, so an edited map gets fresh
+/// ones and the image is only decoded when something is missing.
+fn ensure_thumbnails(
+    dd2vtt: &DD2VTTFile,
+    hash: &str,
+    thumb_dir: &Path,
+) -> Result<(), anyhow::Error> {
+    let dir = thumb_dir.join(hash);
+    let mut image = None;
+
+    for size in THUMBNAIL_SIZES.iter() {
+        let thumb = dir.join(size.file_name());
+        if thumb.exists() {
+            THUMBNAIL_CACHE.with_label_values(&["hit"]).inc();
+            debug!("♻️  Thumbnail already exists: {}", thumb.display());
+            continue;
+        }
+
+        THUMBNAIL_CACHE.with_label_values(&["miss"]).inc();
+        debug!("🖼️  Generating thumbnail: {}", thumb.display());
+        let image = match &mut image {
+            Some(image) => image,
+            None => image.insert(dd2vtt.decode_image()?),
+        };
+        store_variant(&thumb, &render_thumbnail(image, size)?)?;
+        debug!("✅ Thumbnail generated: {}", thumb.display());
+    }
+    Ok(())
+}
+
 // process one file
 #[instrument(level = "debug", fields(file = %path.display()))]
-fn process_one(
+pub(crate) fn process_one(
     path: PathBuf,
-    base: PathBuf,
     thumb_dir: PathBuf,
-) -> Result<MapReference, anyhow::Error> {
+) -> Result<(MapReference, LintReport), anyhow::Error> {
     debug!("🔄 Processing map file: {}", path.display());
-    let dd2vtt = DD2VTTFile::from_path(path.clone());
+    let dd2vtt = DD2VTTFile::load(&path)?;
+    let map_ref = MapReference::from(&dd2vtt);
 
-    let default_path = PathBuf::new();
-    let orig = dd2vtt.path.as_ref().unwrap_or(&default_path);
-    let rel = orig.strip_prefix(&base)?;
-    let mut thumb = thumb_dir.join(rel);
-    thumb.set_extension("png");
+    ensure_thumbnails(&dd2vtt, &map_ref.hash, &thumb_dir)?;
 
-    if let Some(parent) = thumb.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-
-    if thumb.exists() {
-        debug!("♻️  Thumbnail already exists: {}", thumb.display());
-    } else {
-        debug!("🖼️  Generating thumbnail: {}", thumb.display());
-        dd2vtt.clone().export_thumbnail_file(&thumb);
-        debug!("✅ Thumbnail generated: {}", thumb.display());
+    let report = lint_map(&dd2vtt);
+    if !report.is_clean() {
+        debug!(
+            "🔎 Lint found {} errors and {} warnings",
+            report.errors(),
+            report.warnings()
+        );
     }
 
-    let map_ref = MapReference::from(dd2vtt);
     debug!("✅ Processed map: {} ({})", map_ref.name, map_ref.hash);
-    Ok(map_ref)
+    Ok((map_ref, report))
 }
 
 /// Convert `MapReference` to `MapDocument` efficiently
-fn map_ref_to_doc(map_ref: MapReference, base_path: &str) -> MapDoc {
+pub(crate) fn map_ref_to_doc(map_ref: MapReference, base_path: &str) -> MapDoc {
     let path_relative_to_base = map_ref
         .path
         .strip_prefix(base_path)
         .unwrap_or(&map_ref.path);
-    let asset_path = path_relative_to_base.replace(".dd2vtt", ".png");
     let content_path = path_relative_to_base.replace(".dd2vtt", ".md");
 
+    let thumbnails = THUMBNAIL_SIZES
+        .iter()
+        .map(|size| (size.name.clone(), thumbnail_url(&map_ref.hash, size)))
+        .collect();
+
     MapDoc {
+        thumbnail: thumbnail_url(&map_ref.hash, &THUMBNAIL_SIZES[0]),
+        thumbnails,
         id: map_ref.hash,
         name: titlecase(&map_ref.name),
         path: format!("/maps{path_relative_to_base}"),
-        thumbnail: format!("/assets/thumbnails{asset_path}"),
         content: {
             let full_path = format!("{base_path}/{content_path}");
             if Path::new(&full_path).exists() {
@@ -215,7 +363,9 @@ fn map_ref_to_doc(map_ref: MapReference, base_path: &str) -> MapDoc {
 
 /// Core rebuild function that can be called from anywhere
 #[instrument(level = "info")]
-pub async fn rebuild_maps_core() -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+pub async fn rebuild_maps_core(
+    gc_mode: GcMode,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
     let start = Instant::now();
     info!("🚀 Starting map rebuild process");
 
@@ -301,11 +451,14 @@ pub async fn rebuild_maps_core() -> Result<usize, Box<dyn std::error::Error + Se
 
     // Always check if 'maps' index exists, create if missing
     info!("🔍 Checking if 'maps' index exists");
-    if (index.get_stats().await).is_ok() {
+    if observe_meilisearch("get_stats", index.get_stats())
+        .await
+        .is_ok()
+    {
         info!("✅ Index 'maps' already exists")
     } else {
         info!("🏗️  Creating 'maps' index");
-        match client.create_index("maps", Some("id")).await {
+        match observe_meilisearch("create_index", client.create_index("maps", Some("id"))).await {
             Ok(task) => {
                 info!("📋 Index creation task submitted: {}", task.task_uid);
                 let _ = task.wait_for_completion(&client, None, None).await?;
@@ -320,7 +473,7 @@ pub async fn rebuild_maps_core() -> Result<usize, Box<dyn std::error::Error + Se
 
     // Always rebuild documents in streaming batches to minimize memory usage
     info!("�️  Clearing existing documents from search index");
-    index.delete_all_documents().await?;
+    observe_meilisearch("delete_all_documents", index.delete_all_documents()).await?;
     info!("✅ Search index cleared");
 
     info!("🔄 Processing and indexing maps in streaming batches");
@@ -335,6 +488,9 @@ pub async fn rebuild_maps_core() -> Result<usize, Box<dyn std::error::Error + Se
     };
 
     let mut processed = 0;
+    let mut lint = LintSummary::default();
+    let mut live = HashSet::with_capacity(total);
+    let mut failed = 0;
     for (batch_idx, chunk) in paths.chunks(TASK_BATCH_SIZE).enumerate() {
         info!(
             "📊 Processing and indexing batch {}/{} ({} maps)",
@@ -346,28 +502,41 @@ pub async fn rebuild_maps_core() -> Result<usize, Box<dyn std::error::Error + Se
         let handles: Vec<_> = chunk
             .iter()
             .map(|p| {
-                let bd = base.clone();
                 let td = thumb_dir.clone();
                 let p = p.clone();
-                task::spawn_blocking(move || process_one(p, bd, td))
+                task::spawn_blocking(move || process_one(p, td))
             })
             .collect();
 
         let mut batch_docs = Vec::with_capacity(chunk.len());
         for h in handles {
             match h.await {
-                Ok(Ok(map_ref)) => {
+                Ok(Ok((map_ref, report))) => {
                     let doc = map_ref_to_doc(map_ref, &base_as_str);
+                    lint.record(&doc, report);
+                    live.insert(doc.id.clone());
                     batch_docs.push(doc);
                 }
-                Ok(Err(e)) => error!("❌ Processing error: {:?}", e),
-                Err(join_err) => error!("⚠️  Task join error: {:?}", join_err),
+                Ok(Err(e)) => {
+                    failed += 1;
+                    MAP_PROCESSING_FAILURES.inc();
+                    error!("❌ Processing error: {:?}", e);
+                }
+                Err(join_err) => {
+                    failed += 1;
+                    MAP_PROCESSING_FAILURES.inc();
+                    error!("⚠️  Task join error: {:?}", join_err);
+                }
             }
         }
 
         if !batch_docs.is_empty() {
             info!("� Indexing {} documents", batch_docs.len());
-            index.add_documents(&batch_docs, Some("id")).await?;
+            observe_meilisearch(
+                "add_documents",
+                index.add_documents(&batch_docs, Some("id")),
+            )
+            .await?;
             processed += batch_docs.len();
 
             write_lock(
@@ -389,7 +558,40 @@ pub async fn rebuild_maps_core() -> Result<usize, Box<dyn std::error::Error + Se
             total_batches
         );
     }
-    write_lock(&lockfile, &BuildLock::Complete { maps: total, sha })?;
+    if lint.errors + lint.warnings > 0 {
+        warn!(
+            "🔎 Lint found {} errors and {} warnings across {} maps",
+            lint.errors,
+            lint.warnings,
+            lint.maps.len()
+        );
+    }
+
+    // A map that failed to process still exists; its assets are not orphans
+    let gc = if failed > 0 && gc_mode != GcMode::Off {
+        warn!(
+            "⚠️  Skipping asset cleanup: {} maps failed to process",
+            failed
+        );
+        None
+    } else {
+        task::spawn_blocking(move || collect_garbage(&live, gc_mode))
+            .await?
+            .inspect_err(|e| error!("❌ Asset cleanup failed: {}", e))
+            .ok()
+            .flatten()
+    };
+    write_lock(
+        &lockfile,
+        &BuildLock::Complete {
+            maps: total,
+            sha,
+            lint,
+            gc,
+        },
+    )?;
+    REBUILD_COMPLETED.store(true, Ordering::Release);
+    MAPS_INDEXED.set(i64::try_from(processed).unwrap_or(i64::MAX));
     let total_elapsed = start.elapsed();
     info!(
         "🎉 Map rebuild completed successfully: {} maps processed in {:?}",
@@ -439,12 +641,40 @@ pub async fn rebuild_maps_init() -> Result<usize, Box<dyn std::error::Error + Se
     }
 
     // Now proceed with normal rebuild
-    rebuild_maps_core().await
+    rebuild_maps_core(GcMode::from_env()).await
 }
 
-// main handler
-pub async fn maps_rebuild() -> Result<HttpResponse, actix_web::Error> {
-    info!("🌐 Map rebuild requested via HTTP endpoint");
+/// Start a rebuild of the search index and derived assets in the background
+#[utoipa::path(
+    post,
+    path = "/api/v1/maps/rebuild",
+    tag = "rebuild",
+    params(RebuildQuery),
+    security(("admin_token" = ["rebuild"])),
+    responses(
+        (status = 202, description = "Rebuild started", body = RebuildStatus),
+        (status = 200, description = "A rebuild is already running", body = RebuildStatus),
+        (status = 400, description = "Unknown `gc` mode", body = ApiError),
+        (status = 401, description = "Missing admin token", body = ApiError),
+        (status = 403, description = "Invalid or expired admin token, or missing scope", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn maps_rebuild(
+    query: web::Query<RebuildQuery>,
+    admin: web::ReqData<AdminAuthenticated>,
+) -> Result<HttpResponse, actix_web::Error> {
+    info!(
+        "🌐 Map rebuild requested via HTTP endpoint by token {:?}",
+        admin.token
+    );
+    let gc_mode = query
+        .gc
+        .as_deref()
+        .map(str::parse::<GcMode>)
+        .transpose()
+        .map_err(ApiError::bad_request)?
+        .unwrap_or_else(GcMode::from_env);
 
     let lockfile = lock_path();
     info!("🔍 Container info: {}", get_container_info());
@@ -462,18 +692,14 @@ pub async fn maps_rebuild() -> Result<HttpResponse, actix_web::Error> {
                     );
                     if let Err(e) = remove_lock(&lockfile) {
                         error!("❌ Failed to remove stale lock: {}", e);
-                        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                            "error": "Failed to clean up stale lock"
-                        })));
+                        return Err(ApiError::internal("Failed to clean up stale lock").into());
                     }
                 } else {
                     info!(
                         "📊 Rebuild in progress: {}/{} maps processed",
                         processed, total
                     );
-                    return Ok(HttpResponse::Ok().json(serde_json::json!({
-                        "status":"processing","processed":processed,"total":total
-                    })));
+                    return Ok(HttpResponse::Ok().json(RebuildStatus::started(*processed, *total)));
                 }
             }
             BuildLock::Complete { maps, .. } => {
@@ -484,7 +710,7 @@ pub async fn maps_rebuild() -> Result<HttpResponse, actix_web::Error> {
 
     // Start rebuild in background
     actix_web::rt::spawn(async move {
-        if let Err(e) = rebuild_maps_core().await {
+        if let Err(e) = rebuild_maps_core(gc_mode).await {
             error!("❌ Background rebuild failed: {:?}", e);
             let _ = std::fs::remove_file(&lockfile);
         }
@@ -492,17 +718,21 @@ pub async fn maps_rebuild() -> Result<HttpResponse, actix_web::Error> {
 
     let paths = find_dd2vtt_paths().map_err(|e| {
         error!("Failed to scan for maps: {:?}", e);
-        ErrorInternalServerError("Failed to scan for maps")
+        ApiError::internal("Failed to scan for maps")
     })?;
     let total = paths.len();
 
     info!("🚀 Background rebuild started for {} maps", total);
-    Ok(HttpResponse::Accepted().json(serde_json::json!({
-        "status":"processing","processed":0,"total":total
-    })))
+    Ok(HttpResponse::Accepted().json(RebuildStatus::started(0, total)))
 }
 
 /// Rebuild status handler
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/rebuild/status",
+    tag = "rebuild",
+    responses((status = 200, description = "Progress or result of the latest rebuild", body = RebuildStatus))
+)]
 pub async fn rebuild_status() -> Result<HttpResponse, actix_web::Error> {
     let lockfile = lock_path();
     let container_info = get_container_info();
@@ -513,34 +743,54 @@ pub async fn rebuild_status() -> Result<HttpResponse, actix_web::Error> {
                 processed,
                 total,
                 sha,
-            } => Ok(HttpResponse::Ok().json(serde_json::json!({
-                "status": "processing",
-                "processed": processed,
-                "total": total,
-                "sha": sha,
-                "container_info": container_info,
-                "progress_percentage": if total > 0 { (processed * 100) / total } else { 0 }
-            }))),
-            BuildLock::Complete { maps, sha } => Ok(HttpResponse::Ok().json(serde_json::json!({
-                "status": "complete",
-                "maps": maps,
-                "sha": sha,
-                "container_info": container_info
-            }))),
+            } => Ok(HttpResponse::Ok().json(RebuildStatus::Processing {
+                processed,
+                total,
+                sha: Some(sha),
+                container_info: Some(container_info),
+                progress_percentage: Some((processed * 100).checked_div(total).unwrap_or(0)),
+            })),
+            BuildLock::Complete {
+                maps,
+                sha,
+                lint,
+                gc,
+            } => Ok(HttpResponse::Ok().json(RebuildStatus::Complete {
+                maps,
+                sha,
+                lint,
+                gc,
+                container_info,
+            })),
         }
     } else {
-        Ok(HttpResponse::Ok().json(serde_json::json!({
-            "status": "idle",
-            "container_info": container_info
-        })))
+        Ok(HttpResponse::Ok().json(RebuildStatus::Idle { container_info }))
     }
 }
 
 /// Clear rebuild lock handler (admin-only)
-pub async fn clear_rebuild_lock() -> Result<HttpResponse, actix_web::Error> {
+#[utoipa::path(
+    delete,
+    path = "/api/v1/maps/rebuild/clear",
+    tag = "rebuild",
+    security(("admin_token" = ["lock-clear"])),
+    responses(
+        (status = 200, description = "Lock removed"),
+        (status = 401, description = "Missing admin token", body = ApiError),
+        (status = 403, description = "Invalid or expired admin token, or missing scope", body = ApiError),
+        (status = 500, description = "The lock could not be removed", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn clear_rebuild_lock(
+    admin: web::ReqData<AdminAuthenticated>,
+) -> Result<HttpResponse, actix_web::Error> {
     let lockfile = lock_path();
 
-    info!("🔐 Admin requested rebuild lock clear via API");
+    info!(
+        "🔐 Admin token {:?} requested rebuild lock clear via API",
+        admin.token
+    );
 
     match remove_lock(&lockfile) {
         Ok(()) => {
@@ -551,9 +801,23 @@ pub async fn clear_rebuild_lock() -> Result<HttpResponse, actix_web::Error> {
         }
         Err(e) => {
             error!("❌ Failed to clear lock file: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Failed to clear lock file: {}", e)
-            })))
+            Err(ApiError::internal(format!("Failed to clear lock file: {e}")).into())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_lock_without_lint_still_parses() {
+        let lock: BuildLock =
+            serde_json::from_str(r#"{"status":"complete","maps":3,"sha":"abc"}"#).unwrap();
+        let BuildLock::Complete { maps, lint, .. } = lock else {
+            panic!("expected a complete lock");
+        };
+        assert_eq!(maps, 3);
+        assert!(lint.maps.is_empty());
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+
+/// One generation at a time per cache key; concurrent requests for the same
+/// key wait for it, then find the result in the cache
+#[derive(Default)]
+pub(crate) struct SingleFlight {
+    locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+/// Held while generating one key; dropping it, on success or failure, lets
+/// the next waiter in and forgets the key
+pub(crate) struct Flight<'a> {
+    flights: &'a SingleFlight,
+    key: String,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl SingleFlight {
+    pub(crate) async fn lock(&self, key: &str) -> Flight<'_> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+            Arc::clone(locks.entry(key.to_string()).or_default())
+        };
+        Flight {
+            flights: self,
+            key: key.to_string(),
+            _guard: lock.lock_owned().await,
+        }
+    }
+}
+
+impl Drop for Flight<'_> {
+    fn drop(&mut self) {
+        let mut locks = self.flights.locks.lock().unwrap_or_else(|e| e.into_inner());
+        // Waiters still hold the lock; only the last one out removes it
+        if locks
+            .get(&self.key)
+            .is_some_and(|lock| Arc::strong_count(lock) <= 2)
+        {
+            locks.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl SingleFlight {
+        fn contains(&self, key: &str) -> bool {
+            self.locks.lock().unwrap().contains_key(key)
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_key_is_forgotten_once_every_flight_ends() {
+        let flights = SingleFlight::default();
+        let first = flights.lock("map").await;
+        assert!(flights.contains("map"));
+
+        let mut waiting = Box::pin(flights.lock("map"));
+        assert!(futures::poll!(&mut waiting).is_pending());
+        drop(first);
+        assert!(flights.contains("map"));
+
+        let second = waiting.await;
+        drop(second);
+        assert!(!flights.contains("map"));
+    }
+}
@@ -0,0 +1,35 @@
+use crate::api::ApiError;
+use crate::clients::meilisearch::meilisearch_index;
+use crate::metrics::observe_meilisearch;
+use actix_web::Error;
+use shared::types::map_document::MapDocument as MapDoc;
+use shared::utils::root_dir::root_dir;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{debug, error};
+
+/// Look up a map's indexed metadata by id
+pub async fn find_map_document(id: &str) -> Result<MapDoc, Error> {
+    let index = meilisearch_index("maps")?;
+
+    observe_meilisearch("get_document", index.get_document::<MapDoc>(id))
+        .await
+        .map_err(|e| {
+            debug!("Document metadata not found: {}", e);
+            ApiError::not_found("Map metadata not found").into()
+        })
+}
+
+/// Resolve the canonical on-disk path of a map's `.dd2vtt` file
+pub async fn map_file_path(doc: &MapDoc) -> Result<PathBuf, Error> {
+    let root_path = root_dir().map_err(ApiError::internal)?;
+    let file_path = root_path.join(doc.path.trim_start_matches('/'));
+
+    fs::canonicalize(&file_path).await.map_err(|e| {
+        error!(
+            "Failed to canonicalize file path: {}\n{:?}/{}",
+            e, root_path, doc.path
+        );
+        ApiError::internal("Failed to canonicalize file path of request map.").into()
+    })
+}
@@ -1,83 +1,158 @@
-use actix_web::{Error, HttpResponse, web};
-use base64::engine::{Engine, general_purpose};
-use bytes::Bytes;
-use shared::types::map_document::MapDocument as MapDoc;
-use shared::utils::root_dir::root_dir;
-use std::fs;
-use std::path::{Path, PathBuf};
+use crate::api::ApiError;
+use crate::maps::cache::{immutable, map_etag, not_modified};
+use crate::maps::image::{OverlayQuery, cached_variant};
+use crate::maps::source::{find_map_document, map_file_path};
+use crate::metrics::registry::BYTES_SERVED;
+use actix_web::{
+    Error, HttpRequest, HttpResponse,
+    http::header::{CACHE_CONTROL, ETAG},
+    web,
+};
+use serde::Deserialize;
+use shared::render::variant::{OutputFormat, VariantSpec, render_map_variant};
+use shared::types::dd2vtt::DD2VTTFile;
+use shared::utils::casing::kebabcase;
+use std::path::Path;
 use tracing::{debug, error};
+use utoipa::IntoParams;
 
-use crate::clients::meilisearch::meilisearch_index;
-
-async fn get_map_document(id: &str) -> Result<MapDoc, HttpResponse> {
-    let index = meilisearch_index("maps").map_err(|e| {
-        error!("Failed to get meilisearch index: {}", e);
-        HttpResponse::InternalServerError().body("Search service unavailable")
-    })?;
-
-    index.get_document::<MapDoc>(id).await.map_err(|e| {
-        error!("Failed to find map document: {}", e);
-        HttpResponse::NotFound().body(format!("Map with ID {id} not found"))
-    })
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TiledQuery {
+    /// `png`, `jpeg`, `webp` or `avif`; the embedded format when omitted
+    format: Option<String>,
 }
 
-fn build_file_path(doc: &MapDoc) -> Result<PathBuf, HttpResponse> {
-    let root_dir = root_dir().map_err(|e| {
-        error!("Failed to get root directory: {}", e);
-        HttpResponse::InternalServerError().body("Configuration error")
-    })?;
-
-    let full_path = root_dir.join(doc.path.trim_start_matches('/'));
-
-    if !full_path.exists() {
-        error!("Map file not found at apath: {}", doc.path);
-        return Err(HttpResponse::NotFound().body("Map file not found"));
-    }
-
-    Ok(full_path)
+/// Encoded map image ready to be served
+struct MapImage {
+    bytes: Vec<u8>,
+    mime_type: &'static str,
+    extension: &'static str,
 }
 
-fn load_and_decode_image(path: &Path) -> Result<Bytes, HttpResponse> {
-    let file = fs::File::open(path).map_err(|e| {
-        error!("Failed to open DD2VTT file: {}", e);
-        HttpResponse::InternalServerError().body("Failed to read DD2VTT file")
-    })?;
-
-    let dd2vtt: shared::types::dd2vtt::DD2VTTFile = serde_json::from_reader(file).map_err(|e| {
-        error!("Failed to parse DD2VTT file: {}", e);
-        HttpResponse::InternalServerError().body("Failed to parse DD2VTT file")
-    })?;
-
-    let image_bytes = general_purpose::STANDARD
-        .decode(&dd2vtt.image)
-        .map_err(|e| {
-            error!("Failed to decode base64 image: {}", e);
-            HttpResponse::InternalServerError().body("Failed to decode map image")
-        })?;
+/// Extract the embedded image, re-encoding it only when another format or an
+/// overlay is requested
+fn load_map_image(
+    path: &Path,
+    mut spec: VariantSpec,
+    format: Option<OutputFormat>,
+) -> anyhow::Result<MapImage> {
+    let map = DD2VTTFile::load(path)?;
+    let embedded = map.embedded_image()?;
+
+    let unchanged = format.is_none_or(|format| format.mime_type() == embedded.mime_type());
+    if spec.grid.is_none() && spec.overlay.is_none() && unchanged {
+        return Ok(MapImage {
+            mime_type: embedded.mime_type(),
+            extension: embedded.extension(),
+            bytes: embedded.bytes,
+        });
+    }
 
-    Ok(Bytes::from(image_bytes))
+    spec.format = format
+        .or_else(|| OutputFormat::from_image_format(embedded.format))
+        .unwrap_or(OutputFormat::Png);
+    Ok(MapImage {
+        bytes: render_map_variant(&map, &spec)?,
+        mime_type: spec.format.mime_type(),
+        extension: spec.format.extension(),
+    })
 }
 
-pub async fn tiled_map(id: web::Path<String>) -> Result<HttpResponse, Error> {
+/// Serve the map's full resolution image in its embedded format, or transcoded
+/// with `?format=png|jpeg|webp|avif`, optionally with a grid or geometry overlay
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/tiled/{id}",
+    tag = "images",
+    params(("id" = String, Path, description = "Map id (content hash)"), TiledQuery, OverlayQuery),
+    responses(
+        (status = 200, description = "The full resolution image", content(
+            (Vec<u8> = "image/webp"),
+            (Vec<u8> = "image/png"),
+            (Vec<u8> = "image/jpeg"),
+            (Vec<u8> = "image/avif"),
+        )),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Invalid format or overlay option", body = ApiError),
+        (status = 404, description = "No map with this id", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn tiled_map(
+    req: HttpRequest,
+    id: web::Path<String>,
+    query: web::Query<TiledQuery>,
+    overlays: web::Query<OverlayQuery>,
+) -> Result<HttpResponse, Error> {
     let id = id.into_inner();
     debug!("Request for tiled map with id: {}", id);
 
-    let doc = match get_map_document(&id).await {
-        Ok(doc) => doc,
-        Err(response) => return Ok(response),
-    };
+    let format = query
+        .format
+        .as_deref()
+        .map(str::parse::<OutputFormat>)
+        .transpose()
+        .map_err(ApiError::bad_request)?;
+    let spec = VariantSpec {
+        grid: overlays.style()?,
+        overlay: overlays.layers()?,
+        ..VariantSpec::new(OutputFormat::Png)
+    }
+    .quantized();
+
+    let key = format!(
+        "tiled-{}-{}",
+        format.map_or_else(|| "original".to_string(), |f| f.to_string()),
+        spec.cache_key()
+    );
+    let etag = map_etag(&id, &key);
+    if let Some(res) = not_modified(&req, &etag, immutable()) {
+        return Ok(res);
+    }
 
-    let file_path = match build_file_path(&doc) {
-        Ok(path) => path,
-        Err(response) => return Ok(response),
+    let doc = find_map_document(&id).await?;
+    let image = if spec.grid.is_none() && spec.overlay.is_none() && format.is_none() {
+        let file_path = map_file_path(&doc).await?;
+        web::block(move || load_map_image(&file_path, spec, None))
+            .await?
+            .map_err(|e| {
+                error!("Failed to load image of map {}: {:?}", id, e);
+                ApiError::internal("Failed to decode map image")
+            })?
+    } else {
+        // Transcodes and overlays decode the full map, so they go through
+        // the variant cache like the resized images
+        let bytes = cached_variant(&id, &key, move |source| {
+            load_map_image(source, spec, format).map(|image| image.bytes)
+        })
+        .await?;
+        let format = OutputFormat::sniff(&bytes)
+            .or(format)
+            .unwrap_or(OutputFormat::Png);
+        MapImage {
+            bytes,
+            mime_type: format.mime_type(),
+            extension: format.extension(),
+        }
     };
 
-    let image_data = match load_and_decode_image(&file_path) {
-        Ok(data) => data,
-        Err(response) => return Ok(response),
-    };
+    BYTES_SERVED
+        .with_label_values(&["tiled_map"])
+        .inc_by(image.bytes.len() as u64);
 
+    let stem: String = kebabcase(&doc.name)
+        .chars()
+        .filter(|c| !matches!(c, '"' | '\\' | '/'))
+        .collect();
+    let filename = format!("{stem}.{}", image.extension);
     Ok(HttpResponse::Ok()
-        .content_type("image/png")
-        .body(image_data))
+        .content_type(image.mime_type)
+        .insert_header((CACHE_CONTROL, immutable()))
+        .insert_header((ETAG, etag))
+        .append_header((
+            "Content-Disposition",
+            format!("inline; filename=\"{filename}\""),
+        ))
+        .body(image.bytes))
 }
@@ -0,0 +1,290 @@
+use crate::api::API_V1;
+use crate::api::ApiError;
+use crate::hooks::rate_limit::{self, Budget};
+use crate::maps::cache::{immutable, map_etag, not_modified};
+use crate::maps::single_flight::SingleFlight;
+use crate::maps::source::{find_map_document, map_file_path};
+use crate::metrics::registry::BYTES_SERVED;
+use crate::utils::folders::tiles_dir;
+use actix_web::{
+    Error, HttpRequest, HttpResponse,
+    http::header::{CACHE_CONTROL, ETAG},
+    web,
+};
+use serde::{Deserialize, Serialize};
+use shared::render::tiles::{TILE_SIZE, TileCoord, TilePyramid, generate_tiles};
+use shared::types::dd2vtt::DD2VTTFile;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Instant;
+use tokio::fs;
+use tracing::{debug, error, info, instrument};
+use utoipa::ToSchema;
+
+/// Route of a single tile, relative to the `/maps` scope
+pub const TILE_ROUTE: &str = r"/tiles/{id}/{z:\d+}/{x:\d+}/{y:\d+}.webp";
+pub(crate) const MANIFEST_FILE: &str = "pyramid.json";
+
+/// One generation at a time per map
+static GENERATION: LazyLock<SingleFlight> = LazyLock::new(SingleFlight::default);
+
+#[derive(Deserialize)]
+pub struct TilePath {
+    id: String,
+    z: u32,
+    x: u32,
+    y: u32,
+}
+
+/// Pyramid layout returned to viewers
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct PyramidManifest {
+    #[serde(flatten)]
+    pyramid: TilePyramid,
+    /// Template with `{z}`, `{x}` and `{y}` placeholders
+    tile_url: String,
+}
+
+impl PyramidManifest {
+    /// `tiles` is the URL the pyramid's `{z}/{x}/{y}.webp` files are under
+    pub(crate) fn new(tiles: &str, pyramid: TilePyramid) -> Self {
+        Self {
+            pyramid,
+            tile_url: format!("{tiles}/{{z}}/{{x}}/{{y}}.webp"),
+        }
+    }
+}
+
+fn pyramid_dir(id: &str) -> Result<PathBuf, Error> {
+    // Ids are hex content hashes; reject anything that could escape the cache
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(ApiError::not_found("Map not found").into());
+    }
+    Ok(tiles_dir().map_err(ApiError::internal)?.join(id))
+}
+
+fn tile_path(dir: &Path, coord: TileCoord) -> PathBuf {
+    dir.join(coord.z.to_string())
+        .join(coord.x.to_string())
+        .join(format!("{}.webp", coord.y))
+}
+
+async fn read_manifest(dir: &Path) -> Option<TilePyramid> {
+    let data = fs::read(dir.join(MANIFEST_FILE)).await.ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// Decode the map once and write its full pyramid, swapping it in atomically
+#[instrument(level = "info", skip_all, fields(file = %source.display()))]
+pub(crate) fn build_pyramid(source: &Path, dir: &Path) -> anyhow::Result<TilePyramid> {
+    let start = Instant::now();
+    let image = DD2VTTFile::load(source)?.decode_image()?;
+    let pyramid = TilePyramid::new(image.width(), image.height(), TILE_SIZE);
+
+    let staging = dir.with_extension("partial");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+
+    let written = generate_tiles(&image, &pyramid, |coord, bytes| {
+        let path = tile_path(&staging, coord);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, bytes)
+    })?;
+    std::fs::write(staging.join(MANIFEST_FILE), serde_json::to_vec(&pyramid)?)?;
+
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    std::fs::rename(&staging, dir)?;
+
+    info!(
+        "🧩 Generated {} tiles ({} levels) in {:?}",
+        written,
+        pyramid.max_zoom + 1,
+        start.elapsed()
+    );
+    Ok(pyramid)
+}
+
+/// Return the map's pyramid, generating and caching it on first use
+async fn ensure_pyramid(id: &str) -> Result<(TilePyramid, PathBuf), Error> {
+    let dir = pyramid_dir(id)?;
+    if let Some(pyramid) = read_manifest(&dir).await {
+        return Ok((pyramid, dir));
+    }
+
+    let doc = find_map_document(id).await?;
+    let source = map_file_path(&doc).await?;
+
+    let _flight = GENERATION.lock(id).await;
+
+    // Another request may have finished generating while we waited
+    let pyramid = if let Some(pyramid) = read_manifest(&dir).await {
+        pyramid
+    } else {
+        let target = dir.clone();
+        web::block(move || build_pyramid(&source, &target))
+            .await?
+            .map_err(|e| {
+                error!("Failed to generate tiles for {}: {:?}", id, e);
+                ApiError::internal("Failed to generate map tiles")
+            })?
+    };
+    Ok((pyramid, dir))
+}
+
+/// Tile pyramid layout for a map, generating tiles on first request
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/tiles/{id}/pyramid.json",
+    tag = "images",
+    params(("id" = String, Path, description = "Map id (content hash)")),
+    responses(
+        (status = 200, description = "Pyramid layout and tile URL template", body = PyramidManifest),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 404, description = "No map with this id", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn tile_pyramid(req: HttpRequest, id: web::Path<String>) -> Result<HttpResponse, Error> {
+    let id = id.into_inner();
+    debug!("Request for tile pyramid of map {}", id);
+
+    let etag = map_etag(&id, MANIFEST_FILE);
+    if let Some(res) = not_modified(&req, &etag, immutable()) {
+        return Ok(res);
+    }
+
+    let (pyramid, _) = ensure_pyramid(&id).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, immutable()))
+        .insert_header((ETAG, etag))
+        .json(PyramidManifest::new(
+            &format!("{API_V1}/maps/tiles/{id}"),
+            pyramid,
+        )))
+}
+
+/// Serve a single 256px WebP tile at `/api/maps/tiles/{id}/{z}/{x}/{y}.webp`
+#[utoipa::path(
+    get,
+    path = "/api/v1/maps/tiles/{id}/{z}/{x}/{y}.webp",
+    tag = "images",
+    params(
+        ("id" = String, Path, description = "Map id (content hash)"),
+        ("z" = u32, Path, description = "Zoom level; `max_zoom` is full resolution"),
+        ("x" = u32, Path, description = "Tile column"),
+        ("y" = u32, Path, description = "Tile row"),
+    ),
+    responses(
+        (status = 200, description = "The tile", body = Vec<u8>, content_type = "image/webp"),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 404, description = "No map with this id, or the tile is out of range", body = ApiError),
+        (status = 429, description = "Rate limited while generating tiles; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn map_tile(req: HttpRequest, path: web::Path<TilePath>) -> Result<HttpResponse, Error> {
+    let TilePath { id, z, x, y } = path.into_inner();
+    let coord = TileCoord { z, x, y };
+    debug!("Request for tile {:?} of map {}", coord, id);
+
+    let etag = map_etag(&id, &format!("tile-{z}-{x}-{y}"));
+    if let Some(res) = not_modified(&req, &etag, immutable()) {
+        return Ok(res);
+    }
+
+    // Serving a generated tile is a file read; only the request that has to
+    // generate the pyramid counts against the images budget
+    if read_manifest(&pyramid_dir(&id)?).await.is_none() {
+        rate_limit::charge(&req, Budget::Images)?;
+    }
+    let (pyramid, dir) = ensure_pyramid(&id).await?;
+    if !pyramid.contains(coord) {
+        return Err(ApiError::not_found("Tile out of range").into());
+    }
+
+    let data = fs::read(tile_path(&dir, coord)).await.map_err(|e| {
+        error!("Failed to read cached tile {:?} of {}: {}", coord, id, e);
+        ApiError::not_found("Tile not found")
+    })?;
+
+    BYTES_SERVED
+        .with_label_values(&["map_tile"])
+        .inc_by(data.len() as u64);
+
+    Ok(HttpResponse::Ok()
+        .content_type("image/webp")
+        .insert_header((CACHE_CONTROL, immutable()))
+        .insert_header((ETAG, etag))
+        .body(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, test};
+
+    async fn echo(path: web::Path<TilePath>) -> HttpResponse {
+        let TilePath { id, z, x, y } = path.into_inner();
+        HttpResponse::Ok().body(format!("{id}:{z}:{x}:{y}"))
+    }
+
+    #[actix_web::test]
+    async fn test_tile_route_extracts_coordinates() {
+        let app = test::init_service(
+            App::new().service(web::scope("/maps").route(TILE_ROUTE, web::get().to(echo))),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/maps/tiles/abc123/3/10/7.webp")
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(body, "abc123:3:10:7");
+
+        let req = test::TestRequest::get()
+            .uri("/maps/tiles/abc123/3/10/7.png")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_generated_tiles_are_not_rate_limited() {
+        let id = format!("tilesratelimit{}", std::process::id());
+        let dir = pyramid_dir(&id).unwrap();
+        let coord = TileCoord { z: 0, x: 0, y: 0 };
+        let tile = tile_path(&dir, coord);
+        std::fs::create_dir_all(tile.parent().unwrap()).unwrap();
+        std::fs::write(&tile, b"tile").unwrap();
+        let pyramid = TilePyramid::new(TILE_SIZE, TILE_SIZE, TILE_SIZE);
+        std::fs::write(
+            dir.join(MANIFEST_FILE),
+            serde_json::to_vec(&pyramid).unwrap(),
+        )
+        .unwrap();
+
+        let app = test::init_service(
+            App::new().service(web::scope(API_V1).configure(crate::api::routes)),
+        )
+        .await;
+        for _ in 0..100 {
+            let req = test::TestRequest::get()
+                .uri(&format!("{API_V1}/maps/tiles/{id}/0/0/0.webp"))
+                .peer_addr("192.0.2.29:4000".parse().unwrap())
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), 200);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_pyramid_dir_rejects_traversal() {
+        assert!(pyramid_dir("../etc").is_err());
+        assert!(pyramid_dir("").is_err());
+    }
+}
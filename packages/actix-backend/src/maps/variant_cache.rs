@@ -0,0 +1,192 @@
+//! Size cap for `assets/variants`, from `VARIANT_CACHE_MAX_MB` (default 2048,
+//! `off` for none). Cache hits refresh a file's modification time, and a write
+//! that takes the cache over the cap evicts the least recently used files.
+
+use crate::utils::folders::variants_dir;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, PoisonError};
+use std::time::SystemTime;
+use tracing::{debug, info, warn};
+
+const DEFAULT_MAX_MB: u64 = 2048;
+
+/// Cap in bytes; `None` when eviction is off
+static MAX_BYTES: LazyLock<Option<u64>> = LazyLock::new(|| {
+    let configured = env::var("VARIANT_CACHE_MAX_MB").ok();
+    match configured.as_deref().map(str::trim) {
+        Some("off") => None,
+        Some(value) => Some(value.parse::<u64>().unwrap_or_else(|_| {
+            warn!("⚠️  Ignoring invalid VARIANT_CACHE_MAX_MB: {}", value);
+            DEFAULT_MAX_MB
+        })),
+        None => Some(DEFAULT_MAX_MB),
+    }
+    .map(|mb| mb * 1024 * 1024)
+});
+
+/// Bytes in the cache; `None` until the first write scans it
+static USAGE: Mutex<Option<u64>> = Mutex::new(None);
+
+/// Mark a cached file as recently used, without waiting for the disk
+pub(crate) fn touch(path: PathBuf) {
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            debug!("Failed to touch cached variant {}: {}", path.display(), e);
+        }
+    });
+}
+
+/// Account for `bytes` just written to the cache, evicting the least recently
+/// used files if that took it over the cap. Blocks on the disk.
+pub(crate) fn record_write(bytes: u64) {
+    let Some(max) = *MAX_BYTES else {
+        return;
+    };
+    let mut usage = USAGE.lock().unwrap_or_else(PoisonError::into_inner);
+    let result = variants_dir().and_then(|dir| enforce(&dir, max, &mut usage, bytes));
+    if let Err(e) = result {
+        warn!("⚠️  Failed to enforce the variant cache cap: {}", e);
+    }
+}
+
+/// Account for `bytes` removed from the cache behind its back, such as by
+/// garbage collection of deleted maps
+pub(crate) fn record_removal(bytes: u64) {
+    let mut usage = USAGE.lock().unwrap_or_else(PoisonError::into_inner);
+    release(&mut usage, bytes);
+}
+
+fn release(usage: &mut Option<u64>, removed: u64) {
+    if let Some(total) = usage.as_mut() {
+        *total = total.saturating_sub(removed);
+    }
+}
+
+struct CachedFile {
+    path: PathBuf,
+    len: u64,
+    used: SystemTime,
+}
+
+/// Files under `{dir}/{map}/`, skipping renders still being written
+fn cached_files(dir: &Path) -> io::Result<Vec<CachedFile>> {
+    let mut files = Vec::new();
+    for map in fs::read_dir(dir)? {
+        let map = map?;
+        if !map.file_type()?.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(map.path())? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().contains(".partial") {
+                continue;
+            }
+            let meta = entry.metadata()?;
+            if meta.is_file() {
+                files.push(CachedFile {
+                    path: entry.path(),
+                    len: meta.len(),
+                    used: meta.modified()?,
+                });
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn enforce(dir: &Path, max: u64, usage: &mut Option<u64>, written: u64) -> io::Result<()> {
+    if let Some(total) = usage.as_mut() {
+        *total += written;
+        if *total <= max {
+            return Ok(());
+        }
+    }
+
+    let mut files = cached_files(dir)?;
+    let mut total: u64 = files.iter().map(|f| f.len).sum();
+    *usage = Some(total);
+    if total <= max {
+        return Ok(());
+    }
+
+    // Evict down to 90% of the cap so the next few writes don't evict again
+    let target = max / 10 * 9;
+    files.sort_by_key(|f| f.used);
+    let mut evicted = 0;
+    for file in files {
+        if total <= target {
+            break;
+        }
+        match fs::remove_file(&file.path) {
+            Ok(()) => evicted += 1,
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        total -= file.len;
+    }
+    *usage = Some(total);
+
+    info!(
+        "🧹 Evicted {} cached variants; the cache now holds {} bytes",
+        evicted, total
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_least_recently_used_files_are_evicted() {
+        let dir = env::temp_dir().join(format!("variant-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("map")).unwrap();
+        let write = |name: &str, age: u64| {
+            let path = dir.join("map").join(name);
+            fs::write(&path, [0; 100]).unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::now() - Duration::from_secs(age))
+                .unwrap();
+        };
+        write("old.webp", 300);
+        write("recent.webp", 100);
+        write("w64-h0-q0.partial-1", 400);
+
+        let mut usage = None;
+        enforce(&dir, 250, &mut usage, 100).unwrap();
+        assert_eq!(usage, Some(200));
+
+        write("new.webp", 0);
+        enforce(&dir, 250, &mut usage, 100).unwrap();
+        assert_eq!(usage, Some(200));
+        assert!(!dir.join("map/old.webp").exists());
+        assert!(dir.join("map/recent.webp").exists());
+        assert!(dir.join("map/new.webp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_removed_bytes_are_released() {
+        let mut usage = Some(500);
+        release(&mut usage, 200);
+        assert_eq!(usage, Some(300));
+        release(&mut usage, 400);
+        assert_eq!(usage, Some(0));
+
+        let mut unscanned = None;
+        release(&mut unscanned, 100);
+        assert_eq!(unscanned, None);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::metrics::registry::REBUILD_DURATION;
+use std::time::Instant;
+use tracing::Subscriber;
+use tracing::span::{Attributes, Id};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Name of the span created by `#[instrument]` on `rebuild_maps_core`
+const REBUILD_SPAN: &str = "rebuild_maps_core";
+
+struct SpanStart(Instant);
+
+/// Tracing layer that turns the existing rebuild span into a duration histogram
+pub struct SpanMetricsLayer;
+
+impl<S> Layer<S> for SpanMetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != REBUILD_SPAN {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStart(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(&id)
+            && let Some(SpanStart(start)) = span.extensions().get::<SpanStart>()
+        {
+            REBUILD_DURATION.observe(start.elapsed().as_secs_f64());
+        }
+    }
+}
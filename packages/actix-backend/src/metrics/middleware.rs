@@ -0,0 +1,77 @@
+use crate::metrics::registry::{HTTP_REQUEST_DURATION, HTTP_REQUESTS_TOTAL};
+use actix_web::{
+    Error,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+};
+use futures_util::future::{LocalBoxFuture, Ready, ok};
+use std::rc::Rc;
+use std::time::Instant;
+
+/// Route label used when no resource pattern matched (static files, SPA fallback)
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Middleware recording request counts and latencies per matched route.
+///
+/// Routes are labelled by their pattern (e.g. `/api/maps/{id}`) rather than the
+/// raw path so label cardinality stays bounded.
+pub struct HttpMetrics;
+
+impl<S, B> Transform<S, ServiceRequest> for HttpMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: actix_web::body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = HttpMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(HttpMetricsMiddleware(Rc::new(service)))
+    }
+}
+
+pub struct HttpMetricsMiddleware<S>(Rc<S>);
+
+impl<S, B> Service<ServiceRequest> for HttpMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: actix_web::body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let srv = Rc::clone(&self.0);
+        let method = req.method().to_string();
+        let start = Instant::now();
+
+        Box::pin(async move {
+            let res = srv.call(req).await?;
+
+            let route = res
+                .request()
+                .match_pattern()
+                .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+            let status = res.status().as_u16().to_string();
+
+            HTTP_REQUESTS_TOTAL
+                .with_label_values(&[method.as_str(), route.as_str(), status.as_str()])
+                .inc();
+            HTTP_REQUEST_DURATION
+                .with_label_values(&[method.as_str(), route.as_str()])
+                .observe(start.elapsed().as_secs_f64());
+
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,55 @@
+mod layer;
+mod middleware;
+pub mod registry;
+
+pub use layer::SpanMetricsLayer;
+pub use middleware::HttpMetrics;
+
+use actix_web::{HttpResponse, error::ErrorInternalServerError};
+use registry::MEILISEARCH_DURATION;
+use std::future::Future;
+use std::time::Instant;
+
+/// Prometheus scrape endpoint
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses((status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"))
+)]
+pub async fn metrics() -> Result<HttpResponse, actix_web::Error> {
+    let body = registry::gather().map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(body))
+}
+
+/// Time a Meilisearch call, labelled by operation and outcome
+pub async fn observe_meilisearch<T, E, F>(operation: &'static str, call: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let start = Instant::now();
+    let result = call.await;
+    let outcome = if result.is_ok() { "ok" } else { "error" };
+    MEILISEARCH_DURATION
+        .with_label_values(&[operation, outcome])
+        .observe(start.elapsed().as_secs_f64());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_observe_meilisearch_records_outcome() {
+        let ok: Result<u8, ()> = observe_meilisearch("test_op", async { Ok(1) }).await;
+        let err: Result<u8, ()> = observe_meilisearch("test_op", async { Err(()) }).await;
+        assert!(ok.is_ok() && err.is_err());
+
+        let text = registry::gather().unwrap();
+        assert!(text.contains(r#"operation="test_op",outcome="ok""#));
+        assert!(text.contains(r#"operation="test_op",outcome="error""#));
+    }
+}
@@ -0,0 +1,150 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+
+const NAMESPACE: &str = "vtt_maps";
+
+/// Buckets tuned for map rebuilds, which take seconds to minutes
+const REBUILD_BUCKETS: &[f64] = &[0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+pub static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+fn register<C: prometheus::core::Collector + Clone + 'static>(collector: C) -> C {
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("metric registered twice");
+    collector
+}
+
+pub static HTTP_REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled").namespace(NAMESPACE),
+            &["method", "route", "status"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static HTTP_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route",
+            )
+            .namespace(NAMESPACE),
+            &["method", "route"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static REBUILD_DURATION: LazyLock<Histogram> = LazyLock::new(|| {
+    register(
+        Histogram::with_opts(
+            HistogramOpts::new("rebuild_duration_seconds", "Duration of map rebuilds")
+                .namespace(NAMESPACE)
+                .buckets(REBUILD_BUCKETS.to_vec()),
+        )
+        .unwrap(),
+    )
+});
+
+pub static MAPS_INDEXED: LazyLock<IntGauge> = LazyLock::new(|| {
+    register(
+        IntGauge::with_opts(
+            Opts::new("maps_indexed", "Maps indexed by the last completed rebuild")
+                .namespace(NAMESPACE),
+        )
+        .unwrap(),
+    )
+});
+
+pub static MAP_PROCESSING_FAILURES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(
+        IntCounter::with_opts(
+            Opts::new(
+                "map_processing_failures_total",
+                "Map files that failed to process during rebuilds",
+            )
+            .namespace(NAMESPACE),
+        )
+        .unwrap(),
+    )
+});
+
+pub static THUMBNAIL_CACHE: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("thumbnail_cache_total", "Thumbnail cache lookups by result")
+                .namespace(NAMESPACE),
+            &["result"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static BYTES_SERVED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "bytes_served_total",
+                "Map bytes sent to clients by endpoint",
+            )
+            .namespace(NAMESPACE),
+            &["endpoint"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static MEILISEARCH_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "meilisearch_request_duration_seconds",
+                "Meilisearch call latency by operation",
+            )
+            .namespace(NAMESPACE),
+            &["operation", "outcome"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static RATE_LIMITED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "rate_limited_total",
+                "Requests rejected by the rate limiter by budget",
+            )
+            .namespace(NAMESPACE),
+            &["budget"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Force registration so every metric shows up on the first scrape
+pub fn init() {
+    LazyLock::force(&HTTP_REQUESTS_TOTAL);
+    LazyLock::force(&HTTP_REQUEST_DURATION);
+    LazyLock::force(&REBUILD_DURATION);
+    LazyLock::force(&MAPS_INDEXED);
+    LazyLock::force(&MAP_PROCESSING_FAILURES);
+    LazyLock::force(&THUMBNAIL_CACHE);
+    LazyLock::force(&BYTES_SERVED);
+    LazyLock::force(&MEILISEARCH_DURATION);
+    LazyLock::force(&RATE_LIMITED);
+}
+
+/// Render all registered metrics in the Prometheus text format
+pub fn gather() -> Result<String, prometheus::Error> {
+    let mut buf = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buf)?;
+    String::from_utf8(buf).map_err(|e| prometheus::Error::Msg(e.to_string()))
+}
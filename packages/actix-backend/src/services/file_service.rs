@@ -5,13 +5,13 @@ use actix_web::{
     error::ErrorInternalServerError,
     web,
 };
-use std::env;
-use std::path::PathBuf;
+
+use crate::utils::folders::dist_dir;
 
 /// Configure static file serving (SPA shell fallback)
 pub fn file_service(cfg: &mut web::ServiceConfig) {
     // Determine the dist directory
-    let dist = env::var("DIST_DIR").map_or_else(|_| PathBuf::from("dist"), PathBuf::from);
+    let dist = dist_dir();
 
     // Build the Files service with SPA fallback to index.html
     let files = Files::new("/", &dist)
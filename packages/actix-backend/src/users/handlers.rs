@@ -0,0 +1,240 @@
+use crate::api::ApiError;
+use crate::users::oidc::{self, PendingLogin};
+use crate::users::store::{self, Role, User};
+use crate::utils::admin_session::{self, OpenSession, csrf_ok, current_session, start_session};
+use actix_identity::Identity;
+use actix_session::SessionExt;
+use actix_web::{
+    HttpRequest, HttpResponse,
+    http::{
+        StatusCode,
+        header::{CacheControl, CacheDirective, LOCATION},
+    },
+    web,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+const IDENTITY_PREFIX: &str = "user:";
+const SESSION_PENDING: &str = "oidc_pending";
+
+/// A signed-in user's session
+pub struct UserSession {
+    pub user: User,
+    csrf: String,
+}
+
+impl UserSession {
+    /// The name admin routes record, such as `user:1f2e3d4c5b6a7980`
+    pub fn actor(&self) -> String {
+        format!("{IDENTITY_PREFIX}{}", self.user.id)
+    }
+
+    pub fn allows(&self, req: &HttpRequest) -> bool {
+        csrf_ok(req, &self.csrf)
+    }
+}
+
+/// The user a request's session belongs to, if any.
+///
+/// # Errors
+/// Returns an error if the user store cannot be read.
+pub fn session_user(req: &HttpRequest) -> Result<Option<UserSession>> {
+    let Some(OpenSession { id, csrf, .. }) = current_session(req) else {
+        return Ok(None);
+    };
+    let Some(user_id) = id.strip_prefix(IDENTITY_PREFIX) else {
+        return Ok(None);
+    };
+    Ok(store::find_user(user_id)?.map(|user| UserSession { user, csrf }))
+}
+
+fn not_configured() -> ApiError {
+    ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "Sign-in is not configured")
+}
+
+fn provider_failed(e: &anyhow::Error) -> ApiError {
+    error!("❌ Sign-in with the identity provider failed: {:?}", e);
+    ApiError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Sign-in with the identity provider failed",
+    )
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LoginQuery {
+    /// Local path to return to after signing in
+    return_to: Option<String>,
+}
+
+/// Start signing in with the identity provider
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/login",
+    tag = "users",
+    params(LoginQuery),
+    responses(
+        (status = 302, description = "Redirect to the identity provider"),
+        (status = 503, description = "Sign-in is not configured or the provider is down", body = ApiError),
+    )
+)]
+pub async fn login(
+    req: HttpRequest,
+    query: web::Query<LoginQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let config = oidc::config().ok_or_else(not_configured)?;
+    let provider = oidc::provider(config)
+        .await
+        .map_err(|e| provider_failed(&e))?;
+
+    let pending = PendingLogin::new(query.return_to.as_deref().unwrap_or("/"));
+    let url =
+        oidc::authorization_url(config, provider, &pending).map_err(|e| provider_failed(&e))?;
+    req.get_session()
+        .insert(SESSION_PENDING, &pending)
+        .map_err(|e| {
+            error!("❌ Failed to store the pending sign-in: {:?}", e);
+            ApiError::internal("Failed to start sign-in")
+        })?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, url))
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .finish())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    /// Set by the provider when sign-in was refused or failed
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// Finish signing in; the identity provider redirects here
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/callback",
+    tag = "users",
+    params(CallbackQuery),
+    responses(
+        (status = 302, description = "Signed in; redirect to where sign-in started"),
+        (status = 400, description = "No sign-in in progress or a mismatched state", body = ApiError),
+        (status = 403, description = "The provider refused sign-in", body = ApiError),
+        (status = 503, description = "Sign-in is not configured or the provider is down", body = ApiError),
+    )
+)]
+pub async fn callback(
+    req: HttpRequest,
+    query: web::Query<CallbackQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let config = oidc::config().ok_or_else(not_configured)?;
+    let pending = req
+        .get_session()
+        .remove_as::<PendingLogin>(SESSION_PENDING)
+        .and_then(Result::ok)
+        .ok_or_else(|| ApiError::bad_request("No sign-in in progress"))?;
+
+    if let Some(error) = &query.error {
+        warn!("❌ Identity provider refused sign-in: {}", error);
+        return Err(ApiError::forbidden(format!("Sign-in was refused: {error}"))
+            .with_details(json!({ "description": query.error_description }))
+            .into());
+    }
+    if !query
+        .state
+        .as_deref()
+        .is_some_and(|state| pending.state_matches(state))
+    {
+        warn!("❌ Sign-in callback with a mismatched state");
+        return Err(ApiError::bad_request("Sign-in state mismatch").into());
+    }
+    let code = query
+        .code
+        .as_deref()
+        .ok_or_else(|| ApiError::bad_request("Missing authorization code"))?;
+
+    let provider = oidc::provider(config)
+        .await
+        .map_err(|e| provider_failed(&e))?;
+    let profile = oidc::exchange_code(config, provider, code, &pending)
+        .await
+        .map_err(|e| provider_failed(&e))?;
+    let user = web::block(move || store::record_login(profile))
+        .await?
+        .map_err(|e| {
+            error!("❌ Failed to record sign-in: {:?}", e);
+            ApiError::internal("Failed to record sign-in")
+        })?;
+    start_session(&req, format!("{IDENTITY_PREFIX}{}", user.id))?;
+
+    info!("👤 User {} signed in as {}", user.id, user.role());
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, pending.return_to))
+        .finish())
+}
+
+/// Sign out
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/logout",
+    tag = "users",
+    responses((status = 204, description = "Signed out, or there was no session"))
+)]
+pub async fn logout(identity: Option<Identity>) -> HttpResponse {
+    admin_session::logout(identity).await
+}
+
+/// The signed-in user
+#[derive(Serialize, ToSchema)]
+pub struct Me {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    picture: Option<String>,
+    role: Role,
+    /// Send as `X-CSRF-Token` on POST, PUT, PATCH and DELETE requests
+    csrf_token: String,
+    /// Unix seconds
+    created_at: u64,
+}
+
+/// Show the signed-in user
+#[utoipa::path(
+    get,
+    path = "/api/v1/me",
+    tag = "users",
+    responses(
+        (status = 200, description = "The signed-in user", body = Me),
+        (status = 401, description = "Not signed in", body = ApiError),
+    )
+)]
+pub async fn me(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
+    let session = session_user(&req).map_err(|e| {
+        error!("❌ Failed to read the user store: {:?}", e);
+        ApiError::internal("User store unavailable")
+    })?;
+    let Some(UserSession { user, csrf }) = session else {
+        return Err(ApiError::unauthorized("Not signed in").into());
+    };
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(Me {
+            role: user.role(),
+            id: user.id,
+            email: user.email,
+            name: user.name,
+            picture: user.picture,
+            csrf_token: csrf,
+            created_at: user.created_at,
+        }))
+}
@@ -0,0 +1,5 @@
+pub mod handlers;
+pub mod oidc;
+pub mod store;
+
+pub use handlers::{callback, login, logout, me, session_user};
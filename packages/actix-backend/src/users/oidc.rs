@@ -0,0 +1,458 @@
+//! OpenID Connect authorization code flow with PKCE.
+//!
+//! Configured with `OIDC_ISSUER`, `OIDC_CLIENT_ID`, `OIDC_CLIENT_SECRET` and
+//! `OIDC_REDIRECT_URL` (defaulting to `SITE_URL` + `/api/v1/auth/callback`).
+//! Roles come from the `OIDC_ROLE_CLAIM` claim (default `groups`, dotted
+//! paths such as `realm_access.roles` work): values listed in
+//! `OIDC_ADMIN_ROLES` or `OIDC_CURATOR_ROLES` map to those roles, anyone else
+//! is a plain user.
+
+use crate::users::store::{Profile, Role};
+use crate::utils::admin_token::generate_secure_token;
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header, jwk::JwkSet};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::sync::LazyLock;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+use tokio::sync::OnceCell;
+use tracing::warn;
+use url::Url;
+
+const CALLBACK_PATH: &str = "/api/v1/auth/callback";
+const DEFAULT_SCOPES: &str = "openid profile email";
+
+static CONFIG: LazyLock<Option<OidcConfig>> = LazyLock::new(OidcConfig::from_env);
+static PROVIDER: OnceCell<Provider> = OnceCell::const_new();
+static HTTP: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("HTTP client")
+});
+
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_url: String,
+    pub scopes: String,
+    pub role_claim: String,
+    pub admin_roles: Vec<String>,
+    pub curator_roles: Vec<String>,
+}
+
+fn list(var: &str) -> Vec<String> {
+    env::var(var)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl OidcConfig {
+    fn from_env() -> Option<Self> {
+        let issuer = env::var("OIDC_ISSUER").ok().filter(|v| !v.is_empty())?;
+        let Some(client_id) = env::var("OIDC_CLIENT_ID").ok().filter(|v| !v.is_empty()) else {
+            warn!("⚠️ OIDC_ISSUER is set without OIDC_CLIENT_ID; sign-in is disabled");
+            return None;
+        };
+        let redirect_url = env::var("OIDC_REDIRECT_URL").ok().or_else(|| {
+            env::var("SITE_URL")
+                .ok()
+                .map(|site| format!("{}{CALLBACK_PATH}", site.trim_end_matches('/')))
+        });
+        let Some(redirect_url) = redirect_url else {
+            warn!("⚠️ Neither OIDC_REDIRECT_URL nor SITE_URL is set; sign-in is disabled");
+            return None;
+        };
+        Some(Self {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id,
+            client_secret: env::var("OIDC_CLIENT_SECRET")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            redirect_url,
+            scopes: env::var("OIDC_SCOPES").unwrap_or_else(|_| DEFAULT_SCOPES.to_string()),
+            role_claim: env::var("OIDC_ROLE_CLAIM").unwrap_or_else(|_| "groups".to_string()),
+            admin_roles: list("OIDC_ADMIN_ROLES"),
+            curator_roles: list("OIDC_CURATOR_ROLES"),
+        })
+    }
+
+    /// Map the role claim's values onto a [`Role`], taking the highest match
+    pub fn role(&self, claims: &HashMap<String, Value>) -> Role {
+        let mut parts = self.role_claim.split('.');
+        let first = parts.next().and_then(|key| claims.get(key));
+        let values = parts.fold(first, |value, key| value.and_then(|v| v.get(key)));
+        let values: Vec<&str> = match values {
+            Some(Value::String(s)) => s.split_whitespace().collect(),
+            Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let any_of = |roles: &[String]| values.iter().any(|v| roles.iter().any(|r| r == v));
+        if any_of(&self.admin_roles) {
+            Role::Admin
+        } else if any_of(&self.curator_roles) {
+            Role::Curator
+        } else {
+            Role::User
+        }
+    }
+}
+
+/// Sign-in configuration, or `None` when OIDC is not set up
+pub fn config() -> Option<&'static OidcConfig> {
+    CONFIG.as_ref()
+}
+
+/// The endpoints from the issuer's discovery document
+#[derive(Debug, Clone, Deserialize)]
+pub struct Provider {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// Fetch the issuer's discovery document.
+///
+/// # Errors
+/// Returns an error if the document cannot be fetched or is for another issuer.
+pub async fn discover(issuer: &str) -> Result<Provider> {
+    let url = format!("{issuer}/.well-known/openid-configuration");
+    let provider: Provider = HTTP
+        .get(&url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .with_context(|| format!("Failed to fetch {url}"))?
+        .json()
+        .await
+        .with_context(|| format!("Invalid discovery document at {url}"))?;
+    if provider.issuer.trim_end_matches('/') != issuer {
+        bail!("Discovery document is for issuer {}", provider.issuer);
+    }
+    Ok(provider)
+}
+
+/// The configured provider, discovered on first use
+///
+/// # Errors
+/// Returns an error if discovery fails; the next call tries again.
+pub async fn provider(config: &OidcConfig) -> Result<&'static Provider> {
+    PROVIDER.get_or_try_init(|| discover(&config.issuer)).await
+}
+
+/// What must survive the round trip through the provider, kept in the session
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingLogin {
+    pub state: String,
+    pub nonce: String,
+    pub verifier: String,
+    /// Local path to go back to afterwards
+    pub return_to: String,
+}
+
+impl PendingLogin {
+    pub fn new(return_to: &str) -> Self {
+        Self {
+            state: generate_secure_token(),
+            nonce: generate_secure_token(),
+            // PKCE verifiers must be 43 to 128 characters
+            verifier: format!("{}{}", generate_secure_token(), generate_secure_token()),
+            return_to: local_path(return_to),
+        }
+    }
+
+    pub fn state_matches(&self, state: &str) -> bool {
+        bool::from(self.state.as_bytes().ct_eq(state.as_bytes()))
+    }
+}
+
+/// `path` if it stays on this site, otherwise `/`. Browsers drop tabs and
+/// newlines from `Location`, so `/\t/evil.example` would become
+/// `//evil.example`; any whitespace or control character is refused.
+fn local_path(path: &str) -> String {
+    if path.starts_with('/')
+        && !path.starts_with("//")
+        && !path.contains('\\')
+        && !path.chars().any(|c| c.is_whitespace() || c.is_control())
+    {
+        path.to_string()
+    } else {
+        "/".to_string()
+    }
+}
+
+/// Where to send the browser to sign in.
+///
+/// # Errors
+/// Returns an error if the provider's authorization endpoint is not a URL.
+pub fn authorization_url(
+    config: &OidcConfig,
+    provider: &Provider,
+    pending: &PendingLogin,
+) -> Result<String> {
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(pending.verifier.as_bytes()));
+    let mut url =
+        Url::parse(&provider.authorization_endpoint).context("Invalid authorization endpoint")?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", &config.redirect_url)
+        .append_pair("scope", &config.scopes)
+        .append_pair("state", &pending.state)
+        .append_pair("nonce", &pending.nonce)
+        .append_pair("code_challenge", &challenge)
+        .append_pair("code_challenge_method", "S256");
+    Ok(url.into())
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    name: Option<String>,
+    preferred_username: Option<String>,
+    picture: Option<String>,
+    #[serde(flatten)]
+    extra: HashMap<String, Value>,
+}
+
+/// Redeem an authorization code and verify the ID token it returns.
+///
+/// # Errors
+/// Returns an error if the exchange fails or the ID token is invalid.
+pub async fn exchange_code(
+    config: &OidcConfig,
+    provider: &Provider,
+    code: &str,
+    pending: &PendingLogin,
+) -> Result<Profile> {
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", config.redirect_url.as_str()),
+        ("client_id", config.client_id.as_str()),
+        ("code_verifier", pending.verifier.as_str()),
+    ];
+    if let Some(secret) = &config.client_secret {
+        form.push(("client_secret", secret));
+    }
+    let tokens: TokenResponse = HTTP
+        .post(&provider.token_endpoint)
+        .form(&form)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .context("Token request failed")?
+        .json()
+        .await
+        .context("Invalid token response")?;
+
+    let jwks = if is_hmac(decode_header(&tokens.id_token)?.alg) {
+        JwkSet { keys: Vec::new() }
+    } else {
+        HTTP.get(&provider.jwks_uri)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("Failed to fetch the provider's keys")?
+            .json()
+            .await
+            .context("Invalid JWKS")?
+    };
+    let claims = verify_id_token(config, provider, &jwks, &tokens.id_token, &pending.nonce)?;
+
+    Ok(Profile {
+        issuer: provider.issuer.clone(),
+        role: config.role(&claims.extra),
+        subject: claims.sub,
+        email: claims.email,
+        name: claims.name.or(claims.preferred_username),
+        picture: claims.picture,
+    })
+}
+
+fn is_hmac(alg: Algorithm) -> bool {
+    matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+/// Check the ID token's signature, issuer, audience, expiry and nonce. HMAC
+/// tokens are keyed with the client secret, as OIDC specifies.
+fn verify_id_token(
+    config: &OidcConfig,
+    provider: &Provider,
+    jwks: &JwkSet,
+    id_token: &str,
+    nonce: &str,
+) -> Result<Claims> {
+    let header = decode_header(id_token).context("Malformed ID token")?;
+    let key = if is_hmac(header.alg) {
+        let secret = config
+            .client_secret
+            .as_ref()
+            .ok_or_else(|| anyhow!("HMAC-signed ID token without a client secret"))?;
+        DecodingKey::from_secret(secret.as_bytes())
+    } else {
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None => jwks.keys.first(),
+        }
+        .ok_or_else(|| anyhow!("No provider key matches the ID token"))?;
+        if let Some(alg) = jwk.common.key_algorithm
+            && alg.to_string().parse::<Algorithm>().ok() != Some(header.alg)
+        {
+            bail!("ID token algorithm does not match its key");
+        }
+        DecodingKey::from_jwk(jwk).context("Unusable provider key")?
+    };
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[&config.client_id]);
+    validation.set_issuer(&[&provider.issuer]);
+    let claims = decode::<Claims>(id_token, &key, &validation)
+        .context("ID token rejected")?
+        .claims;
+    if !claims
+        .nonce
+        .as_deref()
+        .is_some_and(|n| bool::from(n.as_bytes().ct_eq(nonce.as_bytes())))
+    {
+        bail!("ID token nonce does not match the sign-in");
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, HttpResponse, HttpServer, web};
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde_json::json;
+
+    const SECRET: &str = "mock-client-secret";
+
+    fn config(issuer: &str) -> OidcConfig {
+        OidcConfig {
+            issuer: issuer.to_string(),
+            client_id: "vtt-maps".to_string(),
+            client_secret: Some(SECRET.to_string()),
+            redirect_url: "http://localhost:8080/api/v1/auth/callback".to_string(),
+            scopes: DEFAULT_SCOPES.to_string(),
+            role_claim: "groups".to_string(),
+            admin_roles: vec!["wizards".to_string()],
+            curator_roles: vec!["dm".to_string()],
+        }
+    }
+
+    /// A local identity provider that signs whatever nonce the code carries
+    async fn mock_provider() -> String {
+        let server = HttpServer::new(|| {
+            App::new()
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(|req: actix_web::HttpRequest| async move {
+                        let base = format!("http://{}", req.connection_info().host());
+                        HttpResponse::Ok().json(json!({
+                            "issuer": base,
+                            "authorization_endpoint": format!("{base}/authorize"),
+                            "token_endpoint": format!("{base}/token"),
+                            "jwks_uri": format!("{base}/jwks"),
+                        }))
+                    }),
+                )
+                .route(
+                    "/token",
+                    web::post().to(
+                        |req: actix_web::HttpRequest,
+                         form: web::Form<HashMap<String, String>>| async move {
+                            let base = format!("http://{}", req.connection_info().host());
+                            let claims = json!({
+                                "iss": base,
+                                "aud": form["client_id"],
+                                "sub": "mock-user",
+                                "exp": crate::utils::admin_token::now() + 300,
+                                "nonce": form["code"].trim_start_matches("code-"),
+                                "email": "dm@example.com",
+                                "preferred_username": "dungeon-master",
+                                "groups": ["players", "dm"],
+                            });
+                            let id_token = encode(
+                                &Header::default(),
+                                &claims,
+                                &EncodingKey::from_secret(SECRET.as_bytes()),
+                            )
+                            .unwrap();
+                            HttpResponse::Ok().json(json!({ "id_token": id_token }))
+                        },
+                    ),
+                )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let base = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        base
+    }
+
+    #[actix_web::test]
+    async fn test_code_flow_against_mock_provider() {
+        let issuer = mock_provider().await;
+        let config = config(&issuer);
+        let provider = discover(&issuer).await.unwrap();
+
+        let pending = PendingLogin::new("https://evil.example/");
+        assert_eq!(pending.return_to, "/");
+        for sneaky in ["/\t/evil.example", "/\n/evil.example", "/ /evil.example"] {
+            assert_eq!(PendingLogin::new(sneaky).return_to, "/", "{sneaky:?}");
+        }
+        assert_eq!(
+            PendingLogin::new("/maps/abc?x=1").return_to,
+            "/maps/abc?x=1"
+        );
+        let url = Url::parse(&authorization_url(&config, &provider, &pending).unwrap()).unwrap();
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["state"], pending.state);
+        assert_eq!(params["code_challenge_method"], "S256");
+
+        let code = format!("code-{}", pending.nonce);
+        let profile = exchange_code(&config, &provider, &code, &pending)
+            .await
+            .unwrap();
+        assert_eq!(profile.subject, "mock-user");
+        assert_eq!(profile.name.as_deref(), Some("dungeon-master"));
+        assert_eq!(profile.role, Role::Curator);
+
+        // A code minted for another sign-in carries the wrong nonce
+        let replayed = exchange_code(&config, &provider, "code-other", &pending).await;
+        assert!(replayed.is_err());
+    }
+
+    #[test]
+    fn test_role_claim_paths() {
+        let mut config = config("http://idp.test");
+        config.role_claim = "realm_access.roles".to_string();
+        let claims: HashMap<String, Value> =
+            serde_json::from_value(json!({ "realm_access": { "roles": ["dm", "wizards"] } }))
+                .unwrap();
+        assert_eq!(config.role(&claims), Role::Admin);
+        assert_eq!(config.role(&HashMap::new()), Role::User);
+    }
+}
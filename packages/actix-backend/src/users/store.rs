@@ -0,0 +1,259 @@
+//! User profiles, kept in `.users.json` in the root dir (or `USERS_FILE`).
+
+use crate::utils::admin_token::{Scope, now};
+use crate::utils::private_file;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shared::utils::root_dir::root_dir;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
+use utoipa::ToSchema;
+
+const USERS_FILE: &str = ".users.json";
+
+/// Serializes read-modify-write cycles within this process
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// What a signed-in user may do; admin routes map it onto token scopes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    User,
+    /// Edits map content and rebuilds the index
+    Curator,
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::User, Role::Curator, Role::Admin];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Curator => "curator",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Admin token scopes the role stands in for
+    pub fn scopes(self) -> &'static [Scope] {
+        match self {
+            Role::User => &[],
+            Role::Curator => &[Scope::Rebuild, Scope::ContentEdit],
+            Role::Admin => &Scope::ALL,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Role::ALL
+            .into_iter()
+            .find(|role| role.as_str() == s)
+            .ok_or_else(|| format!("Unknown role: {s} (expected user, curator or admin)"))
+    }
+}
+
+/// A person who signed in through the identity provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    /// Derived from the issuer and subject, so it survives email changes
+    pub id: String,
+    pub issuer: String,
+    pub subject: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub picture: Option<String>,
+    /// Role mapped from the provider's claims at the last sign-in
+    pub claimed_role: Role,
+    /// Set with `actix-backend users role`; wins over the claimed role
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_override: Option<Role>,
+    /// Unix seconds
+    pub created_at: u64,
+    pub last_login_at: u64,
+}
+
+impl User {
+    pub fn role(&self) -> Role {
+        self.role_override.unwrap_or(self.claimed_role)
+    }
+}
+
+/// What the identity provider says about a user at sign-in
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub issuer: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    pub role: Role,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UserFile {
+    users: Vec<User>,
+}
+
+pub fn user_id(issuer: &str, subject: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(format!("{issuer}\n{subject}")));
+    digest[..16].to_string()
+}
+
+fn users_path() -> Result<PathBuf> {
+    if let Ok(path) = env::var("USERS_FILE")
+        && !path.trim().is_empty()
+    {
+        return Ok(PathBuf::from(path));
+    }
+    Ok(root_dir()?.join(USERS_FILE))
+}
+
+fn read_users(path: &Path) -> Result<Vec<User>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read users from {}", path.display()))?;
+    let file: UserFile = serde_json::from_str(&raw)
+        .with_context(|| format!("Invalid users file {}", path.display()))?;
+    Ok(file.users)
+}
+
+/// Write the store readable by the owner only, without ever exposing a
+/// partial file to `find_user`
+fn write_users(path: &Path, users: Vec<User>) -> Result<()> {
+    let json = serde_json::to_vec_pretty(&UserFile { users })?;
+    private_file::replace(path, &json)
+        .with_context(|| format!("Failed to write users to {}", path.display()))
+}
+
+fn update_users<T>(path: &Path, change: impl FnOnce(&mut Vec<User>) -> Result<T>) -> Result<T> {
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut users = read_users(path)?;
+    let result = change(&mut users)?;
+    write_users(path, users)?;
+    Ok(result)
+}
+
+/// Create or refresh the user for a sign-in
+fn record_login_at(path: &Path, profile: Profile) -> Result<User> {
+    let id = user_id(&profile.issuer, &profile.subject);
+    update_users(path, |users| {
+        let now = now();
+        let user = match users.iter_mut().find(|u| u.id == id) {
+            Some(user) => user,
+            None => {
+                users.push(User {
+                    id,
+                    issuer: profile.issuer.clone(),
+                    subject: profile.subject.clone(),
+                    email: None,
+                    name: None,
+                    picture: None,
+                    claimed_role: Role::User,
+                    role_override: None,
+                    created_at: now,
+                    last_login_at: now,
+                });
+                users.last_mut().expect("just pushed")
+            }
+        };
+        user.email = profile.email;
+        user.name = profile.name;
+        user.picture = profile.picture;
+        user.claimed_role = profile.role;
+        user.last_login_at = now;
+        Ok(user.clone())
+    })
+}
+
+/// Create or refresh the user for a sign-in.
+///
+/// # Errors
+/// Returns an error if the user store cannot be read or written.
+pub fn record_login(profile: Profile) -> Result<User> {
+    record_login_at(&users_path()?, profile)
+}
+
+/// # Errors
+/// Returns an error if the user store cannot be read.
+pub fn find_user(id: &str) -> Result<Option<User>> {
+    Ok(read_users(&users_path()?)?
+        .into_iter()
+        .find(|user| user.id == id))
+}
+
+/// # Errors
+/// Returns an error if the user store cannot be read.
+pub fn list_users() -> Result<Vec<User>> {
+    read_users(&users_path()?)
+}
+
+/// Override a user's role, or with `None` go back to the claimed one.
+///
+/// # Errors
+/// Returns an error if there is no such user or the store cannot be written.
+pub fn set_role(id: &str, role: Option<Role>) -> Result<User> {
+    update_users(&users_path()?, |users| {
+        let Some(user) = users.iter_mut().find(|u| u.id == id) else {
+            bail!("No user with id {id:?}");
+        };
+        user.role_override = role;
+        Ok(user.clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(email: &str, role: Role) -> Profile {
+        Profile {
+            issuer: "http://idp.test".to_string(),
+            subject: "42".to_string(),
+            email: Some(email.to_string()),
+            name: None,
+            picture: None,
+            role,
+        }
+    }
+
+    #[test]
+    fn test_login_refreshes_profile_and_keeps_override() {
+        let path = env::temp_dir().join(format!("users-{}.json", std::process::id()));
+        let first = record_login_at(&path, profile("old@example.com", Role::User)).unwrap();
+        update_users(&path, |users| {
+            users[0].role_override = Some(Role::Curator);
+            Ok(())
+        })
+        .unwrap();
+
+        let again = record_login_at(&path, profile("new@example.com", Role::User)).unwrap();
+        assert_eq!(again.id, first.id);
+        assert_eq!(again.email.as_deref(), Some("new@example.com"));
+        assert_eq!(again.role(), Role::Curator);
+        assert_eq!(read_users(&path).unwrap().len(), 1);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!("curator".parse::<Role>(), Ok(Role::Curator));
+        assert!(Role::Admin.scopes().contains(&Scope::Tokens));
+    }
+}
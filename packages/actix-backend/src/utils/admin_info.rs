@@ -3,23 +3,37 @@ use serde_json::json;
 
 /// Get admin token info without revealing the actual token.
 /// This is a public endpoint that shows how to obtain admin access.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/token/info",
+    tag = "admin",
+    responses((status = 200, description = "How to obtain and send the admin token", body = Object))
+)]
 pub async fn get_admin_token_info() -> ActixResult<HttpResponse> {
     Ok(HttpResponse::Ok().json(json!({
-        "message": "Admin token required for destructive operations",
+        "message": "Admin token with the endpoint's scope required for destructive operations",
         "how_to_get_token": {
-            "file": ".admin-token file in project root (auto-generated on first run)",
-            "environment": "ADMIN_TOKEN environment variable (overrides file)",
-            "logs": "Check server startup logs for auto-generated token"
+            "file": ".admin-tokens.json in project root (or ADMIN_TOKENS_FILE) stores named, hashed tokens",
+            "environment": "ADMIN_TOKEN environment variable adds a token with every scope",
+            "first_run": "A default token is generated on first run and its secret printed once to stderr",
+            "cli": "actix-backend tokens create <name> --scope rebuild"
         },
+        "scopes": ["rebuild", "lock-clear", "content-edit", "tokens", "audit"],
         "usage_examples": {
             "header_bearer": "Authorization: Bearer <token>",
             "header_direct": "Authorization: <token>", 
-            "query_param": "?admin_token=<token>"
+            "query_param": "?admin_token=<token>",
+            "session": "POST /api/v1/admin/login with {\"token\": \"<token>\"}, then send the returned csrf_token as X-CSRF-Token on POST and DELETE"
         },
         "protected_endpoints": [
-            "POST /api/maps/rebuild - Rebuild search index",
-            "DELETE /api/maps/rebuild/clear - Clear rebuild lock"
+            "POST /api/v1/maps/rebuild - Rebuild search index (rebuild)",
+            "DELETE /api/v1/maps/rebuild/clear - Clear rebuild lock (lock-clear)",
+            "GET/POST /api/v1/admin/tokens - List or create tokens (tokens)",
+            "POST /api/v1/admin/tokens/{name}/rotate - Rotate a token's secret (tokens)",
+            "DELETE /api/v1/admin/tokens/{name} - Revoke a token (tokens)",
+            "GET /api/v1/admin/audit - Page through the audit log (audit)"
         ],
-        "note": "The token is automatically generated and saved to .admin-token file on first server startup if not provided via environment variable."
+        "api_docs": "/api/openapi.json",
+        "note": "Tokens may expire; an expired token is rejected like an unknown one."
     })))
 }
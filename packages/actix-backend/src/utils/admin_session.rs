@@ -0,0 +1,284 @@
+//! Cookie login sessions for admin tokens.
+//!
+//! `POST /admin/login` exchanges a token for an identity session named
+//! `token:<name>`, so browsers need not keep the secret around. A session
+//! ends with its token: rotating, revoking or expiring the token logs it out.
+//! State-changing requests authenticated by a session must echo the session's
+//! CSRF token in `X-CSRF-Token`. Users signing in through OIDC get the same
+//! kind of session, named `user:<id>`.
+
+use crate::api::ApiError;
+use crate::utils::admin_token::{
+    AdminToken, Scope, active_token, authenticate, generate_secure_token, secret_fingerprint,
+};
+use crate::utils::audit_log::{self, AuditEntry};
+use actix_identity::{Identity, IdentityExt};
+use actix_session::SessionExt;
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse, ResponseError,
+    http::{
+        Method,
+        header::{CacheControl, CacheDirective},
+    },
+    web,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use subtle::ConstantTimeEq;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+/// Header carrying the CSRF token on state-changing session requests
+pub const CSRF_HEADER: &str = "x-csrf-token";
+const IDENTITY_PREFIX: &str = "token:";
+const SESSION_CSRF: &str = "admin_csrf";
+/// [`secret_fingerprint`] of the token a session logged in with
+const SESSION_SECRET: &str = "admin_secret";
+
+#[derive(Deserialize, ToSchema)]
+pub struct Login {
+    /// An admin token's secret
+    token: String,
+}
+
+/// The token behind the current session
+#[derive(Serialize, ToSchema)]
+pub struct AdminSession {
+    /// Token name
+    token: String,
+    scopes: Vec<Scope>,
+    /// Unix seconds when the token expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    /// Send as `X-CSRF-Token` on POST, PUT, PATCH and DELETE requests
+    csrf_token: String,
+}
+
+impl AdminSession {
+    fn new(token: AdminToken, csrf_token: String) -> Self {
+        Self {
+            token: token.name,
+            scopes: token.scopes,
+            expires_at: token.expires_at,
+            csrf_token,
+        }
+    }
+}
+
+/// A still valid login session
+pub struct SessionLogin {
+    pub token: AdminToken,
+    csrf: String,
+}
+
+impl SessionLogin {
+    pub fn allows(&self, req: &HttpRequest) -> bool {
+        csrf_ok(req, &self.csrf)
+    }
+}
+
+/// Whether `req` may proceed in a session with the CSRF token `csrf`: safe
+/// methods always, others only when they send it
+pub fn csrf_ok(req: &HttpRequest, csrf: &str) -> bool {
+    !needs_csrf(req.method())
+        || req
+            .headers()
+            .get(CSRF_HEADER)
+            .is_some_and(|sent| bool::from(sent.as_bytes().ct_eq(csrf.as_bytes())))
+}
+
+fn needs_csrf(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// The identity of a login session, such as `token:ci`
+pub struct OpenSession {
+    pub id: String,
+    pub csrf: String,
+}
+
+/// Log the request's session in as `id`, returning the session's CSRF token
+pub fn start_session(req: &HttpRequest, id: String) -> Result<String, ApiError> {
+    let csrf = generate_secure_token();
+    Identity::login(&req.extensions(), id).map_err(|e| {
+        error!("❌ Failed to open session: {:?}", e);
+        ApiError::internal("Failed to open session")
+    })?;
+    req.get_session().insert(SESSION_CSRF, &csrf).map_err(|e| {
+        error!("❌ Failed to store session: {:?}", e);
+        ApiError::internal("Failed to open session")
+    })?;
+    Ok(csrf)
+}
+
+/// The request's login session, if it has one
+pub fn current_session(req: &HttpRequest) -> Option<OpenSession> {
+    let id = req.get_identity().ok()?.id().ok()?;
+    let csrf = req
+        .get_session()
+        .get::<String>(SESSION_CSRF)
+        .ok()
+        .flatten()?;
+    Some(OpenSession { id, csrf })
+}
+
+/// The token a request's login session was opened with, if the session is
+/// still valid.
+///
+/// # Errors
+/// Returns an error if the token store cannot be read.
+pub fn session_login(req: &HttpRequest) -> Result<Option<SessionLogin>> {
+    let Some(OpenSession { id, csrf }) = current_session(req) else {
+        return Ok(None);
+    };
+    let Some(name) = id.strip_prefix(IDENTITY_PREFIX) else {
+        return Ok(None);
+    };
+    let Some(secret) = req.get_session().get::<String>(SESSION_SECRET)? else {
+        return Ok(None);
+    };
+
+    // A token re-created or rotated after login has a secret the session never saw
+    Ok(active_token(name, &secret)?.map(|token| SessionLogin { token, csrf }))
+}
+
+/// Exchange an admin token for a login session cookie
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/login",
+    tag = "admin",
+    request_body = Login,
+    responses(
+        (status = 200, description = "Session opened; keep the CSRF token", body = AdminSession),
+        (status = 403, description = "Invalid or expired admin token", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn login(
+    req: HttpRequest,
+    body: web::Json<Login>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let started = Instant::now();
+    let mut entry = AuditEntry::begin(&req);
+
+    let result = open_session(&req, &body.token, &mut entry);
+    let status = match &result {
+        Ok(res) => res.status(),
+        Err(e) => e.status_code(),
+    };
+    entry.finish(status, started.elapsed());
+    audit_log::record(entry).await;
+    Ok(result?)
+}
+
+fn open_session(
+    req: &HttpRequest,
+    secret: &str,
+    entry: &mut AuditEntry,
+) -> Result<HttpResponse, ApiError> {
+    let token = match authenticate(secret) {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            warn!("❌ Admin login with an invalid token");
+            return Err(ApiError::forbidden("Invalid or expired admin token"));
+        }
+        Err(e) => {
+            error!("❌ Error validating admin token for login: {:?}", e);
+            return Err(ApiError::internal("Authentication service unavailable"));
+        }
+    };
+    entry.token = Some(token.name.clone());
+
+    let csrf = start_session(req, format!("{IDENTITY_PREFIX}{}", token.name))?;
+    req.get_session()
+        .insert(SESSION_SECRET, secret_fingerprint(secret))
+        .map_err(|e| {
+            error!("❌ Failed to store session: {:?}", e);
+            ApiError::internal("Failed to open session")
+        })?;
+
+    info!("🔑 Admin session opened for token {:?}", token.name);
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(AdminSession::new(token, csrf)))
+}
+
+/// End the current admin session
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/logout",
+    tag = "admin",
+    responses((status = 204, description = "Logged out, or there was no session"))
+)]
+pub async fn logout(identity: Option<Identity>) -> HttpResponse {
+    if let Some(identity) = identity {
+        if let Ok(id) = identity.id() {
+            info!("🔒 Admin session closed for {}", id);
+        }
+        identity.logout();
+    }
+    HttpResponse::NoContent().finish()
+}
+
+/// Show the token behind the current session and its CSRF token
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/session",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The current session", body = AdminSession),
+        (status = 401, description = "No valid session", body = ApiError),
+    )
+)]
+pub async fn get_admin_session(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
+    let login = session_login(&req).map_err(|e| {
+        error!("❌ Error validating admin session: {:?}", e);
+        ApiError::internal("Authentication service unavailable")
+    })?;
+    let Some(SessionLogin { token, csrf }) = login else {
+        return Err(ApiError::unauthorized("Not logged in").into());
+    };
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(AdminSession::new(token, csrf)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn session() -> SessionLogin {
+        SessionLogin {
+            token: serde_json::from_value(serde_json::json!({
+                "name": "ci",
+                "scopes": [Scope::Rebuild],
+                "created_at": 0,
+            }))
+            .unwrap(),
+            csrf: "abc".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_state_changes_need_csrf_token() {
+        let login = session();
+        assert!(login.allows(&TestRequest::get().to_http_request()));
+        assert!(!login.allows(&TestRequest::post().to_http_request()));
+        assert!(
+            !login.allows(
+                &TestRequest::delete()
+                    .insert_header((CSRF_HEADER, "abd"))
+                    .to_http_request()
+            )
+        );
+        assert!(
+            login.allows(
+                &TestRequest::post()
+                    .insert_header((CSRF_HEADER, "abc"))
+                    .to_http_request()
+            )
+        );
+    }
+}
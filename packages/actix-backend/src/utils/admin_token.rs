@@ -1,83 +1,316 @@
+//! Named admin tokens with scopes and optional expiry.
+//!
+//! Tokens are stored in `.admin-tokens.json` (or `ADMIN_TOKENS_FILE`) as
+//! SHA-256 hashes of their secrets. Secrets are 32 random alphanumerics, so a
+//! slow password hash would add nothing but latency. The file is cached in
+//! memory and re-read only when its modification time changes, so edits from
+//! the `tokens` CLI apply to a running server. `ADMIN_TOKEN` adds one more
+//! token, named `env`, with every scope.
+
+use crate::utils::private_file;
 use anyhow::{Context, Result};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use shared::utils::root_dir::root_dir;
-use std::{env, fs, path::PathBuf};
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex, PoisonError, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
 use tracing::{info, warn};
+use utoipa::ToSchema;
 
 const TOKEN_LENGTH: usize = 32;
-const ADMIN_TOKEN_FILE: &str = ".admin-token";
+const MAX_NAME_LENGTH: usize = 64;
+const ADMIN_TOKENS_FILE: &str = ".admin-tokens.json";
+/// Single plaintext token from before named tokens; migrated on startup
+const LEGACY_TOKEN_FILE: &str = ".admin-token";
+/// Name of the token read from `ADMIN_TOKEN`
+const ENV_TOKEN_NAME: &str = "env";
+/// Actor recorded for changes the server makes on its own
+const SYSTEM_ACTOR: &str = "system";
 
-/// Gets or generates an admin token for protecting destructive operations.
-///
-/// # Priority
-/// 1. ADMIN_TOKEN environment variable
-/// 2. .admin-token file in the project root
-/// 3. Generate new token and save to .admin-token file
-///
-/// # Errors
-/// Returns an error if the token file cannot be read or written.
-pub fn get_or_create_admin_token() -> Result<String> {
-    // Check environment variable first
-    if let Ok(token) = env::var("ADMIN_TOKEN") {
-        if !token.trim().is_empty() {
-            info!("🔑 Using admin token from ADMIN_TOKEN environment variable");
-            return Ok(token.trim().to_string());
+/// What an admin token may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    Rebuild,
+    LockClear,
+    ContentEdit,
+    /// Create, rotate and revoke admin tokens
+    Tokens,
+    /// Read the audit log
+    Audit,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 5] = [
+        Scope::Rebuild,
+        Scope::LockClear,
+        Scope::ContentEdit,
+        Scope::Tokens,
+        Scope::Audit,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Rebuild => "rebuild",
+            Scope::LockClear => "lock-clear",
+            Scope::ContentEdit => "content-edit",
+            Scope::Tokens => "tokens",
+            Scope::Audit => "audit",
         }
     }
+}
 
-    // Check for existing token file
-    let token_path = admin_token_path()?;
-    if token_path.exists() {
-        match fs::read_to_string(&token_path) {
-            Ok(token) => {
-                let token = token.trim().to_string();
-                if !token.is_empty() {
-                    info!("🔑 Using admin token from file: {}", token_path.display());
-                    return Ok(token);
-                }
-                warn!("⚠️  Admin token file is empty, generating new token");
-            }
-            Err(e) => {
-                warn!(
-                    "⚠️  Failed to read admin token file: {}, generating new token",
-                    e
-                );
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| {
+                format!("Unknown scope: {s} (expected rebuild, lock-clear, content-edit, tokens or audit)")
+            })
+    }
+}
+
+/// An admin token as listed; the secret is never kept and its hash never shown
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AdminToken {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Unix seconds
+    pub created_at: u64,
+    /// Who created the token, such as `token:ci` or `cli:alice`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    /// Unix seconds; `None` never expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated_by: Option<String>,
+    /// Revoked tokens are kept so the record of who revoked them survives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_by: Option<String>,
+}
+
+impl AdminToken {
+    fn new(name: &str, scopes: Vec<Scope>, expires_at: Option<u64>, actor: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            scopes,
+            created_at: now(),
+            created_by: Some(actor.to_string()),
+            expires_at,
+            rotated_at: None,
+            rotated_by: None,
+            revoked_at: None,
+            revoked_by: None,
+        }
+    }
+
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    /// `active`, `expired` or `revoked`
+    pub fn status(&self) -> &'static str {
+        if self.is_revoked() {
+            "revoked"
+        } else if self.is_expired(now()) {
+            "expired"
+        } else {
+            "active"
+        }
+    }
+}
+
+/// A token as written to the store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredToken {
+    #[serde(flatten)]
+    token: AdminToken,
+    /// Hex SHA-256 of the secret
+    hash: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TokenFile {
+    tokens: Vec<StoredToken>,
+}
+
+/// Why a token change was refused
+#[derive(Debug)]
+pub enum TokenError {
+    Invalid(String),
+    Exists(String),
+    NotFound(String),
+    /// The caller lacks a scope the token has or would have
+    Forbidden(Scope),
+    Store(anyhow::Error),
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenError::Invalid(msg) => f.write_str(msg),
+            TokenError::Exists(name) => write!(f, "An admin token named {name:?} already exists"),
+            TokenError::NotFound(name) => write!(f, "No active admin token named {name:?}"),
+            TokenError::Forbidden(scope) => {
+                write!(f, "Only holders of the `{scope}` scope may manage it")
             }
+            TokenError::Store(e) => write!(f, "Admin token store unavailable: {e}"),
         }
     }
+}
+
+impl std::error::Error for TokenError {}
+
+impl From<anyhow::Error> for TokenError {
+    fn from(e: anyhow::Error) -> Self {
+        TokenError::Store(e)
+    }
+}
 
-    // Generate new token
-    let token = generate_secure_token();
-    fs::write(&token_path, &token)
-        .with_context(|| format!("Failed to write admin token to {}", token_path.display()))?;
+/// Tokens as of the file's last modification time
+struct Cache {
+    tokens: Vec<StoredToken>,
+    modified: Option<SystemTime>,
+}
 
-    info!(
-        "🔑 Generated new admin token and saved to: {}",
-        token_path.display()
-    );
-    info!("🔐 Admin token: {}", token);
-    info!("💡 You can also set the ADMIN_TOKEN environment variable to override this file");
+static CACHE: LazyLock<RwLock<Option<Cache>>> = LazyLock::new(|| RwLock::new(None));
 
-    Ok(token)
+/// Serializes read-modify-write cycles within this process; `lock_store`
+/// serializes them with other processes such as the CLI
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
-/// Validates if the provided token matches the admin token.
-///
-/// # Errors
-/// Returns an error if the admin token cannot be retrieved.
-pub fn validate_admin_token(provided_token: &str) -> Result<bool> {
-    let admin_token = get_or_create_admin_token()?;
-    Ok(admin_token == provided_token.trim())
+fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.trim().as_bytes()))
+}
+
+/// Gets the path to the token store.
+fn tokens_path() -> Result<PathBuf> {
+    if let Ok(path) = env::var("ADMIN_TOKENS_FILE")
+        && !path.trim().is_empty()
+    {
+        return Ok(PathBuf::from(path));
+    }
+    Ok(root_dir()?.join(ADMIN_TOKENS_FILE))
+}
+
+fn file_modified(path: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn read_tokens(path: &PathBuf) -> Result<Vec<StoredToken>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read admin tokens from {}", path.display()))?;
+    let file: TokenFile = serde_json::from_str(&raw)
+        .with_context(|| format!("Invalid admin tokens file {}", path.display()))?;
+    Ok(file.tokens)
+}
+
+/// Write the store readable by the owner only, and drop the cached copy
+fn write_tokens(path: &Path, tokens: Vec<StoredToken>) -> Result<()> {
+    let json = serde_json::to_vec_pretty(&TokenFile { tokens })?;
+    private_file::replace(path, &json)
+        .with_context(|| format!("Failed to write admin tokens to {}", path.display()))?;
+    *CACHE.write().unwrap_or_else(PoisonError::into_inner) = None;
+    Ok(())
+}
+
+/// Take an advisory lock on `{store}.lock`, held until the file is dropped
+fn lock_store(path: &Path) -> Result<File> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", Path::new(&lock_path).display()))?;
+    file.lock()
+        .with_context(|| format!("Failed to lock {}", Path::new(&lock_path).display()))?;
+    Ok(file)
+}
+
+/// Apply `change` to the stored tokens and save them
+fn update_tokens<T>(
+    change: impl FnOnce(&mut Vec<StoredToken>) -> Result<T, TokenError>,
+) -> Result<T, TokenError> {
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let path = tokens_path()?;
+    let _lock = lock_store(&path)?;
+    let mut tokens = read_tokens(&path)?;
+    let result = change(&mut tokens)?;
+    write_tokens(&path, tokens)?;
+    Ok(result)
 }
 
-/// Gets the path to the admin token file.
-fn admin_token_path() -> Result<PathBuf> {
-    let root = root_dir()?;
-    Ok(root.join(ADMIN_TOKEN_FILE))
+/// Callers can only hand out scopes they hold themselves
+fn check_held(scopes: &[Scope], held: &[Scope]) -> Result<(), TokenError> {
+    match scopes.iter().find(|scope| !held.contains(scope)) {
+        Some(scope) => Err(TokenError::Forbidden(*scope)),
+        None => Ok(()),
+    }
+}
+
+fn active_mut<'a>(tokens: &'a mut [StoredToken], name: &str) -> Option<&'a mut StoredToken> {
+    tokens
+        .iter_mut()
+        .find(|t| t.token.name == name && !t.token.is_revoked())
+}
+
+fn env_token() -> Option<StoredToken> {
+    let secret = env::var("ADMIN_TOKEN").ok()?;
+    if secret.trim().is_empty() {
+        return None;
+    }
+    Some(StoredToken {
+        token: AdminToken {
+            created_at: 0,
+            ..AdminToken::new(ENV_TOKEN_NAME, Scope::ALL.to_vec(), None, SYSTEM_ACTOR)
+        },
+        hash: hash_secret(&secret),
+    })
 }
 
 /// Generates a cryptographically secure random token.
-fn generate_secure_token() -> String {
+pub(crate) fn generate_secure_token() -> String {
     let mut rng = rand::thread_rng();
     (0..TOKEN_LENGTH)
         .map(|_| {
@@ -91,10 +324,272 @@ fn generate_secure_token() -> String {
         .collect()
 }
 
+fn validate_name(name: &str) -> Result<(), TokenError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid || name == ENV_TOKEN_NAME {
+        return Err(TokenError::Invalid(format!(
+            "Invalid token name {name:?}: use up to {MAX_NAME_LENGTH} letters, digits, `-`, `_` or `.`"
+        )));
+    }
+    Ok(())
+}
+
+/// All stored tokens, including expired and revoked ones.
+///
+/// # Errors
+/// Returns an error if the token store cannot be read.
+pub fn list_tokens() -> Result<Vec<AdminToken>> {
+    Ok(read_tokens(&tokens_path()?)?
+        .into_iter()
+        .map(|t| t.token)
+        .collect())
+}
+
+/// Create a named token and return it with its secret, which is not stored.
+/// `expires_in` is in seconds.
+///
+/// # Errors
+/// Returns an error if the name is invalid or taken, a scope is not among
+/// `held`, or the store cannot be written.
+pub fn create_token(
+    name: &str,
+    scopes: Vec<Scope>,
+    expires_in: Option<u64>,
+    actor: &str,
+    held: &[Scope],
+) -> Result<(AdminToken, String), TokenError> {
+    let name = name.trim();
+    validate_name(name)?;
+    if scopes.is_empty() {
+        return Err(TokenError::Invalid(
+            "A token needs at least one scope".into(),
+        ));
+    }
+    check_held(&scopes, held)?;
+
+    update_tokens(|tokens| {
+        if active_mut(tokens, name).is_some() {
+            return Err(TokenError::Exists(name.to_string()));
+        }
+        let secret = generate_secure_token();
+        let expires_at = expires_in.map(|secs| now().saturating_add(secs));
+        let token = AdminToken::new(name, scopes, expires_at, actor);
+        tokens.push(StoredToken {
+            token: token.clone(),
+            hash: hash_secret(&secret),
+        });
+        info!("🔑 Admin token {:?} created by {}", name, actor);
+        Ok((token, secret))
+    })
+}
+
+/// Replace a token's secret, keeping its name, scopes and expiry.
+///
+/// # Errors
+/// Returns an error if there is no active token by that name, it has scopes
+/// beyond `held`, or the store cannot be written.
+pub fn rotate_token(
+    name: &str,
+    actor: &str,
+    held: &[Scope],
+) -> Result<(AdminToken, String), TokenError> {
+    update_tokens(|tokens| {
+        let stored =
+            active_mut(tokens, name).ok_or_else(|| TokenError::NotFound(name.to_string()))?;
+        check_held(&stored.token.scopes, held)?;
+        let secret = generate_secure_token();
+        stored.hash = hash_secret(&secret);
+        stored.token.rotated_at = Some(now());
+        stored.token.rotated_by = Some(actor.to_string());
+        info!("🔄 Admin token {:?} rotated by {}", name, actor);
+        Ok((stored.token.clone(), secret))
+    })
+}
+
+/// Revoke a token; it stays listed with who revoked it.
+///
+/// # Errors
+/// Returns an error if there is no active token by that name, it has scopes
+/// beyond `held`, or the store cannot be written.
+pub fn revoke_token(name: &str, actor: &str, held: &[Scope]) -> Result<AdminToken, TokenError> {
+    update_tokens(|tokens| revoke_in(tokens, name, actor, held))
+}
+
+fn revoke_in(
+    tokens: &mut [StoredToken],
+    name: &str,
+    actor: &str,
+    held: &[Scope],
+) -> Result<AdminToken, TokenError> {
+    let stored = active_mut(tokens, name).ok_or_else(|| TokenError::NotFound(name.to_string()))?;
+    check_held(&stored.token.scopes, held)?;
+    stored.token.revoked_at = Some(now());
+    stored.token.revoked_by = Some(actor.to_string());
+    info!("🚫 Admin token {:?} revoked by {}", name, actor);
+    Ok(stored.token.clone())
+}
+
+/// Prepare the token store for protecting destructive operations.
+///
+/// Moves a legacy plaintext `.admin-token` into the store as `default`. If
+/// there are no tokens at all, creates a `default` token with every scope
+/// and prints its secret once to stderr, keeping it out of the logs.
+///
+/// # Errors
+/// Returns an error if the token files cannot be read or written.
+pub fn init_admin_tokens() -> Result<()> {
+    let path = tokens_path()?;
+    let mut tokens = read_tokens(&path)?;
+
+    let legacy = root_dir()?.join(LEGACY_TOKEN_FILE);
+    if let Ok(secret) = fs::read_to_string(&legacy) {
+        if !secret.trim().is_empty() && !tokens.iter().any(|t| t.token.name == "default") {
+            tokens.push(StoredToken {
+                token: AdminToken::new("default", Scope::ALL.to_vec(), None, SYSTEM_ACTOR),
+                hash: hash_secret(&secret),
+            });
+            write_tokens(&path, tokens.clone())?;
+            info!("🔑 Migrated {} into {}", legacy.display(), path.display());
+        }
+        fs::remove_file(&legacy)
+            .with_context(|| format!("Failed to remove {}", legacy.display()))?;
+    }
+
+    if env_token().is_some() {
+        info!("🔑 Accepting the admin token from the ADMIN_TOKEN environment variable");
+    }
+    if tokens.is_empty() && env_token().is_none() {
+        let (_, secret) = create_token(
+            "default",
+            Scope::ALL.to_vec(),
+            None,
+            SYSTEM_ACTOR,
+            &Scope::ALL,
+        )?;
+        warn!(
+            "🔑 Generated a default admin token in {}; its secret was printed to stderr",
+            path.display()
+        );
+        eprintln!("Admin token (shown once, stored hashed): {secret}");
+    } else {
+        info!(
+            "🔑 Loaded {} admin tokens from {}",
+            tokens.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Find the active token whose hash matches `secret`
+fn find_token<'a>(tokens: &'a [StoredToken], secret: &str, now: u64) -> Option<&'a AdminToken> {
+    let hash = hash_secret(secret);
+    // Compare against every token so timing does not reveal which one matched
+    let mut found = None;
+    for stored in tokens {
+        if bool::from(stored.hash.as_bytes().ct_eq(hash.as_bytes()))
+            && !stored.token.is_revoked()
+            && found.is_none()
+        {
+            found = Some(&stored.token);
+        }
+    }
+    let token = found?;
+    if token.is_expired(now) {
+        warn!("⌛ Admin token {:?} has expired", token.name);
+        return None;
+    }
+    Some(token)
+}
+
+/// Run `f` over the cached tokens, reloading them if the file changed
+fn with_tokens<T>(f: impl FnOnce(&[StoredToken]) -> T) -> Result<T> {
+    let path = tokens_path()?;
+    let modified = file_modified(&path);
+    {
+        let cache = CACHE.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(cache) = cache.as_ref()
+            && cache.modified == modified
+        {
+            return Ok(f(&cache.tokens));
+        }
+    }
+
+    let mut tokens = read_tokens(&path)?;
+    tokens.extend(env_token());
+    let result = f(&tokens);
+    *CACHE.write().unwrap_or_else(PoisonError::into_inner) = Some(Cache { tokens, modified });
+    Ok(result)
+}
+
+/// Look up the token presented by a request.
+///
+/// # Errors
+/// Returns an error if the token store cannot be read.
+pub fn authenticate(secret: &str) -> Result<Option<AdminToken>> {
+    with_tokens(|tokens| find_token(tokens, secret, now()).cloned())
+}
+
+/// Identifies a secret without revealing it or its stored hash, so a login
+/// session can tell when its token was rotated or re-created
+pub fn secret_fingerprint(secret: &str) -> String {
+    fingerprint(&hash_secret(secret))
+}
+
+fn fingerprint(hash: &str) -> String {
+    format!("{:x}", Sha256::digest(format!("session:{hash}").as_bytes()))
+}
+
+/// The unexpired, unrevoked token named `name`, if its secret still has
+/// `secret_fingerprint`
+fn find_active<'a>(
+    tokens: &'a [StoredToken],
+    name: &str,
+    secret_fingerprint: &str,
+    now: u64,
+) -> Option<&'a AdminToken> {
+    tokens
+        .iter()
+        .find(|stored| {
+            stored.token.name == name && !stored.token.is_revoked() && !stored.token.is_expired(now)
+        })
+        .filter(|stored| {
+            bool::from(
+                fingerprint(&stored.hash)
+                    .as_bytes()
+                    .ct_eq(secret_fingerprint.as_bytes()),
+            )
+        })
+        .map(|stored| &stored.token)
+}
+
+/// Look up a login session's token by name and [`secret_fingerprint`].
+///
+/// # Errors
+/// Returns an error if the token store cannot be read.
+pub fn active_token(name: &str, secret_fingerprint: &str) -> Result<Option<AdminToken>> {
+    let now = now();
+    with_tokens(|tokens| find_active(tokens, name, secret_fingerprint, now).cloned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn stored(name: &str, secret: &str, expires_at: Option<u64>) -> StoredToken {
+        StoredToken {
+            token: AdminToken {
+                expires_at,
+                ..AdminToken::new(name, vec![Scope::Rebuild], None, "test")
+            },
+            hash: hash_secret(secret),
+        }
+    }
+
     #[test]
     fn test_generate_secure_token() {
         let token = generate_secure_token();
@@ -103,10 +598,121 @@ mod tests {
     }
 
     #[test]
-    fn test_validate_admin_token() {
-        // This test would need to mock the file system or environment
-        // For now, just test that the function signature works
-        let result = validate_admin_token("test");
-        assert!(result.is_ok() || result.is_err()); // Either outcome is valid for testing
+    fn test_find_token_matches_hash_and_expiry() {
+        let mut revoked = stored("gone", "gone-secret", None);
+        revoked.token.revoked_at = Some(1);
+        let tokens = [
+            stored("ci", "ci-secret", None),
+            stored("old", "old-secret", Some(100)),
+            revoked,
+        ];
+        assert_eq!(find_token(&tokens, "ci-secret ", 200).unwrap().name, "ci");
+        assert!(find_token(&tokens, "old-secret", 99).is_some());
+        assert!(find_token(&tokens, "old-secret", 200).is_none());
+        assert!(find_token(&tokens, "gone-secret", 0).is_none());
+        assert!(find_token(&tokens, "nope", 0).is_none());
+    }
+
+    #[test]
+    fn test_sessions_end_when_the_secret_changes() {
+        let tokens = [stored("ci", "old-secret", None)];
+        let session = secret_fingerprint("old-secret");
+        assert_eq!(find_active(&tokens, "ci", &session, 0).unwrap().name, "ci");
+        assert!(find_active(&tokens, "other", &session, 0).is_none());
+
+        // Rotated within the same second as the login
+        let tokens = [stored("ci", "new-secret", None)];
+        assert!(find_active(&tokens, "ci", &session, 0).is_none());
+    }
+
+    #[test]
+    fn test_scopes_round_trip() {
+        let json = serde_json::to_string(&Scope::ALL).unwrap();
+        assert_eq!(
+            json,
+            r#"["rebuild","lock-clear","content-edit","tokens","audit"]"#
+        );
+        assert_eq!("lock-clear".parse::<Scope>(), Ok(Scope::LockClear));
+        assert!("everything".parse::<Scope>().is_err());
+        let token = stored("ci", "s", None).token;
+        assert!(token.allows(Scope::Rebuild));
+        assert!(!token.allows(Scope::ContentEdit));
+    }
+
+    #[test]
+    fn test_stored_token_hides_hash_when_listed() {
+        let json = serde_json::to_value(stored("ci", "s", None)).unwrap();
+        assert!(json["hash"].is_string());
+        let parsed: StoredToken = serde_json::from_value(json).unwrap();
+        let listed = serde_json::to_value(parsed.token).unwrap();
+        assert_eq!(listed["name"], "ci");
+        assert!(listed.get("hash").is_none());
+    }
+
+    #[test]
+    fn test_write_tokens_replaces_store_atomically() {
+        let dir = env::temp_dir().join(format!("admin-tokens-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(ADMIN_TOKENS_FILE);
+        fs::write(&path, "{").unwrap();
+
+        write_tokens(&path, vec![stored("ci", "s", None)]).unwrap();
+        assert_eq!(read_tokens(&path).unwrap()[0].token.name, "ci");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_store_lock_excludes_other_holders() {
+        let dir = env::temp_dir().join(format!("admin-tokens-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(ADMIN_TOKENS_FILE);
+
+        let held = lock_store(&path).unwrap();
+        let other = File::open(dir.join(format!("{ADMIN_TOKENS_FILE}.lock"))).unwrap();
+        assert!(other.try_lock().is_err());
+        drop(held);
+        assert!(other.try_lock().is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_callers_only_grant_scopes_they_hold() {
+        let held = [Scope::Tokens, Scope::Rebuild];
+        assert!(check_held(&[Scope::Rebuild], &held).is_ok());
+        assert!(matches!(
+            check_held(&[Scope::Rebuild, Scope::ContentEdit], &held),
+            Err(TokenError::Forbidden(Scope::ContentEdit))
+        ));
+    }
+
+    #[test]
+    fn test_revoking_needs_every_scope_of_the_token() {
+        let mut tokens = [StoredToken {
+            token: AdminToken::new("default", Scope::ALL.to_vec(), None, "test"),
+            hash: hash_secret("s"),
+        }];
+        assert!(matches!(
+            revoke_in(&mut tokens, "default", "token:ops", &[Scope::Tokens]),
+            Err(TokenError::Forbidden(_))
+        ));
+        assert!(!tokens[0].token.is_revoked());
+
+        let revoked = revoke_in(&mut tokens, "default", "token:root", &Scope::ALL).unwrap();
+        assert_eq!(revoked.revoked_by.as_deref(), Some("token:root"));
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("ci.github-actions_1").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("env").is_err());
+        assert!(validate_name("two words").is_err());
     }
 }
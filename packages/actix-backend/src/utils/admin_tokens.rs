@@ -0,0 +1,175 @@
+use crate::api::ApiError;
+use crate::hooks::admin_auth::AdminAuthenticated;
+use crate::utils::admin_token::{
+    AdminToken, Scope, TokenError, create_token, list_tokens, revoke_token, rotate_token,
+};
+use actix_web::{HttpResponse, web};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::ToSchema;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateToken {
+    /// Letters, digits, `-`, `_` or `.`, such as `ci` or a maintainer's handle
+    name: String,
+    scopes: Vec<Scope>,
+    /// Omit for a token that never expires
+    expires_in_days: Option<u64>,
+}
+
+/// A token and its secret, which is only ever returned here
+#[derive(Serialize, ToSchema)]
+pub struct IssuedToken {
+    token: AdminToken,
+    secret: String,
+}
+
+impl From<TokenError> for ApiError {
+    fn from(e: TokenError) -> Self {
+        match e {
+            TokenError::Invalid(_) => ApiError::bad_request(e),
+            TokenError::Exists(_) => ApiError::conflict(e),
+            TokenError::NotFound(_) => ApiError::not_found(e),
+            TokenError::Forbidden(_) => ApiError::forbidden(e),
+            TokenError::Store(ref inner) => {
+                error!("❌ Admin token store failed: {:?}", inner);
+                ApiError::internal("Admin token store unavailable")
+            }
+        }
+    }
+}
+
+/// Who performed a change, as recorded on the token
+fn actor(admin: &AdminAuthenticated) -> String {
+    if admin.token.starts_with("user:") {
+        admin.token.clone()
+    } else {
+        format!("token:{}", admin.token)
+    }
+}
+
+/// List admin tokens without their secrets
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/tokens",
+    tag = "admin",
+    security(("admin_token" = ["tokens"])),
+    responses(
+        (status = 200, description = "All tokens, including expired and revoked ones", body = Vec<AdminToken>),
+        (status = 401, description = "Missing admin token", body = ApiError),
+        (status = 403, description = "Invalid or expired admin token, or missing scope", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn list_admin_tokens() -> Result<HttpResponse, actix_web::Error> {
+    let tokens = list_tokens().map_err(|e| ApiError::from(TokenError::Store(e)))?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+/// Create a named admin token
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/tokens",
+    tag = "admin",
+    security(("admin_token" = ["tokens"])),
+    request_body = CreateToken,
+    responses(
+        (status = 201, description = "The new token and its secret", body = IssuedToken),
+        (status = 400, description = "Invalid name or no scopes", body = ApiError),
+        (status = 403, description = "Missing scope, or a requested scope the caller lacks", body = ApiError),
+        (status = 409, description = "An active token already has this name", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn create_admin_token(
+    body: web::Json<CreateToken>,
+    admin: web::ReqData<AdminAuthenticated>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let CreateToken {
+        name,
+        scopes,
+        expires_in_days,
+    } = body.into_inner();
+    let expires_in = expires_in_days.map(|days| days.saturating_mul(SECONDS_PER_DAY));
+    let (token, secret) = create_token(&name, scopes, expires_in, &actor(&admin), &admin.scopes)
+        .map_err(ApiError::from)?;
+    Ok(HttpResponse::Created().json(IssuedToken { token, secret }))
+}
+
+/// Replace a token's secret; the old secret stops working immediately
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/tokens/{name}/rotate",
+    tag = "admin",
+    security(("admin_token" = ["tokens"])),
+    params(("name" = String, Path, description = "Token name")),
+    responses(
+        (status = 200, description = "The token and its new secret", body = IssuedToken),
+        (status = 403, description = "Missing scope, or the token has scopes the caller lacks", body = ApiError),
+        (status = 404, description = "No active token with this name", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn rotate_admin_token(
+    name: web::Path<String>,
+    admin: web::ReqData<AdminAuthenticated>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (token, secret) =
+        rotate_token(&name, &actor(&admin), &admin.scopes).map_err(ApiError::from)?;
+    Ok(HttpResponse::Ok().json(IssuedToken { token, secret }))
+}
+
+/// Revoke a token; it stays listed with who revoked it
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/tokens/{name}",
+    tag = "admin",
+    security(("admin_token" = ["tokens"])),
+    params(("name" = String, Path, description = "Token name")),
+    responses(
+        (status = 200, description = "The revoked token", body = AdminToken),
+        (status = 403, description = "Missing scope, or the token has scopes the caller lacks", body = ApiError),
+        (status = 404, description = "No active token with this name", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn revoke_admin_token(
+    name: web::Path<String>,
+    admin: web::ReqData<AdminAuthenticated>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = revoke_token(&name, &actor(&admin), &admin.scopes).map_err(ApiError::from)?;
+    Ok(HttpResponse::Ok().json(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{ResponseError, http::StatusCode};
+
+    #[test]
+    fn test_token_errors_map_to_status() {
+        let status = |e: TokenError| ApiError::from(e).status_code();
+        assert_eq!(
+            status(TokenError::Invalid("bad".into())),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(TokenError::Exists("ci".into())),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            status(TokenError::NotFound("ci".into())),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(TokenError::Forbidden(Scope::Tokens)),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(TokenError::Store(anyhow::anyhow!("disk full"))),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
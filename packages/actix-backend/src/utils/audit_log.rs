@@ -0,0 +1,361 @@
+//! Append-only JSONL log of admin requests.
+//!
+//! Every `AdminAuth`-protected request appends one line to
+//! `logs/audit.jsonl` under the root dir (or `AUDIT_LOG_DIR`). Once the file
+//! passes `AUDIT_LOG_MAX_BYTES` it is renamed to `audit.jsonl.1`, shifting
+//! older files up to `AUDIT_LOG_KEEP`.
+
+use crate::api::ApiError;
+use crate::hooks::rate_limit::{TRUSTED_PROXIES, client_ip};
+use crate::utils::folders::audit_dir;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, http::StatusCode, web};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::error;
+use tracing_actix_web::RequestId;
+use utoipa::{IntoParams, ToSchema};
+
+const AUDIT_FILE: &str = "audit.jsonl";
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_KEEP: usize = 5;
+/// Query parameters never written to the log
+const SECRET_PARAMS: [&str; 1] = ["admin_token"];
+
+/// Serializes appends and rotation within this process
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    /// Rejected by `AdminAuth`: no token, an unknown or expired one, or a missing scope
+    Denied,
+    Failed,
+}
+
+impl Outcome {
+    fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Outcome::Denied,
+            s if s.is_client_error() || s.is_server_error() => Outcome::Failed,
+            _ => Outcome::Success,
+        }
+    }
+}
+
+/// One admin request
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    /// Unix milliseconds when the request arrived
+    pub timestamp: u64,
+    /// Name of the admin token, or `user:<id>` for a signed-in user, if recognised
+    pub token: Option<String>,
+    pub ip: Option<String>,
+    pub method: String,
+    /// Route pattern, such as `/api/v1/admin/tokens/{name}`
+    pub route: String,
+    pub path: String,
+    /// Path and query parameters, without secrets
+    pub params: BTreeMap<String, String>,
+    pub status: u16,
+    pub outcome: Outcome,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl AuditEntry {
+    /// Start an entry for a request; finish it with [`AuditEntry::finish`]
+    pub fn begin(req: &HttpRequest) -> Self {
+        let mut params: BTreeMap<String, String> = req
+            .match_info()
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        for pair in req.query_string().split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            if !SECRET_PARAMS.contains(&key) {
+                params.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+                .unwrap_or(0),
+            token: None,
+            // `Forwarded` headers are only believed from `TRUSTED_PROXIES`,
+            // so the audited client cannot choose its own address
+            ip: client_ip(req, &TRUSTED_PROXIES).map(|ip| ip.to_string()),
+            method: req.method().to_string(),
+            route: req
+                .match_pattern()
+                .unwrap_or_else(|| req.path().to_string()),
+            path: req.path().to_string(),
+            params,
+            status: 0,
+            outcome: Outcome::Failed,
+            duration_ms: 0,
+            request_id: req.extensions().get::<RequestId>().map(ToString::to_string),
+        }
+    }
+
+    pub fn finish(&mut self, status: StatusCode, elapsed: Duration) {
+        self.status = status.as_u16();
+        self.outcome = Outcome::from_status(status);
+        self.duration_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+    }
+}
+
+fn max_bytes() -> u64 {
+    env::var("AUDIT_LOG_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_BYTES)
+}
+
+fn keep() -> usize {
+    env::var("AUDIT_LOG_KEEP")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_KEEP)
+}
+
+fn rotated(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("{AUDIT_FILE}.{n}"))
+}
+
+/// Shift `audit.jsonl` to `.1`, `.1` to `.2` and so on, dropping the oldest
+fn rotate(dir: &Path, keep: usize) -> Result<()> {
+    if keep == 0 {
+        fs::remove_file(dir.join(AUDIT_FILE))?;
+        return Ok(());
+    }
+    let _ = fs::remove_file(rotated(dir, keep));
+    for n in (1..keep).rev() {
+        let from = rotated(dir, n);
+        if from.exists() {
+            fs::rename(&from, rotated(dir, n + 1))?;
+        }
+    }
+    fs::rename(dir.join(AUDIT_FILE), rotated(dir, 1))?;
+    Ok(())
+}
+
+fn append(dir: &Path, entry: &AuditEntry, max_bytes: u64, keep: usize) -> Result<()> {
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let path = dir.join(AUDIT_FILE);
+    if fs::metadata(&path).is_ok_and(|m| m.len() >= max_bytes) {
+        rotate(dir, keep).context("Failed to rotate the audit log")?;
+    }
+
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(&line))
+        .with_context(|| format!("Failed to append to {}", path.display()))
+}
+
+/// Append an entry off the async workers; failures are logged rather than
+/// failing the request
+pub async fn record(entry: AuditEntry) {
+    let path = entry.path.clone();
+    let result = web::block(move || {
+        audit_dir()
+            .map_err(anyhow::Error::from)
+            .and_then(|dir| append(&dir, &entry, max_bytes(), keep()))
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|written| written);
+    if let Err(e) = result {
+        error!("❌ Failed to write audit entry for {}: {:?}", path, e);
+    }
+}
+
+/// Filters for reading the audit log, newest entries first
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    /// Entries per page
+    #[serde(default = "default_limit")]
+    #[param(default = 50)]
+    pub limit: usize,
+    /// Entries to skip
+    #[serde(default)]
+    pub offset: usize,
+    /// Only this token's requests
+    pub token: Option<String>,
+    /// Only requests whose path contains this
+    pub path: Option<String>,
+    pub outcome: Option<Outcome>,
+    /// Only entries at or after these Unix milliseconds
+    pub since: Option<u64>,
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.token
+            .as_ref()
+            .is_none_or(|t| entry.token.as_ref() == Some(t))
+            && self
+                .path
+                .as_ref()
+                .is_none_or(|p| entry.path.contains(p.as_str()))
+            && self.outcome.is_none_or(|o| entry.outcome == o)
+            && self.since.is_none_or(|s| entry.timestamp >= s)
+    }
+}
+
+/// One page of entries matching `query`, newest first, from the current and
+/// rotated files
+fn read_from(dir: &Path, query: &AuditQuery, keep: usize) -> Result<Vec<AuditEntry>> {
+    let files = std::iter::once(dir.join(AUDIT_FILE)).chain((1..=keep).map(|n| rotated(dir, n)));
+    let mut page = Vec::new();
+    let mut skipped = 0;
+    for path in files.filter(|p| p.exists()) {
+        let file =
+            fs::File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut entries = BufReader::new(file)
+            .lines()
+            .map_while(std::result::Result::ok)
+            .filter_map(|line| serde_json::from_str::<AuditEntry>(&line).ok())
+            .filter(|entry| query.matches(entry))
+            .collect::<Vec<_>>();
+        entries.reverse();
+        for entry in entries {
+            if skipped < query.offset {
+                skipped += 1;
+            } else if page.len() < query.limit {
+                page.push(entry);
+            } else {
+                return Ok(page);
+            }
+        }
+    }
+    Ok(page)
+}
+
+/// Read a page of the audit log.
+///
+/// # Errors
+/// Returns an error if the log directory or a log file cannot be read.
+pub fn read_entries(query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+    read_from(&audit_dir()?, query, keep())
+}
+
+/// Page through recent admin requests, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/audit",
+    tag = "admin",
+    security(("admin_token" = ["audit"])),
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "Matching entries, newest first", body = [AuditEntry]),
+        (status = 401, description = "Missing admin token", body = ApiError),
+        (status = 403, description = "Invalid or expired admin token, or missing scope", body = ApiError),
+        (status = 429, description = "Rate limited; retry after `Retry-After` seconds", body = ApiError),
+    )
+)]
+pub async fn get_audit_log(
+    query: web::Query<AuditQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let entries = web::block(move || read_entries(&query))
+        .await?
+        .map_err(|e| {
+            error!("❌ Failed to read the audit log: {:?}", e);
+            ApiError::internal("Failed to read the audit log")
+        })?;
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, status: StatusCode) -> AuditEntry {
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{path}?gc=off&admin_token=secret"))
+            .to_http_request();
+        let mut entry = AuditEntry::begin(&req);
+        entry.token = Some("ci".to_string());
+        entry.finish(status, Duration::from_millis(5));
+        entry
+    }
+
+    #[test]
+    fn test_entry_drops_secret_params() {
+        let entry = entry("/api/v1/maps/rebuild", StatusCode::ACCEPTED);
+        assert_eq!(entry.params.get("gc").map(String::as_str), Some("off"));
+        assert!(!entry.params.contains_key("admin_token"));
+        assert_eq!(entry.outcome, Outcome::Success);
+        assert_eq!(Outcome::from_status(StatusCode::FORBIDDEN), Outcome::Denied);
+    }
+
+    #[test]
+    fn test_entry_ignores_forwarded_ip_from_untrusted_peer() {
+        let req = actix_web::test::TestRequest::post()
+            .uri("/api/v1/maps/rebuild")
+            .peer_addr("198.51.100.1:4000".parse().unwrap())
+            .insert_header(("X-Forwarded-For", "203.0.113.7"))
+            .insert_header(("Forwarded", "for=203.0.113.7"))
+            .to_http_request();
+        let entry = AuditEntry::begin(&req);
+        assert_eq!(entry.ip.as_deref(), Some("198.51.100.1"));
+    }
+
+    #[test]
+    fn test_append_rotates_and_reads_newest_first() {
+        let dir = std::env::temp_dir().join(format!("audit-log-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for n in 0..6 {
+            let status = if n % 2 == 0 {
+                StatusCode::OK
+            } else {
+                StatusCode::FORBIDDEN
+            };
+            // A tiny size limit rotates after every entry, keeping two old files
+            append(&dir, &entry(&format!("/api/v1/{n}"), status), 1, 2).unwrap();
+        }
+        assert!(rotated(&dir, 2).exists());
+        assert!(!rotated(&dir, 3).exists());
+
+        let all = AuditQuery {
+            limit: 10,
+            ..AuditQuery::default()
+        };
+        let paths = read_from(&dir, &all, 2)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["/api/v1/5", "/api/v1/4", "/api/v1/3"]);
+
+        let denied = AuditQuery {
+            limit: 1,
+            offset: 1,
+            outcome: Some(Outcome::Denied),
+            ..AuditQuery::default()
+        };
+        let page = read_from(&dir, &denied, 2).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].path, "/api/v1/3");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
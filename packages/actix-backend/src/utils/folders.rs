@@ -1,8 +1,14 @@
 use shared::utils::root_dir::root_dir;
+use std::env;
 use std::fs::{canonicalize, create_dir_all};
 use std::io;
 use std::path::PathBuf;
 
+/// Directory holding the built frontend (`DIST_DIR`, defaults to `dist`).
+pub fn dist_dir() -> PathBuf {
+    env::var("DIST_DIR").map_or_else(|_| PathBuf::from("dist"), PathBuf::from)
+}
+
 pub fn assets_dir() -> Result<PathBuf, io::Error> {
     let mut path = root_dir()?;
     path.push("assets");
@@ -16,15 +22,40 @@ pub fn assets_dir() -> Result<PathBuf, io::Error> {
     canonicalize(path)
 }
 
-pub fn thumbnails_dir() -> Result<PathBuf, io::Error> {
+/// Creates (if needed) and resolves a directory under `assets/`
+fn asset_subdir(name: &str) -> Result<PathBuf, io::Error> {
     let mut path = assets_dir()?;
-    path.push("thumbnails");
+    path.push(name);
     create_dir_all(&path)?;
     if !path.exists() {
         return Err(io::Error::new(
             io::ErrorKind::NotFound,
-            format!("Thumbnails path missing: {}", path.display()),
+            format!("{name} path missing: {}", path.display()),
         ));
     }
     canonicalize(path)
 }
+
+pub fn thumbnails_dir() -> Result<PathBuf, io::Error> {
+    asset_subdir("thumbnails")
+}
+
+/// Disk cache for generated map tile pyramids
+pub fn tiles_dir() -> Result<PathBuf, io::Error> {
+    asset_subdir("tiles")
+}
+
+/// Disk cache for resized and transcoded map image variants
+pub fn variants_dir() -> Result<PathBuf, io::Error> {
+    asset_subdir("variants")
+}
+
+/// Admin audit log (`AUDIT_LOG_DIR`, defaults to `logs/` under the root dir)
+pub fn audit_dir() -> Result<PathBuf, io::Error> {
+    let path = match env::var("AUDIT_LOG_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => root_dir()?.join("logs"),
+    };
+    create_dir_all(&path)?;
+    Ok(path)
+}
@@ -1,6 +1,10 @@
 pub mod admin_info;
+pub mod admin_session;
 pub mod admin_token;
+pub mod admin_tokens;
+pub mod audit_log;
 pub mod folders;
 pub mod markdown;
+pub(crate) mod private_file;
 pub mod repo;
 pub mod setup;
@@ -20,11 +20,7 @@ const FETCH_PROGRESS_INTERVAL: usize = 50;
 const BYTES_TO_MB: usize = 1024 * 1024;
 
 fn calculate_percentage(current: usize, total: usize) -> usize {
-    if total > 0 {
-        (100 * current) / total
-    } else {
-        0
-    }
+    (100 * current).checked_div(total).unwrap_or(0)
 }
 
 fn log_clone_progress(progress: &Progress) {
@@ -111,7 +107,7 @@ fn clone_repository(url: &str, branch: &str, root: &std::path::Path) -> Result<(
     cb.transfer_progress(move |progress: Progress| {
         let current = progress_counter_clone.fetch_add(1, Ordering::Relaxed);
 
-        if current % CLONE_PROGRESS_INTERVAL == 0
+        if current.is_multiple_of(CLONE_PROGRESS_INTERVAL)
             || progress.received_objects() == progress.total_objects()
         {
             log_clone_progress(&progress);
@@ -171,7 +167,7 @@ fn update_existing_repository(root: &std::path::Path, branch: &str) -> Result<()
     // Progress callback for fetch
     cb.transfer_progress(move |progress: Progress| {
         let current = progress_counter_clone.fetch_add(1, Ordering::Relaxed);
-        if (current % FETCH_PROGRESS_INTERVAL == 0
+        if (current.is_multiple_of(FETCH_PROGRESS_INTERVAL)
             || progress.received_objects() == progress.total_objects())
             && progress.total_objects() > 0
         {
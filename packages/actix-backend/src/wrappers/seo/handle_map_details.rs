@@ -1,4 +1,6 @@
+use crate::api::API_V1;
 use crate::clients::meilisearch::meilisearch_index;
+use crate::metrics::observe_meilisearch;
 use crate::wrappers::seo::inject_seo_metadata::{SeoData, inject_seo_metadata};
 use actix_web::error::ErrorNotFound;
 use actix_web::{Error, HttpRequest};
@@ -17,16 +19,25 @@ pub(crate) async fn handle_map_details_metadata(
 
     let id = uri.split('/').next_back().unwrap_or_default();
 
-    let doc = meilisearch_index("maps")?
-        .get_document::<MapDocument>(id)
+    let index = meilisearch_index("maps")?;
+    let doc = observe_meilisearch("get_document", index.get_document::<MapDocument>(id))
         .await
         .map_err(|_| ErrorNotFound("Map metadata not found"))?;
 
-    let seo = SeoData {
+    inject_seo_metadata(html, http_request, map_seo(&doc))
+}
+
+/// Metadata for a map's detail page
+pub(crate) fn map_seo(doc: &MapDocument) -> SeoData {
+    SeoData {
         title: format!("{} | D&D VTT Maps", doc.name),
         description: format!("View the {} battle map on D&D VTT Maps", doc.name),
         keywords: Some(format!("D&D, VTT, Maps, {}", doc.name)),
-        image_url: doc.thumbnail.clone(),
-    };
-    inject_seo_metadata(html, http_request, seo)
+        image_url: doc.thumbnails.get("social").cloned().unwrap_or_else(|| {
+            format!(
+                "{API_V1}/maps/image/{}?w=1200&amp;h=630&amp;format=jpeg",
+                doc.id
+            )
+        }),
+    }
 }
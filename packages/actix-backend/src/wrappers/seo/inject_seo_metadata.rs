@@ -18,7 +18,15 @@ pub fn inject_seo_metadata(
     let scheme = connection_info.scheme();
     let host = connection_info.host();
     let uri = req.uri();
-    let canonical = format!("{scheme}://{host}{uri}");
+    render_seo_metadata(html, &format!("{scheme}://{host}{uri}"), seo)
+}
+
+/// Append the SEO tags to the page's `<head>` with an explicit canonical URL
+pub fn render_seo_metadata(
+    html: String,
+    canonical: &str,
+    seo: SeoData,
+) -> Result<String, actix_web::Error> {
     let keywords = seo.keywords.unwrap_or_default();
 
     let blob = format!(
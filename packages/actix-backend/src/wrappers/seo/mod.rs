@@ -16,6 +16,9 @@ use crate::wrappers::seo::{
     handle_default_metadata::handle_default_metadata,
     handle_map_details::handle_map_details_metadata,
 };
+pub(crate) use handle_default_metadata::default_seo;
+pub(crate) use handle_map_details::map_seo;
+pub(crate) use inject_seo_metadata::{SeoData, render_seo_metadata};
 
 pub struct SeoMetadata;
 
@@ -57,13 +60,16 @@ where
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let srv = Rc::clone(&self.0);
         Box::pin(async move {
+            // API HTML is a fragment (rendered notes and docs), not a page
+            let is_api = req.path().starts_with("/api/");
             let res = srv.call(req).await?;
 
-            let is_html = res
-                .headers()
-                .get(CONTENT_TYPE)
-                .and_then(|v| v.to_str().ok())
-                .is_some_and(|ct| ct.starts_with("text/html"));
+            let is_html = !is_api
+                && res
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|ct| ct.starts_with("text/html"));
 
             if !is_html {
                 return Ok(res.map_into_boxed_body());
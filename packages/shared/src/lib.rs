@@ -1,5 +1,8 @@
+pub mod render;
+pub mod static_site;
 pub mod types;
 pub mod utils;
+pub mod validate;
 
 /// Decodes a base64 encoded string into bytes.
 ///
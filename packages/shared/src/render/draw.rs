@@ -0,0 +1,209 @@
+//! Minimal raster primitives shared by the overlay renderers
+
+use image::{Rgba, RgbaImage};
+
+/// Glyph height of the built-in font, in font pixels
+const GLYPH_HEIGHT: u32 = 5;
+/// Glyph advance of the built-in font (3 px glyph + 1 px spacing)
+const GLYPH_ADVANCE: u32 = 4;
+
+/// 3x5 bitmap glyphs for map labels; each row's low three bits, MSB on the left
+fn glyph(c: char) -> Option<[u8; 5]> {
+    Some(match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        ' ' => [0; 5],
+        _ => return None,
+    })
+}
+
+/// Alpha-blend `color` over `pixel`
+pub(crate) fn blend(pixel: &mut Rgba<u8>, color: Rgba<u8>) {
+    let alpha = u16::from(color[3]);
+    for channel in 0..3 {
+        let dst = u16::from(pixel[channel]);
+        let src = u16::from(color[channel]);
+        // Max value is 255 * 255 / 255, which always fits
+        #[allow(clippy::cast_possible_truncation)]
+        let mixed = ((src * alpha + dst * (255 - alpha) + 127) / 255) as u8;
+        pixel[channel] = mixed;
+    }
+    pixel[3] = pixel[3].max(color[3]);
+}
+
+/// Alpha-blend `color` over the pixel at (`x`, `y`), ignoring out of bounds
+pub(crate) fn blend_pixel(image: &mut RgbaImage, x: u32, y: u32, color: Rgba<u8>) {
+    if x < image.width() && y < image.height() {
+        blend(image.get_pixel_mut(x, y), color);
+    }
+}
+
+/// Blend a filled rectangle, clipped to the image
+pub(crate) fn fill_rect(image: &mut RgbaImage, x: u32, y: u32, w: u32, h: u32, color: Rgba<u8>) {
+    let x_end = x.saturating_add(w).min(image.width());
+    let y_end = y.saturating_add(h).min(image.height());
+    for py in y..y_end {
+        for px in x..x_end {
+            blend_pixel(image, px, py, color);
+        }
+    }
+}
+
+/// Size in pixels of `text` rendered at `scale`
+pub(crate) fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let chars = u32::try_from(text.chars().count()).unwrap_or(u32::MAX);
+    let width = (chars * GLYPH_ADVANCE).saturating_sub(1);
+    (width * scale, GLYPH_HEIGHT * scale)
+}
+
+/// Draw `text` with its top-left corner at (`x`, `y`); unknown characters are skipped
+pub(crate) fn draw_text(
+    image: &mut RgbaImage,
+    x: u32,
+    y: u32,
+    text: &str,
+    scale: u32,
+    color: Rgba<u8>,
+) {
+    let mut cursor = x;
+    for c in text.chars() {
+        if let Some(rows) = glyph(c) {
+            for (row, bits) in (0..).zip(rows) {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) != 0 {
+                        fill_rect(
+                            image,
+                            cursor + col * scale,
+                            y + row * scale,
+                            scale,
+                            scale,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+        cursor += GLYPH_ADVANCE * scale;
+    }
+}
+
+/// Pixel bounds of a box around (`x0`, `y0`)..(`x1`, `y1`), clipped to the image
+fn clipped_bounds(image: &RgbaImage, x0: f64, y0: f64, x1: f64, y1: f64) -> (u32, u32, u32, u32) {
+    let clip = |v: f64, max: u32| {
+        // Clamped to the image before the cast
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let v = v.clamp(0.0, f64::from(max)) as u32;
+        v
+    };
+    (
+        clip(x0.min(x1).floor(), image.width()),
+        clip(y0.min(y1).floor(), image.height()),
+        clip(x0.max(x1).ceil() + 1.0, image.width()),
+        clip(y0.max(y1).ceil() + 1.0, image.height()),
+    )
+}
+
+/// Distance from `p` to the segment `a`-`b`
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p.0 - (a.0 + t * dx)).hypot(p.1 - (a.1 + t * dy))
+}
+
+/// Draw a line segment `width` pixels thick with round caps
+pub(crate) fn draw_segment(
+    image: &mut RgbaImage,
+    from: (f64, f64),
+    to: (f64, f64),
+    width: f64,
+    color: Rgba<u8>,
+) {
+    let half = width / 2.0;
+    let (x0, y0, x1, y1) = clipped_bounds(
+        image,
+        from.0.min(to.0) - half,
+        from.1.min(to.1) - half,
+        from.0.max(to.0) + half,
+        from.1.max(to.1) + half,
+    );
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let center = (f64::from(x) + 0.5, f64::from(y) + 0.5);
+            if segment_distance(center, from, to) <= half {
+                blend_pixel(image, x, y, color);
+            }
+        }
+    }
+}
+
+/// Draw a circle; `fill` covers the disc, `stroke` a ring `width` pixels thick
+pub(crate) fn draw_circle(
+    image: &mut RgbaImage,
+    center: (f64, f64),
+    radius: f64,
+    width: f64,
+    fill: Option<Rgba<u8>>,
+    stroke: Rgba<u8>,
+) {
+    let half = width / 2.0;
+    let reach = radius + half;
+    let (x0, y0, x1, y1) = clipped_bounds(
+        image,
+        center.0 - reach,
+        center.1 - reach,
+        center.0 + reach,
+        center.1 + reach,
+    );
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let distance = (f64::from(x) + 0.5 - center.0).hypot(f64::from(y) + 0.5 - center.1);
+            if (distance - radius).abs() <= half {
+                blend_pixel(image, x, y, stroke);
+            } else if distance < radius
+                && let Some(fill) = fill
+            {
+                blend_pixel(image, x, y, fill);
+            }
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use crate::render::draw::{blend, draw_text, fill_rect, text_size};
+use crate::types::map_resolution::{MapResolution, Point};
+use image::{Rgba, RgbaImage};
+
+/// Smallest grid spacing drawn; anything finer would just fill the image
+const MIN_GRID_PIXELS: f64 = 4.0;
+/// Padding around cell labels, in label pixels
+const LABEL_PADDING: u32 = 1;
+
+/// How grid lines and labels look
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridStyle {
+    pub color: [u8; 3],
+    /// 0.0 (invisible) to 1.0 (opaque)
+    pub opacity: f32,
+    /// Line width in output pixels
+    pub line_width: u32,
+    /// Label each cell A1, B1, ... from the top-left
+    pub labels: bool,
+}
+
+impl Default for GridStyle {
+    fn default() -> Self {
+        Self {
+            color: [0, 0, 0],
+            opacity: 0.5,
+            line_width: 2,
+            labels: false,
+        }
+    }
+}
+
+impl GridStyle {
+    /// Bound opacity and width to drawable values
+    #[must_use]
+    pub fn clamped(mut self) -> Self {
+        self.opacity = if self.opacity.is_nan() {
+            0.0
+        } else {
+            self.opacity.clamp(0.0, 1.0)
+        };
+        self.line_width = self.line_width.clamp(1, 64);
+        self
+    }
+
+    /// Clamp, then snap color channels to multiples of `0x11` and opacity to
+    /// tenths, so request-chosen styles map onto a bounded set of cache keys
+    #[must_use]
+    pub fn quantized(self) -> Self {
+        let mut style = self.clamped();
+        #[allow(clippy::cast_possible_truncation)]
+        let snap = |c: u8| ((u16::from(c) + 8) / 17 * 17) as u8;
+        style.color = style.color.map(snap);
+        style.opacity = (style.opacity * 10.0).round() / 10.0;
+        style
+    }
+
+    /// Compact identifier of the style, used in cache keys
+    #[must_use]
+    pub fn cache_key(&self) -> String {
+        let [r, g, b] = self.color;
+        format!(
+            "g{r:02x}{g:02x}{b:02x}o{}w{}{}",
+            self.alpha(),
+            self.line_width,
+            if self.labels { "l" } else { "" }
+        )
+    }
+
+    fn alpha(&self) -> u8 {
+        // Clamped to 0..=255 before the cast
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let alpha = (self.opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+        alpha
+    }
+
+    fn line_color(&self) -> Rgba<u8> {
+        let [r, g, b] = self.color;
+        Rgba([r, g, b, self.alpha()])
+    }
+}
+
+/// Parse `RRGGBB` or `#RRGGBB`
+#[must_use]
+pub fn parse_hex_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Where the grid falls on an image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridLayout {
+    pub pixels_per_grid: f64,
+    /// Grid coordinate of the image's top-left corner
+    pub origin: Point,
+}
+
+impl From<&MapResolution> for GridLayout {
+    fn from(resolution: &MapResolution) -> Self {
+        Self {
+            pixels_per_grid: f64::from(resolution.pixels_per_grid),
+            origin: resolution.map_origin,
+        }
+    }
+}
+
+impl GridLayout {
+    /// Layout for the image resized by `factor`
+    #[must_use]
+    pub fn scaled(self, factor: f64) -> Self {
+        Self {
+            pixels_per_grid: self.pixels_per_grid * factor,
+            ..self
+        }
+    }
+
+    /// Pixel offsets of the grid lines crossing an axis `length` pixels long
+    #[must_use]
+    pub fn lines(&self, origin: f64, length: u32) -> Vec<f64> {
+        if self.pixels_per_grid.is_nan() || self.pixels_per_grid < MIN_GRID_PIXELS {
+            return Vec::new();
+        }
+        let first = (origin.ceil() - origin) * self.pixels_per_grid;
+        let end = f64::from(length) + 0.5;
+        (0..)
+            .map(|i| first + f64::from(i) * self.pixels_per_grid)
+            .take_while(|x| *x <= end)
+            .collect()
+    }
+}
+
+/// Spreadsheet-style column name: A..Z, AA, AB, ...
+#[must_use]
+pub fn column_label(mut index: usize) -> String {
+    let mut label = Vec::new();
+    loop {
+        // index % 26 is always < 26
+        #[allow(clippy::cast_possible_truncation)]
+        label.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    label.reverse();
+    String::from_utf8(label).unwrap_or_default()
+}
+
+/// Mark the pixels covered by lines of `width` centered on `lines`
+fn coverage(lines: &[f64], width: u32, length: u32) -> Vec<bool> {
+    let mut mask = vec![false; length as usize];
+    let half = f64::from(width) / 2.0;
+    for line in lines {
+        // Clamped to the image before the cast
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let start = (line - half).round().clamp(0.0, f64::from(length)) as usize;
+        let end = (start + width as usize).min(mask.len());
+        mask[start..end].fill(true);
+    }
+    mask
+}
+
+/// Burn a grid, and optionally A1-style cell labels, into the image
+pub fn draw_grid(image: &mut RgbaImage, layout: &GridLayout, style: &GridStyle) {
+    let (width, height) = image.dimensions();
+    let columns = layout.lines(layout.origin.x, width);
+    let rows = layout.lines(layout.origin.y, height);
+
+    let color = style.line_color();
+    let on_column = coverage(&columns, style.line_width, width);
+    let on_row = coverage(&rows, style.line_width, height);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        if on_column[x as usize] || on_row[y as usize] {
+            blend(pixel, color);
+        }
+    }
+
+    if style.labels {
+        draw_labels(image, layout, style, &columns, &rows);
+    }
+}
+
+fn draw_labels(
+    image: &mut RgbaImage,
+    layout: &GridLayout,
+    style: &GridStyle,
+    columns: &[f64],
+    rows: &[f64],
+) {
+    // Roughly a fifth of a cell tall, but never below one pixel per dot
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let scale = ((layout.pixels_per_grid / 40.0).floor() as u32).max(1);
+    let [r, g, b] = style.color;
+    let ink = Rgba([r, g, b, 255]);
+    let backing = Rgba([255, 255, 255, 160]);
+    let pad = LABEL_PADDING * scale;
+    let inset = style.line_width.div_ceil(2) + pad;
+
+    // Cells too small for their longest label stay unlabelled
+    let longest = format!(
+        "{}{}",
+        column_label(columns.len().saturating_sub(1)),
+        rows.len()
+    );
+    let (longest_width, _) = text_size(&longest, scale);
+    if f64::from(longest_width + inset + 2 * pad) > layout.pixels_per_grid {
+        return;
+    }
+
+    for (row, y) in rows.iter().enumerate() {
+        for (column, x) in columns.iter().enumerate() {
+            let label = format!("{}{}", column_label(column), row + 1);
+            let (w, h) = text_size(&label, scale);
+
+            // Line positions are within the image, so they fit in u32
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let (left, top) = (x.round() as u32 + inset, y.round() as u32 + inset);
+            fill_rect(image, left, top, w + 2 * pad, h + 2 * pad, backing);
+            draw_text(image, left + pad, top + pad, &label, scale, ink);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::map_resolution::Coordinates;
+
+    fn white(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]))
+    }
+
+    #[test]
+    fn test_grid_lines_follow_origin() {
+        let layout = GridLayout {
+            pixels_per_grid: 100.0,
+            origin: Point { x: 0.0, y: -0.25 },
+        };
+        assert_eq!(layout.lines(0.0, 300), vec![0.0, 100.0, 200.0, 300.0]);
+        assert_eq!(layout.lines(-0.25, 200), vec![25.0, 125.0]);
+        assert!(layout.scaled(0.01).lines(0.0, 300).is_empty());
+    }
+
+    #[test]
+    fn test_draw_grid_blends_lines_once() {
+        let resolution = MapResolution {
+            map_origin: Point::default(),
+            map_size: Coordinates { x: 2, y: 2 },
+            pixels_per_grid: 10,
+        };
+        let mut image = white(20, 20);
+        let style = GridStyle {
+            opacity: 1.0,
+            line_width: 2,
+            ..GridStyle::default()
+        };
+        draw_grid(&mut image, &GridLayout::from(&resolution), &style);
+
+        assert_eq!(image.get_pixel(10, 5), &Rgba([0, 0, 0, 255]));
+        assert_eq!(image.get_pixel(5, 5), &Rgba([255, 255, 255, 255]));
+
+        let mut image = white(20, 20);
+        let style = GridStyle::default();
+        draw_grid(&mut image, &GridLayout::from(&resolution), &style);
+        // Crossings are blended once, not twice
+        assert_eq!(image.get_pixel(10, 10), image.get_pixel(10, 5));
+    }
+
+    #[test]
+    fn test_labels_are_drawn_in_large_cells() {
+        let layout = GridLayout {
+            pixels_per_grid: 128.0,
+            origin: Point::default(),
+        };
+        let style = GridStyle {
+            labels: true,
+            ..GridStyle::default()
+        };
+        let mut image = white(256, 256);
+        draw_grid(&mut image, &layout, &style);
+
+        let inked = image
+            .enumerate_pixels()
+            .filter(|(x, y, p)| *x > 2 && *x < 60 && *y > 2 && *y < 60 && p[0] == 0)
+            .count();
+        assert!(inked > 0);
+    }
+
+    #[test]
+    fn test_quantized_style_snaps_color_and_opacity() {
+        let style = GridStyle {
+            color: [0xfe, 0x83, 0x07],
+            opacity: 0.47,
+            ..GridStyle::default()
+        }
+        .quantized();
+        assert_eq!(style.color, [0xff, 0x88, 0x00]);
+        assert!((style.opacity - 0.5).abs() < f32::EPSILON);
+        assert_eq!(style.cache_key(), "gff8800o128w2");
+    }
+
+    #[test]
+    fn test_column_labels_and_colors() {
+        assert_eq!(column_label(0), "A");
+        assert_eq!(column_label(25), "Z");
+        assert_eq!(column_label(26), "AA");
+        assert_eq!(column_label(27), "AB");
+        assert_eq!(parse_hex_color("#ff8000"), Some([255, 128, 0]));
+        assert_eq!(parse_hex_color("12345"), None);
+    }
+}
@@ -0,0 +1,7 @@
+mod draw;
+pub mod grid;
+pub mod overlay;
+pub mod print;
+pub mod thumbnail;
+pub mod tiles;
+pub mod variant;
@@ -0,0 +1,225 @@
+use crate::render::draw::{draw_circle, draw_segment};
+use crate::render::grid::{GridLayout, parse_hex_color};
+use crate::types::dd2vtt::DD2VTTFile;
+use crate::types::map_resolution::Point;
+use image::{Rgba, RgbaImage};
+
+const WALL_COLOR: Rgba<u8> = Rgba([230, 30, 30, 255]);
+const OBJECT_COLOR: Rgba<u8> = Rgba([255, 170, 0, 255]);
+const CLOSED_DOOR_COLOR: Rgba<u8> = Rgba([30, 110, 255, 255]);
+const OPEN_DOOR_COLOR: Rgba<u8> = Rgba([20, 210, 90, 255]);
+/// Opacity of the light radius fill
+const LIGHT_FILL_ALPHA: u8 = 48;
+
+/// Which map geometry layers to draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverlayOptions {
+    pub walls: bool,
+    pub objects: bool,
+    pub doors: bool,
+    pub lights: bool,
+}
+
+impl Default for OverlayOptions {
+    fn default() -> Self {
+        Self {
+            walls: true,
+            objects: true,
+            doors: true,
+            lights: true,
+        }
+    }
+}
+
+impl OverlayOptions {
+    /// Parse `all` or a comma separated list of `walls`, `objects`, `doors`
+    /// and `lights`
+    ///
+    /// # Errors
+    /// Returns an error naming the first unknown layer.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.is_empty() || matches!(value, "1" | "true" | "all") {
+            return Ok(Self::default());
+        }
+
+        let mut options = Self {
+            walls: false,
+            objects: false,
+            doors: false,
+            lights: false,
+        };
+        for layer in value.split(',').map(str::trim) {
+            match layer {
+                "walls" => options.walls = true,
+                "objects" => options.objects = true,
+                "doors" => options.doors = true,
+                "lights" => options.lights = true,
+                other => return Err(format!("Unknown overlay layer: {other}")),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Compact identifier of the enabled layers, used in cache keys
+    #[must_use]
+    pub fn cache_key(&self) -> String {
+        let layers = [
+            (self.walls, 'w'),
+            (self.objects, 'o'),
+            (self.doors, 'd'),
+            (self.lights, 'l'),
+        ];
+        std::iter::once('v')
+            .chain(layers.iter().filter(|(on, _)| *on).map(|(_, c)| *c))
+            .collect()
+    }
+}
+
+/// Light colors are `AARRGGBB`; the alpha is ignored
+fn light_color(color: &str) -> [u8; 3] {
+    let rgb = color
+        .get(color.len().saturating_sub(6)..)
+        .unwrap_or_default();
+    parse_hex_color(rgb).unwrap_or([255, 255, 255])
+}
+
+/// Draw walls, object occluders, doors and light radii over a map image.
+///
+/// Geometry is in grid units; `layout` must match the image's current scale.
+pub fn draw_overlay(
+    image: &mut RgbaImage,
+    map: &DD2VTTFile,
+    layout: &GridLayout,
+    options: &OverlayOptions,
+) {
+    let ppg = layout.pixels_per_grid;
+    let to_pixel = |p: &Point| ((p.x - layout.origin.x) * ppg, (p.y - layout.origin.y) * ppg);
+    let width = (ppg / 24.0).max(2.0);
+
+    let polyline = |image: &mut RgbaImage, points: &[Point], width: f64, color| {
+        for pair in points.windows(2) {
+            draw_segment(image, to_pixel(&pair[0]), to_pixel(&pair[1]), width, color);
+        }
+    };
+
+    if options.lights {
+        for light in &map.lights {
+            let [r, g, b] = light_color(&light.color);
+            draw_circle(
+                image,
+                to_pixel(&light.position),
+                light.range * ppg,
+                width / 2.0,
+                Some(Rgba([r, g, b, LIGHT_FILL_ALPHA])),
+                Rgba([r, g, b, 220]),
+            );
+            draw_circle(
+                image,
+                to_pixel(&light.position),
+                width,
+                width,
+                Some(Rgba([r, g, b, 255])),
+                Rgba([0, 0, 0, 255]),
+            );
+        }
+    }
+    if options.objects {
+        for line in &map.objects_line_of_sight {
+            polyline(image, line, width, OBJECT_COLOR);
+        }
+    }
+    if options.walls {
+        for line in &map.line_of_sight {
+            polyline(image, line, width, WALL_COLOR);
+        }
+    }
+    if options.doors {
+        for portal in &map.portals {
+            let (color, thickness) = if portal.closed {
+                (CLOSED_DOOR_COLOR, width * 1.5)
+            } else {
+                (OPEN_DOOR_COLOR, width)
+            };
+            polyline(image, &portal.bounds, thickness, color);
+            draw_circle(
+                image,
+                to_pixel(&portal.position),
+                width,
+                width / 2.0,
+                Some(color),
+                color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> DD2VTTFile {
+        serde_json::from_value(serde_json::json!({
+            "image": "",
+            "resolution": {
+                "map_origin": { "x": 0, "y": 0 },
+                "map_size": { "x": 4, "y": 4 },
+                "pixels_per_grid": 32
+            },
+            "line_of_sight": [[{ "x": 0.5, "y": 1 }, { "x": 3.5, "y": 1 }]],
+            "portals": [{
+                "position": { "x": 2, "y": 3 },
+                "bounds": [{ "x": 1.5, "y": 3 }, { "x": 2.5, "y": 3 }],
+                "rotation": 0,
+                "closed": true,
+                "freestanding": false
+            }],
+            "lights": [{
+                "position": { "x": 3, "y": 3 },
+                "range": 0.5,
+                "intensity": 1,
+                "color": "ff00ff00",
+                "shadows": true
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_overlay_draws_each_layer() {
+        let map = map();
+        let layout = GridLayout::from(map.resolution());
+        let mut image = RgbaImage::from_pixel(128, 128, Rgba([0, 0, 0, 255]));
+        draw_overlay(&mut image, &map, &layout, &OverlayOptions::default());
+
+        assert_eq!(image.get_pixel(64, 32), &WALL_COLOR);
+        assert_eq!(image.get_pixel(56, 96), &CLOSED_DOOR_COLOR);
+        // Light radius fill is green over black
+        let lit = image.get_pixel(96 + 10, 96);
+        assert!(lit[1] > 0 && lit[0] == 0);
+        assert_eq!(image.get_pixel(10, 10), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_overlay_layers_can_be_disabled() {
+        let map = map();
+        let layout = GridLayout::from(map.resolution());
+        let mut image = RgbaImage::from_pixel(128, 128, Rgba([0, 0, 0, 255]));
+        let options = OverlayOptions::parse("doors").unwrap();
+        draw_overlay(&mut image, &map, &layout, &options);
+
+        assert_eq!(image.get_pixel(64, 32), &Rgba([0, 0, 0, 255]));
+        assert_eq!(image.get_pixel(56, 96), &CLOSED_DOOR_COLOR);
+    }
+
+    #[test]
+    fn test_parse_overlay_options() {
+        assert_eq!(OverlayOptions::parse("all"), Ok(OverlayOptions::default()));
+        assert_eq!(OverlayOptions::default().cache_key(), "vwodl");
+        assert_eq!(
+            OverlayOptions::parse("walls, lights").unwrap().cache_key(),
+            "vwl"
+        );
+        assert!(OverlayOptions::parse("furniture").is_err());
+    }
+}
@@ -0,0 +1,500 @@
+use crate::render::grid::column_label;
+use crate::types::dd2vtt::{DD2VTTFile, MapFileError};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, GenericImageView, ImageError};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str};
+use std::fmt;
+use std::str::FromStr;
+
+/// PDF user space units per inch
+const POINTS_PER_INCH: f32 = 72.0;
+const POINTS_PER_MM: f32 = POINTS_PER_INCH / 25.4;
+const PAGE_JPEG_QUALITY: u8 = 90;
+const FONT: Name = Name(b"F1");
+const IMAGE: Name = Name(b"Im1");
+const LABEL_SIZE: f32 = 8.0;
+const CROP_MARK_LENGTH: f32 = 12.0;
+const CROP_MARK_GAP: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaperSize {
+    Letter,
+    A4,
+}
+
+impl PaperSize {
+    /// Portrait width and height in points
+    #[must_use]
+    pub fn points(self) -> (f32, f32) {
+        match self {
+            PaperSize::Letter => (8.5 * POINTS_PER_INCH, 11.0 * POINTS_PER_INCH),
+            PaperSize::A4 => (210.0 * POINTS_PER_MM, 297.0 * POINTS_PER_MM),
+        }
+    }
+}
+
+impl fmt::Display for PaperSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PaperSize::Letter => "letter",
+            PaperSize::A4 => "a4",
+        })
+    }
+}
+
+impl FromStr for PaperSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "letter" => Ok(PaperSize::Letter),
+            "a4" => Ok(PaperSize::A4),
+            other => Err(format!("Unsupported paper size: {other}")),
+        }
+    }
+}
+
+/// Printed edge length of one grid square
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SquareSize {
+    Inch,
+    Millimeters25,
+}
+
+impl SquareSize {
+    #[must_use]
+    pub fn points(self) -> f32 {
+        match self {
+            SquareSize::Inch => POINTS_PER_INCH,
+            SquareSize::Millimeters25 => 25.0 * POINTS_PER_MM,
+        }
+    }
+}
+
+impl fmt::Display for SquareSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SquareSize::Inch => "1in",
+            SquareSize::Millimeters25 => "25mm",
+        })
+    }
+}
+
+impl FromStr for SquareSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "1in" | "in" | "inch" => Ok(SquareSize::Inch),
+            "25mm" | "mm" => Ok(SquareSize::Millimeters25),
+            other => Err(format!("Unsupported square size: {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrintOptions {
+    pub paper: PaperSize,
+    pub square: SquareSize,
+    /// Unprinted border around each page, in points; holds marks and labels
+    pub margin: f32,
+    /// Map area repeated on neighbouring pages, in points
+    pub overlap: f32,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            paper: PaperSize::Letter,
+            square: SquareSize::Inch,
+            margin: 0.5 * POINTS_PER_INCH,
+            overlap: 0.25 * POINTS_PER_INCH,
+        }
+    }
+}
+
+impl PrintOptions {
+    /// Stable file name identifying this print layout within a map's cache
+    #[must_use]
+    pub fn cache_key(&self) -> String {
+        format!("print-{}-{}.pdf", self.paper, self.square)
+    }
+}
+
+/// How a map at a given print scale is split across pages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrintLayout {
+    /// Page size in points, in the orientation that needs fewer pages
+    pub page_width: f32,
+    pub page_height: f32,
+    pub margin: f32,
+    pub overlap: f32,
+    /// Printed map size in points
+    pub map_width: f32,
+    pub map_height: f32,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+/// Pages needed to cover `length` when each page shows `printable` and
+/// repeats `overlap` of its neighbour
+fn pages_along(length: f32, printable: f32, overlap: f32) -> u32 {
+    if length <= printable {
+        return 1;
+    }
+    let step = (printable - overlap).max(1.0);
+    // Page counts are small positive numbers
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let pages = ((length - overlap) / step).ceil() as u32;
+    pages
+}
+
+impl PrintLayout {
+    /// Lay out a `width` x `height` pixel map at `pixels_per_grid`
+    #[must_use]
+    pub fn new(width: u32, height: u32, pixels_per_grid: u32, options: &PrintOptions) -> Self {
+        #[allow(clippy::cast_precision_loss)]
+        let points_per_pixel = options.square.points() / pixels_per_grid.max(1) as f32;
+        #[allow(clippy::cast_precision_loss)]
+        let (map_width, map_height) = (
+            width as f32 * points_per_pixel,
+            height as f32 * points_per_pixel,
+        );
+        let (short, long) = options.paper.points();
+
+        let layout = |page_width: f32, page_height: f32| {
+            let printable_w = page_width - 2.0 * options.margin;
+            let printable_h = page_height - 2.0 * options.margin;
+            Self {
+                page_width,
+                page_height,
+                margin: options.margin,
+                overlap: options.overlap,
+                map_width,
+                map_height,
+                columns: pages_along(map_width, printable_w, options.overlap),
+                rows: pages_along(map_height, printable_h, options.overlap),
+            }
+        };
+
+        let portrait = layout(short, long);
+        let landscape = layout(long, short);
+        if landscape.page_count() < portrait.page_count() {
+            landscape
+        } else {
+            portrait
+        }
+    }
+
+    #[must_use]
+    pub fn page_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// Width and height of the area inside the margins
+    #[must_use]
+    pub fn printable(&self) -> (f32, f32) {
+        (
+            self.page_width - 2.0 * self.margin,
+            self.page_height - 2.0 * self.margin,
+        )
+    }
+
+    /// Map position, in points from the top-left, shown at the top-left of a page
+    #[must_use]
+    pub fn page_origin(&self, column: u32, row: u32) -> (f32, f32) {
+        let (printable_w, printable_h) = self.printable();
+        #[allow(clippy::cast_precision_loss)]
+        let origin = (
+            column as f32 * (printable_w - self.overlap),
+            row as f32 * (printable_h - self.overlap),
+        );
+        origin
+    }
+}
+
+/// Assembly label of a page: columns lettered, rows numbered, like the grid
+#[must_use]
+pub fn page_label(column: u32, row: u32) -> String {
+    format!("{}{}", column_label(column as usize), row + 1)
+}
+
+/// Text for the base-14 Helvetica font, which only covers ASCII reliably
+fn pdf_text(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() {
+                c as u8
+            } else {
+                b'?'
+            }
+        })
+        .collect()
+}
+
+fn show_text(content: &mut Content, x: f32, y: f32, text: &str) {
+    content
+        .begin_text()
+        .set_font(FONT, LABEL_SIZE)
+        .next_line(x, y)
+        .show(Str(&pdf_text(text)))
+        .end_text();
+}
+
+fn line(content: &mut Content, from: (f32, f32), to: (f32, f32)) {
+    content.move_to(from.0, from.1).line_to(to.0, to.1).stroke();
+}
+
+/// Short marks outside each corner of the printable area, for trimming
+fn crop_marks(content: &mut Content, left: f32, bottom: f32, right: f32, top: f32) {
+    let near = CROP_MARK_GAP;
+    let far = CROP_MARK_GAP + CROP_MARK_LENGTH;
+    for (x, dx) in [(left, -1.0), (right, 1.0)] {
+        for (y, dy) in [(bottom, -1.0), (top, 1.0)] {
+            line(content, (x + dx * near, y), (x + dx * far, y));
+            line(content, (x, y + dy * near), (x, y + dy * far));
+        }
+    }
+}
+
+fn encode_page_image(image: &DynamicImage) -> Result<Vec<u8>, ImageError> {
+    let mut out = Vec::new();
+    image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut out, PAGE_JPEG_QUALITY))?;
+    Ok(out)
+}
+
+/// Render a map as a multi-page PDF at a true print scale, one grid square
+/// per inch (or 25 mm), with overlap guides, crop marks and assembly labels.
+///
+/// # Errors
+/// Returns an error if the embedded image cannot be decoded or re-encoded.
+pub fn render_print_pdf(
+    map: &DD2VTTFile,
+    name: &str,
+    options: &PrintOptions,
+) -> Result<Vec<u8>, MapFileError> {
+    let image = map.decode_image()?;
+    let (width, height) = image.dimensions();
+    let pixels_per_grid = u32::from(map.resolution().pixels_per_grid);
+    let layout = PrintLayout::new(width, height, pixels_per_grid, options);
+    #[allow(clippy::cast_precision_loss)]
+    let pixels_per_point = pixels_per_grid.max(1) as f32 / options.square.points();
+    let (printable_w, printable_h) = layout.printable();
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let font_id = Ref::new(3);
+    // Each page uses three objects: page, content stream and image
+    let page_refs: Vec<(u32, u32, Ref)> = (0..layout.rows)
+        .flat_map(|row| (0..layout.columns).map(move |column| (column, row)))
+        .zip((4..).step_by(3))
+        .map(|((column, row), id)| (column, row, Ref::new(id)))
+        .collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_refs.iter().map(|(_, _, id)| *id))
+        .count(i32::try_from(page_refs.len()).unwrap_or(i32::MAX));
+    pdf.type1_font(font_id).base_font(Name(b"Helvetica"));
+
+    for (index, (column, row, page_id)) in page_refs.iter().copied().enumerate() {
+        let content_id = Ref::new(page_id.get() + 1);
+        let image_id = Ref::new(page_id.get() + 2);
+
+        // Crop the part of the map shown on this page
+        let (origin_x, origin_y) = layout.page_origin(column, row);
+        // Page origins are within the map, so these fit in u32
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let (crop_x, crop_y) = (
+            ((origin_x * pixels_per_point).round() as u32).min(width.saturating_sub(1)),
+            ((origin_y * pixels_per_point).round() as u32).min(height.saturating_sub(1)),
+        );
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let (crop_w, crop_h) = (
+            ((printable_w * pixels_per_point).round() as u32).clamp(1, width - crop_x),
+            ((printable_h * pixels_per_point).round() as u32).clamp(1, height - crop_y),
+        );
+        let tile = image.crop_imm(crop_x, crop_y, crop_w, crop_h);
+        let jpeg = encode_page_image(&tile)?;
+
+        let mut xobject = pdf.image_xobject(image_id, &jpeg);
+        xobject.filter(Filter::DctDecode);
+        xobject.width(i32::try_from(crop_w).unwrap_or(i32::MAX));
+        xobject.height(i32::try_from(crop_h).unwrap_or(i32::MAX));
+        xobject.color_space().device_rgb();
+        xobject.bits_per_component(8);
+        xobject.finish();
+
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, layout.page_width, layout.page_height));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        let mut resources = page.resources();
+        resources.fonts().pair(FONT, font_id);
+        resources.x_objects().pair(IMAGE, image_id);
+        resources.finish();
+        page.finish();
+
+        // PDF space starts at the bottom-left of the page
+        #[allow(clippy::cast_precision_loss)]
+        let (drawn_w, drawn_h) = (
+            crop_w as f32 / pixels_per_point,
+            crop_h as f32 / pixels_per_point,
+        );
+        let left = layout.margin;
+        let top = layout.page_height - layout.margin;
+        let right = left + printable_w;
+        let bottom = top - printable_h;
+
+        let mut content = Content::new();
+        content.save_state();
+        content.transform([drawn_w, 0.0, 0.0, drawn_h, left, top - drawn_h]);
+        content.x_object(IMAGE);
+        content.restore_state();
+
+        content.set_line_width(0.5).set_stroke_gray(0.0);
+        crop_marks(&mut content, left, bottom, right, top);
+
+        // Dashed guides where the next page's content begins
+        content.save_state();
+        content
+            .set_stroke_gray(0.4)
+            .set_dash_pattern([4.0, 3.0], 0.0);
+        if column + 1 < layout.columns {
+            let x = right - layout.overlap;
+            line(&mut content, (x, top - drawn_h), (x, top));
+        }
+        if row + 1 < layout.rows {
+            let y = top - (printable_h - layout.overlap);
+            line(&mut content, (left, y), (left + drawn_w, y));
+        }
+        content.restore_state();
+
+        let label = page_label(column, row);
+        // Header clears the crop marks; neighbour hints sit mid-edge below it
+        show_text(
+            &mut content,
+            left + CROP_MARK_LENGTH + 2.0 * CROP_MARK_GAP,
+            top + CROP_MARK_GAP + LABEL_SIZE,
+            &format!(
+                "{name} - page {label} ({} of {}) - {} x {} pages - 1 square = {}",
+                index + 1,
+                layout.page_count(),
+                layout.columns,
+                layout.rows,
+                options.square
+            ),
+        );
+        let mid_x = left + printable_w / 2.0;
+        let mid_y = bottom + printable_h / 2.0;
+        let gutter = layout.margin / 2.0;
+        if column > 0 {
+            show_text(
+                &mut content,
+                left - gutter - LABEL_SIZE,
+                mid_y,
+                &format!("< {}", page_label(column - 1, row)),
+            );
+        }
+        if column + 1 < layout.columns {
+            show_text(
+                &mut content,
+                right + CROP_MARK_GAP,
+                mid_y,
+                &format!("{} >", page_label(column + 1, row)),
+            );
+        }
+        if row > 0 {
+            show_text(
+                &mut content,
+                mid_x,
+                top + CROP_MARK_GAP,
+                &format!("^ {}", page_label(column, row - 1)),
+            );
+        }
+        if row + 1 < layout.rows {
+            show_text(
+                &mut content,
+                mid_x,
+                bottom - gutter,
+                &format!("v {}", page_label(column, row + 1)),
+            );
+        }
+
+        pdf.stream(content_id, &content.finish());
+    }
+
+    Ok(pdf.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tiles::encode_webp;
+    use base64::{Engine as _, engine::general_purpose};
+    use image::RgbaImage;
+
+    #[test]
+    fn test_layout_prefers_orientation_with_fewer_pages() {
+        // 48 x 27 squares at 128 px per grid
+        let layout = PrintLayout::new(6144, 3456, 128, &PrintOptions::default());
+        assert!(layout.page_width > layout.page_height);
+        assert_eq!((layout.columns, layout.rows), (5, 4));
+        assert!((layout.map_width - 48.0 * 72.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_pages_overlap_neighbours() {
+        let layout = PrintLayout::new(2048, 256, 128, &PrintOptions::default());
+        let (printable_w, _) = layout.printable();
+        let (next_x, _) = layout.page_origin(1, 0);
+        assert!((printable_w - next_x - 18.0).abs() < 0.01);
+
+        let (last_x, _) = layout.page_origin(layout.columns - 1, 0);
+        assert!(last_x + printable_w >= layout.map_width);
+        assert!(layout.page_origin(layout.columns - 2, 0).0 + printable_w < layout.map_width);
+    }
+
+    #[test]
+    fn test_small_map_fits_one_page() {
+        let options = PrintOptions {
+            paper: PaperSize::A4,
+            square: SquareSize::Millimeters25,
+            ..PrintOptions::default()
+        };
+        let layout = PrintLayout::new(512, 512, 128, &options);
+        assert_eq!(layout.page_count(), 1);
+        assert_eq!(page_label(0, 0), "A1");
+        assert_eq!(options.cache_key(), "print-a4-25mm.pdf");
+    }
+
+    #[test]
+    fn test_parse_print_options() {
+        assert_eq!("A4".parse::<PaperSize>(), Ok(PaperSize::A4));
+        assert_eq!("25mm".parse::<SquareSize>(), Ok(SquareSize::Millimeters25));
+        assert!("tabloid".parse::<PaperSize>().is_err());
+    }
+
+    #[test]
+    fn test_render_print_pdf_writes_one_page_per_tile() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(1536, 256));
+        let map: DD2VTTFile = serde_json::from_value(serde_json::json!({
+            "image": general_purpose::STANDARD.encode(encode_webp(&image).unwrap()),
+            "resolution": {
+                "map_size": { "x": 12, "y": 2 },
+                "pixels_per_grid": 128
+            }
+        }))
+        .unwrap();
+
+        let pdf = render_print_pdf(&map, "Test", &PrintOptions::default()).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with(b"%PDF"));
+        assert_eq!(text.matches("/Subtype /Image").count(), 2);
+        assert!(text.contains("(Test - page A1 (1 of 2)"));
+        assert!(text.contains("(B1 >)"));
+    }
+}
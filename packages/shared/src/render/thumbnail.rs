@@ -0,0 +1,173 @@
+use crate::render::variant::{
+    DEFAULT_QUALITY, MAX_VARIANT_EDGE, OutputFormat, VariantSpec, encode,
+};
+use image::{DynamicImage, GenericImageView, ImageError};
+use std::fmt;
+use std::str::FromStr;
+
+/// Sizes generated when none are configured: gallery cards, compact list
+/// rows and social link previews
+pub const DEFAULT_THUMBNAIL_SIZES: &str = "card=480x270:webp,list=160x90:webp,social=1200x630:png";
+
+/// A named bounding box a map thumbnail is fitted into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThumbnailSize {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub format: OutputFormat,
+}
+
+impl ThumbnailSize {
+    /// File name of this size within a map's thumbnail directory
+    #[must_use]
+    pub fn file_name(&self) -> String {
+        format!(
+            "{}-{}x{}.{}",
+            self.name,
+            self.width,
+            self.height,
+            self.format.extension()
+        )
+    }
+
+    /// Output size fitting the image inside the box without upscaling
+    #[must_use]
+    pub fn fit(&self, width: u32, height: u32) -> (u32, u32) {
+        VariantSpec {
+            width: Some(self.width),
+            height: Some(self.height),
+            ..VariantSpec::new(self.format)
+        }
+        .target_size(width, height)
+    }
+}
+
+impl fmt::Display for ThumbnailSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}={}x{}:{}",
+            self.name, self.width, self.height, self.format
+        )
+    }
+}
+
+/// Parses `name=WIDTHxHEIGHT[:webp|png]`, defaulting to WebP
+impl FromStr for ThumbnailSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, spec) = s
+            .trim()
+            .split_once('=')
+            .ok_or_else(|| format!("Thumbnail size must look like name=WxH: {s}"))?;
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("Invalid thumbnail name: {name}"));
+        }
+
+        let (dimensions, format) = spec.split_once(':').unwrap_or((spec, "webp"));
+        let format = match format.parse::<OutputFormat>()? {
+            format @ (OutputFormat::Webp | OutputFormat::Png) => format,
+            other => return Err(format!("Thumbnails are WebP or PNG, not {other}")),
+        };
+
+        let edge = |v: &str| {
+            v.parse::<u32>()
+                .ok()
+                .filter(|v| (1..=MAX_VARIANT_EDGE).contains(v))
+                .ok_or_else(|| format!("Invalid thumbnail dimension: {v}"))
+        };
+        let (width, height) = dimensions
+            .split_once('x')
+            .ok_or_else(|| format!("Thumbnail size must look like name=WxH: {s}"))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            width: edge(width)?,
+            height: edge(height)?,
+            format,
+        })
+    }
+}
+
+/// Parse a comma separated list of thumbnail sizes; the first one is the
+/// map's primary thumbnail
+///
+/// # Errors
+/// Returns an error if any size is malformed, names repeat, or the list is empty.
+pub fn parse_thumbnail_sizes(s: &str) -> Result<Vec<ThumbnailSize>, String> {
+    let sizes = s
+        .split(',')
+        .filter(|part| !part.trim().is_empty())
+        .map(str::parse::<ThumbnailSize>)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if sizes.is_empty() {
+        return Err("No thumbnail sizes configured".to_string());
+    }
+    for (i, size) in sizes.iter().enumerate() {
+        if sizes[..i].iter().any(|other| other.name == size.name) {
+            return Err(format!("Duplicate thumbnail name: {}", size.name));
+        }
+    }
+    Ok(sizes)
+}
+
+/// Fit a map image into `size` and encode it
+///
+/// # Errors
+/// Returns an error if the thumbnail cannot be encoded.
+pub fn render_thumbnail(image: &DynamicImage, size: &ThumbnailSize) -> Result<Vec<u8>, ImageError> {
+    let (width, height) = image.dimensions();
+    let (target_w, target_h) = size.fit(width, height);
+    encode(
+        &image.thumbnail_exact(target_w, target_h),
+        size.format,
+        DEFAULT_QUALITY,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_parse_thumbnail_sizes() {
+        let sizes = parse_thumbnail_sizes(DEFAULT_THUMBNAIL_SIZES).unwrap();
+        assert_eq!(sizes.len(), 3);
+        assert_eq!(sizes[0].file_name(), "card-480x270.webp");
+        assert_eq!(sizes[2].format, OutputFormat::Png);
+        assert_eq!(sizes[2].to_string(), "social=1200x630:png");
+
+        let sizes = parse_thumbnail_sizes(" tiny=64x64 ").unwrap();
+        assert_eq!(sizes[0].format, OutputFormat::Webp);
+
+        assert!(parse_thumbnail_sizes("").is_err());
+        assert!(parse_thumbnail_sizes("a=1x1,a=2x2").is_err());
+        assert!(parse_thumbnail_sizes("a=0x10").is_err());
+        assert!(parse_thumbnail_sizes("a=10x10:jpeg").is_err());
+        assert!(parse_thumbnail_sizes("../a=10x10").is_err());
+    }
+
+    #[test]
+    fn test_render_thumbnail_fits_box() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(4800, 2000));
+        let size: ThumbnailSize = "card=480x270:png".parse().unwrap();
+
+        let bytes = render_thumbnail(&image, &size).unwrap();
+        assert_eq!(
+            image::guess_format(&bytes).unwrap(),
+            image::ImageFormat::Png
+        );
+        assert_eq!(
+            image::load_from_memory(&bytes).unwrap().dimensions(),
+            (480, 200)
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageError};
+use serde::{Deserialize, Serialize};
+use std::io;
+use thiserror::Error;
+
+/// Edge length of a tile in pixels
+pub const TILE_SIZE: u32 = 256;
+
+#[derive(Error, Debug)]
+pub enum TileError {
+    #[error("Failed to encode tile: {0}")]
+    Encode(#[from] ImageError),
+
+    #[error("Failed to store tile: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Address of one tile in an XYZ pyramid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TileCoord {
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Layout of an XYZ tile pyramid over an image.
+///
+/// Level `max_zoom` is the image at full resolution; every level below halves
+/// it, down to level 0 which fits in a single tile. Edge tiles are cropped to
+/// the image rather than padded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TilePyramid {
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    pub max_zoom: u32,
+}
+
+impl TilePyramid {
+    #[must_use]
+    pub fn new(width: u32, height: u32, tile_size: u32) -> Self {
+        let tile_size = tile_size.max(1);
+        let longest = width.max(height).max(1);
+        let mut max_zoom = 0;
+        while u64::from(tile_size) << max_zoom < u64::from(longest) {
+            max_zoom += 1;
+        }
+        Self {
+            width,
+            height,
+            tile_size,
+            max_zoom,
+        }
+    }
+
+    /// Pixel size of the image at zoom level `z`
+    #[must_use]
+    pub fn level_size(&self, z: u32) -> Option<(u32, u32)> {
+        if z > self.max_zoom {
+            return None;
+        }
+        let scale = 1u64 << (self.max_zoom - z);
+        let shrink = |v: u32| {
+            u32::try_from(u64::from(v).div_ceil(scale))
+                .unwrap_or(1)
+                .max(1)
+        };
+        Some((shrink(self.width), shrink(self.height)))
+    }
+
+    /// Number of tile columns and rows at zoom level `z`
+    #[must_use]
+    pub fn tile_count(&self, z: u32) -> Option<(u32, u32)> {
+        self.level_size(z)
+            .map(|(w, h)| (w.div_ceil(self.tile_size), h.div_ceil(self.tile_size)))
+    }
+
+    #[must_use]
+    pub fn contains(&self, coord: TileCoord) -> bool {
+        self.tile_count(coord.z)
+            .is_some_and(|(cols, rows)| coord.x < cols && coord.y < rows)
+    }
+
+    /// Every tile in the pyramid, lowest zoom first
+    pub fn coords(&self) -> impl Iterator<Item = TileCoord> + '_ {
+        (0..=self.max_zoom).flat_map(move |z| {
+            let (cols, rows) = self.tile_count(z).unwrap_or((0, 0));
+            (0..rows).flat_map(move |y| (0..cols).map(move |x| TileCoord { z, x, y }))
+        })
+    }
+}
+
+/// Cut a tile out of an image already scaled to the tile's level
+#[must_use]
+pub fn crop_tile(level: &DynamicImage, tile_size: u32, x: u32, y: u32) -> DynamicImage {
+    let (width, height) = level.dimensions();
+    let left = x * tile_size;
+    let top = y * tile_size;
+    level.crop_imm(
+        left,
+        top,
+        tile_size.min(width.saturating_sub(left)),
+        tile_size.min(height.saturating_sub(top)),
+    )
+}
+
+/// Encode an image as lossless WebP
+///
+/// # Errors
+/// Returns an error if the image cannot be encoded.
+pub fn encode_webp(image: &DynamicImage) -> Result<Vec<u8>, ImageError> {
+    let mut out = Vec::new();
+    image
+        .to_rgba8()
+        .write_with_encoder(WebPEncoder::new_lossless(&mut out))?;
+    Ok(out)
+}
+
+/// Render every tile of the pyramid, handing each encoded WebP to `store`.
+///
+/// Levels are produced by repeatedly halving the previous one, so the source
+/// image is only resampled at full size once.
+///
+/// # Errors
+/// Returns an error if a tile cannot be encoded or `store` fails.
+pub fn generate_tiles<F>(
+    image: &DynamicImage,
+    pyramid: &TilePyramid,
+    mut store: F,
+) -> Result<usize, TileError>
+where
+    F: FnMut(TileCoord, &[u8]) -> io::Result<()>,
+{
+    let mut written = 0;
+    let mut level = image.clone();
+
+    for z in (0..=pyramid.max_zoom).rev() {
+        let (width, height) = pyramid.level_size(z).unwrap_or((1, 1));
+        if level.dimensions() != (width, height) {
+            level = level.resize_exact(width, height, FilterType::Triangle);
+        }
+
+        let (cols, rows) = pyramid.tile_count(z).unwrap_or((0, 0));
+        for y in 0..rows {
+            for x in 0..cols {
+                let tile = crop_tile(&level, pyramid.tile_size, x, y);
+                store(TileCoord { z, x, y }, &encode_webp(&tile)?)?;
+                written += 1;
+            }
+        }
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_pyramid_levels_for_battlemap() {
+        // 48x27 squares at 128 px per grid
+        let pyramid = TilePyramid::new(6144, 3456, TILE_SIZE);
+        assert_eq!(pyramid.max_zoom, 5);
+        assert_eq!(pyramid.level_size(5), Some((6144, 3456)));
+        assert_eq!(pyramid.tile_count(5), Some((24, 14)));
+        assert_eq!(pyramid.level_size(0), Some((192, 108)));
+        assert_eq!(pyramid.tile_count(0), Some((1, 1)));
+        assert_eq!(pyramid.level_size(6), None);
+    }
+
+    #[test]
+    fn test_pyramid_bounds() {
+        let pyramid = TilePyramid::new(600, 300, TILE_SIZE);
+        assert!(pyramid.contains(TileCoord { z: 2, x: 2, y: 1 }));
+        assert!(!pyramid.contains(TileCoord { z: 2, x: 3, y: 0 }));
+        assert!(!pyramid.contains(TileCoord { z: 3, x: 0, y: 0 }));
+        assert_eq!(pyramid.coords().count(), 1 + 2 + 6);
+    }
+
+    #[test]
+    fn test_small_image_is_single_tile() {
+        let pyramid = TilePyramid::new(100, 40, TILE_SIZE);
+        assert_eq!(pyramid.max_zoom, 0);
+        assert_eq!(pyramid.tile_count(0), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_generate_tiles_crops_edges() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(300, 260));
+        let pyramid = TilePyramid::new(300, 260, TILE_SIZE);
+        let mut sizes = Vec::new();
+
+        let written = generate_tiles(&image, &pyramid, |coord, bytes| {
+            let tile = image::load_from_memory(bytes).unwrap();
+            sizes.push((coord, tile.dimensions()));
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(written, 5);
+        assert!(sizes.contains(&(TileCoord { z: 1, x: 1, y: 1 }, (44, 4))));
+        assert!(sizes.contains(&(TileCoord { z: 0, x: 0, y: 0 }, (150, 130))));
+    }
+}
@@ -0,0 +1,418 @@
+use crate::render::grid::{GridLayout, GridStyle, draw_grid};
+use crate::render::overlay::{OverlayOptions, draw_overlay};
+use crate::render::tiles::encode_webp;
+use crate::types::dd2vtt::{DD2VTTFile, MapFileError};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageError, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest edge a variant may be resized to
+pub const MAX_VARIANT_EDGE: u32 = 8192;
+/// Edges requested sizes are rounded up to, bounding how many renditions of
+/// one map can exist
+pub const VARIANT_EDGES: [u32; 16] = [
+    64, 128, 256, 384, 512, 640, 768, 1024, 1280, 1536, 2048, 2560, 3072, 4096, 6144, 8192,
+];
+/// Lossy quality is rounded to a multiple of this
+const QUALITY_STEP: u8 = 10;
+pub const DEFAULT_QUALITY: u8 = 80;
+/// AVIF encoder speed (1 = slowest/best, 10 = fastest)
+const AVIF_SPEED: u8 = 8;
+
+/// Encodings a map image variant can be served in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Webp,
+    Avif,
+    Png,
+    Jpeg,
+}
+
+impl OutputFormat {
+    #[must_use]
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Avif => "image/avif",
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+        }
+    }
+
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+        }
+    }
+
+    /// Format of already encoded bytes, such as a cached rendition
+    #[must_use]
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        image::guess_format(bytes)
+            .ok()
+            .and_then(Self::from_image_format)
+    }
+
+    /// Output format matching a decoded image format, if it is one we encode
+    #[must_use]
+    pub fn from_image_format(format: ImageFormat) -> Option<Self> {
+        match format {
+            ImageFormat::WebP => Some(OutputFormat::Webp),
+            ImageFormat::Avif => Some(OutputFormat::Avif),
+            ImageFormat::Png => Some(OutputFormat::Png),
+            ImageFormat::Jpeg => Some(OutputFormat::Jpeg),
+            _ => None,
+        }
+    }
+
+    /// Choose the best format a client accepts, from an `Accept` header.
+    ///
+    /// Prefers AVIF, then WebP, and falls back to JPEG which every client can
+    /// display.
+    #[must_use]
+    pub fn negotiate(accept: &str) -> Self {
+        let accepts = |mime: &str| {
+            accept.split(',').any(|part| {
+                let mut params = part.split(';');
+                let name = params.next().unwrap_or_default().trim();
+                let rejected = params.any(|p| {
+                    p.trim()
+                        .strip_prefix("q=")
+                        .and_then(|q| q.parse::<f32>().ok())
+                        .is_some_and(|q| q <= 0.0)
+                });
+                name.eq_ignore_ascii_case(mime) && !rejected
+            })
+        };
+
+        if accepts("image/avif") {
+            OutputFormat::Avif
+        } else if accepts("image/webp") {
+            OutputFormat::Webp
+        } else {
+            OutputFormat::Jpeg
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpeg",
+        })
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "webp" => Ok(OutputFormat::Webp),
+            "avif" => Ok(OutputFormat::Avif),
+            "png" => Ok(OutputFormat::Png),
+            "jpeg" | "jpg" => Ok(OutputFormat::Jpeg),
+            other => Err(format!("Unsupported image format: {other}")),
+        }
+    }
+}
+
+/// A resized and transcoded rendition of a map image
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantSpec {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub format: OutputFormat,
+    pub quality: u8,
+    /// Grid burned into the image after resizing
+    pub grid: Option<GridStyle>,
+    /// Walls, doors and lights drawn over the image for review
+    pub overlay: Option<OverlayOptions>,
+}
+
+impl VariantSpec {
+    #[must_use]
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            width: None,
+            height: None,
+            format,
+            quality: DEFAULT_QUALITY,
+            grid: None,
+            overlay: None,
+        }
+    }
+
+    /// Bound the requested size and quality to sane values
+    #[must_use]
+    pub fn clamped(mut self) -> Self {
+        let clamp = |v: Option<u32>| v.filter(|v| *v > 0).map(|v| v.min(MAX_VARIANT_EDGE));
+        self.width = clamp(self.width);
+        self.height = clamp(self.height);
+        self.quality = self.quality.clamp(1, 100);
+        self.grid = self.grid.map(GridStyle::clamped);
+        self
+    }
+
+    /// Clamp, then snap size, quality and grid style to a bounded set of
+    /// renditions, so arbitrary requests can't mint unlimited cache entries
+    #[must_use]
+    pub fn quantized(self) -> Self {
+        let mut spec = self.clamped();
+        let snap = |v: Option<u32>| {
+            v.map(|v| {
+                VARIANT_EDGES
+                    .into_iter()
+                    .find(|edge| *edge >= v)
+                    .unwrap_or(MAX_VARIANT_EDGE)
+            })
+        };
+        spec.width = snap(spec.width);
+        spec.height = snap(spec.height);
+        spec.quality = (spec.quality.saturating_add(QUALITY_STEP / 2) / QUALITY_STEP
+            * QUALITY_STEP)
+            .clamp(QUALITY_STEP, 100);
+        spec.grid = spec.grid.map(GridStyle::quantized);
+        spec
+    }
+
+    /// Stable file name identifying this variant within a map's cache
+    #[must_use]
+    pub fn cache_key(&self) -> String {
+        let quality = match self.format {
+            // Lossless encoders ignore quality; share one cache entry
+            OutputFormat::Png | OutputFormat::Webp => 0,
+            OutputFormat::Avif | OutputFormat::Jpeg => self.quality,
+        };
+        let layers: String = [
+            self.grid.as_ref().map(GridStyle::cache_key),
+            self.overlay.as_ref().map(OverlayOptions::cache_key),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|key| format!("-{key}"))
+        .collect();
+        format!(
+            "w{}-h{}-q{}{}.{}",
+            self.width.unwrap_or(0),
+            self.height.unwrap_or(0),
+            quality,
+            layers,
+            self.format.extension()
+        )
+    }
+
+    /// Output size fitting inside the requested box without upscaling
+    #[must_use]
+    pub fn target_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (w, h) = (f64::from(width.max(1)), f64::from(height.max(1)));
+        let scale_w = self.width.map_or(1.0, |t| f64::from(t) / w);
+        let scale_h = self.height.map_or(1.0, |t| f64::from(t) / h);
+        let scale = scale_w.min(scale_h).min(1.0);
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let fit = |v: f64| ((v * scale).round() as u32).max(1);
+        (fit(w), fit(h))
+    }
+}
+
+/// Encode an image in the requested format
+///
+/// # Errors
+/// Returns an error if the image cannot be encoded.
+pub fn encode(
+    image: &DynamicImage,
+    format: OutputFormat,
+    quality: u8,
+) -> Result<Vec<u8>, ImageError> {
+    let mut out = Vec::new();
+    match format {
+        OutputFormat::Webp => return encode_webp(image),
+        OutputFormat::Avif => {
+            image
+                .to_rgba8()
+                .write_with_encoder(AvifEncoder::new_with_speed_quality(
+                    &mut out, AVIF_SPEED, quality,
+                ))?
+        }
+        OutputFormat::Png => image.write_with_encoder(PngEncoder::new(&mut out))?,
+        // JPEG has no alpha channel
+        OutputFormat::Jpeg => image
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, quality))?,
+    }
+    Ok(out)
+}
+
+fn resize(image: &DynamicImage, spec: &VariantSpec) -> Option<DynamicImage> {
+    let (width, height) = image.dimensions();
+    let (target_w, target_h) = spec.target_size(width, height);
+    ((target_w, target_h) != (width, height))
+        .then(|| image.resize_exact(target_w, target_h, FilterType::Lanczos3))
+}
+
+/// Resize and transcode an image according to `spec`.
+///
+/// Grid and geometry overlays need the map's data and are ignored here; use
+/// [`render_map_variant`] for those.
+///
+/// # Errors
+/// Returns an error if the image cannot be encoded.
+pub fn render_variant(image: &DynamicImage, spec: &VariantSpec) -> Result<Vec<u8>, ImageError> {
+    match resize(image, spec) {
+        Some(resized) => encode(&resized, spec.format, spec.quality),
+        None => encode(image, spec.format, spec.quality),
+    }
+}
+
+/// Render a variant of a map's embedded image, including its overlays
+///
+/// # Errors
+/// Returns an error if the embedded image cannot be decoded or encoded.
+pub fn render_map_variant(map: &DD2VTTFile, spec: &VariantSpec) -> Result<Vec<u8>, MapFileError> {
+    let image = map.decode_image()?;
+    if spec.grid.is_none() && spec.overlay.is_none() {
+        return Ok(render_variant(&image, spec)?);
+    }
+
+    let mut canvas = resize(&image, spec).map_or_else(|| image.to_rgba8(), |r| r.to_rgba8());
+    let factor = f64::from(canvas.width()) / f64::from(image.width().max(1));
+    let layout = GridLayout::from(map.resolution()).scaled(factor);
+
+    if let Some(style) = &spec.grid {
+        draw_grid(&mut canvas, &layout, style);
+    }
+    if let Some(options) = &spec.overlay {
+        draw_overlay(&mut canvas, map, &layout, options);
+    }
+    Ok(encode(
+        &DynamicImage::ImageRgba8(canvas),
+        spec.format,
+        spec.quality,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_negotiate_prefers_modern_formats() {
+        assert_eq!(
+            OutputFormat::negotiate("image/avif,image/webp,*/*"),
+            OutputFormat::Avif
+        );
+        assert_eq!(
+            OutputFormat::negotiate("image/webp,image/*;q=0.8"),
+            OutputFormat::Webp
+        );
+        assert_eq!(
+            OutputFormat::negotiate("image/avif;q=0,image/webp"),
+            OutputFormat::Webp
+        );
+        assert_eq!(OutputFormat::negotiate("*/*"), OutputFormat::Jpeg);
+        assert_eq!(OutputFormat::negotiate(""), OutputFormat::Jpeg);
+    }
+
+    #[test]
+    fn test_target_size_fits_box_without_upscaling() {
+        let mut spec = VariantSpec::new(OutputFormat::Png);
+        assert_eq!(spec.target_size(6144, 3456), (6144, 3456));
+
+        spec.width = Some(640);
+        assert_eq!(spec.target_size(6144, 3456), (640, 360));
+
+        spec.height = Some(100);
+        assert_eq!(spec.target_size(6144, 3456), (178, 100));
+
+        spec.width = Some(10_000);
+        spec.height = None;
+        assert_eq!(spec.target_size(600, 300), (600, 300));
+    }
+
+    #[test]
+    fn test_cache_key_ignores_quality_for_lossless() {
+        let mut a = VariantSpec::new(OutputFormat::Webp);
+        let mut b = a.clone();
+        a.quality = 10;
+        b.quality = 90;
+        assert_eq!(a.cache_key(), b.cache_key());
+
+        a.format = OutputFormat::Jpeg;
+        b.format = OutputFormat::Jpeg;
+        assert_ne!(a.cache_key(), b.cache_key());
+
+        b.quality = 10;
+        b.grid = Some(GridStyle::default());
+        assert_ne!(a.cache_key(), b.cache_key());
+    }
+
+    #[test]
+    fn test_quantized_snaps_size_and_quality() {
+        let spec = VariantSpec {
+            width: Some(1000),
+            height: Some(20_000),
+            quality: 84,
+            ..VariantSpec::new(OutputFormat::Jpeg)
+        }
+        .quantized();
+        assert_eq!(spec.width, Some(1024));
+        assert_eq!(spec.height, Some(MAX_VARIANT_EDGE));
+        assert_eq!(spec.quality, 80);
+
+        let spec = VariantSpec {
+            quality: 1,
+            ..VariantSpec::new(OutputFormat::Jpeg)
+        }
+        .quantized();
+        assert_eq!(spec.quality, 10);
+    }
+
+    #[test]
+    fn test_render_variant_resizes_and_transcodes() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(400, 200));
+        let spec = VariantSpec {
+            width: Some(100),
+            ..VariantSpec::new(OutputFormat::Jpeg)
+        };
+
+        let bytes = render_variant(&image, &spec).unwrap();
+        assert_eq!(
+            image::guess_format(&bytes).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+        assert_eq!(
+            image::load_from_memory(&bytes).unwrap().dimensions(),
+            (100, 50)
+        );
+    }
+
+    #[test]
+    fn test_sniff_recognizes_rendered_formats() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(16, 16));
+        for format in [
+            OutputFormat::Png,
+            OutputFormat::Jpeg,
+            OutputFormat::Webp,
+            OutputFormat::Avif,
+        ] {
+            let bytes = render_variant(&image, &VariantSpec::new(format)).unwrap();
+            assert_eq!(OutputFormat::sniff(&bytes), Some(format));
+        }
+        assert_eq!(OutputFormat::sniff(b"%PDF-1.7"), None);
+    }
+}
@@ -0,0 +1,76 @@
+//! URL layout of the static site export.
+//!
+//! The exporter writes each file at its URL path below the output directory
+//! and the frontend's `static-site` build requests the same paths, so both
+//! sides agree without a server to route queries. These keep the
+//! unversioned `/api` prefix the export has always used.
+
+/// Maps per catalog page; matches the frontend's default page size
+pub const CATALOG_PAGE_SIZE: usize = 100;
+
+/// Page `page` of the catalog, zero based
+#[must_use]
+pub fn catalog_page(page: usize) -> String {
+    format!("/api/maps/pages/{page}.json")
+}
+
+/// Catalog page holding the map at `offset`
+#[must_use]
+pub fn catalog_page_for_offset(offset: usize) -> String {
+    catalog_page(offset / CATALOG_PAGE_SIZE)
+}
+
+/// A single `MapDocument`
+#[must_use]
+pub fn map_document(id: &str) -> String {
+    format!("/api/maps/{id}.json")
+}
+
+/// The map's notes rendered to HTML
+#[must_use]
+pub fn map_content(id: &str) -> String {
+    format!("/api/maps/content/{id}.html")
+}
+
+/// The map's full resolution image
+#[must_use]
+pub fn map_image(id: &str) -> String {
+    format!("/api/maps/tiled/{id}.webp")
+}
+
+/// The original `.dd2vtt` file
+#[must_use]
+pub fn map_download(id: &str) -> String {
+    format!("/api/maps/download/{id}.dd2vtt")
+}
+
+/// Root of a map's tile pyramid, holding `pyramid.json` and `{z}/{x}/{y}.webp`
+#[must_use]
+pub fn tiles(id: &str) -> String {
+    format!("/api/maps/tiles/{id}")
+}
+
+/// A repository document such as `readme` or `license`, rendered to HTML
+#[must_use]
+pub fn doc_page(name: &str) -> String {
+    format!("/api/docs/{name}.html")
+}
+
+/// A map's detail page, pre-rendered with its SEO metadata. Static hosts
+/// serve `/maps/{id}.html` for `/maps/{id}`, so the SPA route is unchanged.
+#[must_use]
+pub fn map_page(id: &str) -> String {
+    format!("/maps/{id}.html")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_page_for_offset() {
+        assert_eq!(catalog_page_for_offset(0), "/api/maps/pages/0.json");
+        assert_eq!(catalog_page_for_offset(99), "/api/maps/pages/0.json");
+        assert_eq!(catalog_page_for_offset(100), "/api/maps/pages/1.json");
+    }
+}
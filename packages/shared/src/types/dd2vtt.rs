@@ -1,19 +1,81 @@
-use image::ImageReader;
+use base64::{Engine as _, engine::general_purpose};
+use image::{DynamicImage, ImageError, ImageFormat, ImageReader};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io::{Cursor, Read};
+use std::io::{self, Cursor, Read};
 use std::path::{Path, PathBuf};
+use thiserror::Error;
 
-use crate::decode;
+use crate::types::map_geometry::{Light, Polyline, Portal};
 use crate::types::map_resolution::MapResolution;
 use serde_json;
 
+#[derive(Error, Debug)]
+pub enum MapFileError {
+    #[error("Failed to read map file: {0}")]
+    Read(#[from] io::Error),
+
+    #[error("Failed to parse map file: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("Invalid embedded image data: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("Failed to decode embedded image: {0}")]
+    Image(#[from] ImageError),
+}
+
+/// Raw bytes of the image embedded in a map file, with its sniffed format
+#[derive(Debug, Clone)]
+pub struct EmbeddedImage {
+    pub bytes: Vec<u8>,
+    pub format: ImageFormat,
+}
+
+impl EmbeddedImage {
+    #[must_use]
+    pub fn mime_type(&self) -> &'static str {
+        self.format.to_mime_type()
+    }
+
+    /// Preferred file extension, e.g. `webp` or `png`
+    #[must_use]
+    pub fn extension(&self) -> &'static str {
+        self.format
+            .extensions_str()
+            .first()
+            .copied()
+            .unwrap_or("bin")
+    }
+
+    /// Decodes the image into pixels.
+    ///
+    /// # Errors
+    /// Returns an error if the data cannot be decoded.
+    pub fn decode(&self) -> Result<DynamicImage, ImageError> {
+        image::load_from_memory_with_format(&self.bytes, self.format)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DD2VTTFile {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub path: Option<PathBuf>,
+    /// Universal VTT format version, e.g. `0.3`
+    #[serde(default)]
+    pub format: Option<f64>,
     pub image: String,
     pub(crate) resolution: MapResolution,
+    /// Walls
+    #[serde(default)]
+    pub line_of_sight: Vec<Polyline>,
+    /// Occlusion lines of objects such as pillars and furniture
+    #[serde(default)]
+    pub objects_line_of_sight: Vec<Polyline>,
+    #[serde(default)]
+    pub portals: Vec<Portal>,
+    #[serde(default)]
+    pub lights: Vec<Light>,
 }
 
 impl DD2VTTFile {
@@ -31,18 +93,86 @@ impl DD2VTTFile {
         dd2vtt_file
     }
 
-    /// Exports a thumbnail image to the specified output path.
+    /// Loads a DD2VTT file, returning an error instead of panicking.
     ///
-    /// # Panics
-    /// Panics if the image format cannot be guessed, the image cannot be decoded, or the thumbnail cannot be saved.
-    pub fn export_thumbnail_file(self, output: &Path) {
-        let bytes = decode(self.image);
-        let img = ImageReader::new(Cursor::new(bytes))
-            .with_guessed_format()
-            .expect("Unable to guess image format")
-            .decode()
-            .expect("Unable to decode image");
-        let thumbnail = img.thumbnail(img.width() / 16, img.height() / 16);
-        thumbnail.save(output).expect("Unable to save thumbnail");
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, MapFileError> {
+        let file = fs::File::open(path)?;
+        let mut dd2vtt_file: DD2VTTFile = serde_json::from_reader(io::BufReader::new(file))?;
+        dd2vtt_file.path = Some(path.to_path_buf());
+        Ok(dd2vtt_file)
+    }
+
+    #[must_use]
+    pub fn resolution(&self) -> &MapResolution {
+        &self.resolution
+    }
+
+    /// Decodes the base64 embedded image into raw file bytes.
+    ///
+    /// # Errors
+    /// Returns an error if the embedded data is not valid base64.
+    pub fn image_bytes(&self) -> Result<Vec<u8>, MapFileError> {
+        Ok(general_purpose::STANDARD.decode(&self.image)?)
+    }
+
+    /// Decodes the embedded image and detects its format from the magic bytes.
+    ///
+    /// Dungeondraft exports embed either PNG or WebP, so the format must not
+    /// be assumed.
+    ///
+    /// # Errors
+    /// Returns an error if the data is not valid base64 or not a known image format.
+    pub fn embedded_image(&self) -> Result<EmbeddedImage, MapFileError> {
+        let bytes = self.image_bytes()?;
+        let format = image::guess_format(&bytes)?;
+        Ok(EmbeddedImage { bytes, format })
+    }
+
+    /// Decodes the embedded image into pixels.
+    ///
+    /// # Errors
+    /// Returns an error if the embedded data cannot be decoded as an image.
+    pub fn decode_image(&self) -> Result<DynamicImage, MapFileError> {
+        let img = ImageReader::new(Cursor::new(self.image_bytes()?))
+            .with_guessed_format()?
+            .decode()?;
+        Ok(img)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tiles::encode_webp;
+    use image::RgbaImage;
+
+    fn map_with_image(bytes: &[u8]) -> DD2VTTFile {
+        serde_json::from_value(serde_json::json!({
+            "image": general_purpose::STANDARD.encode(bytes),
+            "resolution": {
+                "map_size": { "x": 1, "y": 1 },
+                "pixels_per_grid": 4
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_embedded_image_sniffs_webp() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+        let map = map_with_image(&encode_webp(&image).unwrap());
+
+        let embedded = map.embedded_image().unwrap();
+        assert_eq!(embedded.format, ImageFormat::WebP);
+        assert_eq!(embedded.mime_type(), "image/webp");
+        assert_eq!(embedded.extension(), "webp");
+    }
+
+    #[test]
+    fn test_embedded_image_rejects_unknown_data() {
+        let map = map_with_image(b"not an image");
+        assert!(matches!(map.embedded_image(), Err(MapFileError::Image(_))));
     }
 }
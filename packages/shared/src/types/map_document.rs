@@ -1,12 +1,18 @@
 use crate::types::map_resolution::MapResolution;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct MapDocument {
     pub id: String,
     pub name: String,
     pub path: String,
+    /// URL of the primary thumbnail
     pub thumbnail: String,
+    /// Thumbnail URLs by configured size name (e.g. `card`, `list`, `social`)
+    #[serde(default)]
+    pub thumbnails: BTreeMap<String, String>,
     pub content: Option<String>,
     pub resolution: MapResolution,
 }
@@ -0,0 +1,35 @@
+use crate::types::map_resolution::Point;
+use serde::{Deserialize, Serialize};
+
+/// Connected wall or occluder segments, in grid units
+pub type Polyline = Vec<Point>;
+
+/// A door or window; blocks sight while closed
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Portal {
+    pub position: Point,
+    /// The two end points of the opening
+    #[serde(default)]
+    pub bounds: Vec<Point>,
+    #[serde(default)]
+    pub rotation: f64,
+    #[serde(default)]
+    pub closed: bool,
+    #[serde(default)]
+    pub freestanding: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Light {
+    pub position: Point,
+    /// Radius in grid units
+    #[serde(default)]
+    pub range: f64,
+    #[serde(default)]
+    pub intensity: f64,
+    /// `AARRGGBB` hex
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub shadows: bool,
+}
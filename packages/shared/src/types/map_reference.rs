@@ -39,9 +39,15 @@ use crate::types::dd2vtt::DD2VTTFile;
 
 impl From<DD2VTTFile> for MapReference {
     fn from(value: DD2VTTFile) -> Self {
-        let path = value.path.expect("DD2VTTFile path missing");
+        Self::from(&value)
+    }
+}
+
+impl From<&DD2VTTFile> for MapReference {
+    fn from(value: &DD2VTTFile) -> Self {
+        let path = value.path.as_ref().expect("DD2VTTFile path missing");
         let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
-        let bytes = std::fs::read(&path)
+        let bytes = std::fs::read(path)
             .unwrap_or_else(|_| panic!("Unable to read file bytes: {}", path.display()));
         let hash = Sha256::digest(&bytes);
 
@@ -50,7 +56,7 @@ impl From<DD2VTTFile> for MapReference {
             path: path.to_string_lossy().to_string(),
             hash: format!("{hash:x}"),
             bytes: bytes.len() as u64,
-            resolution: value.resolution,
+            resolution: value.resolution.clone(),
         }
     }
 }
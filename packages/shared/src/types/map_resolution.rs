@@ -1,13 +1,26 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Coordinates {
     pub x: u16,
     pub y: u16,
 }
 
+/// A position in grid units; fractional for walls, lights and offset origins
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct MapResolution {
+    /// Grid coordinate of the image's top-left corner
+    #[serde(default)]
+    pub map_origin: Point,
     pub map_size: Coordinates,
     pub pixels_per_grid: u16,
 }
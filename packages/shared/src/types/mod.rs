@@ -1,4 +1,5 @@
 pub mod dd2vtt;
 pub mod map_document;
+pub mod map_geometry;
 pub mod map_reference;
 pub mod map_resolution;
@@ -14,3 +14,12 @@ pub fn capitalize(s: &str) -> String {
         Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
     }
 }
+
+/// Lowercases and joins whitespace separated words with `-`, e.g. for file names
+#[must_use]
+pub fn kebabcase(s: &str) -> String {
+    s.to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
@@ -0,0 +1,370 @@
+use crate::types::dd2vtt::DD2VTTFile;
+use crate::types::map_geometry::Polyline;
+use crate::types::map_resolution::Point;
+use image::ImageReader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Cursor;
+
+/// Universal VTT format versions importers are known to handle
+pub const SUPPORTED_FORMATS: [f64; 2] = [0.2, 0.3];
+/// Embedded images larger than this slow down every download and import
+pub const MAX_IMAGE_BYTES: usize = 32 * 1024 * 1024;
+/// Geometry may sit this far (in grid units) outside the map before it is flagged
+const BOUNDS_TOLERANCE: f64 = 0.01;
+/// Segments shorter than this (in grid units) are treated as points
+const MIN_SEGMENT_LENGTH: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LintCode {
+    InvalidImage,
+    ImageSizeMismatch,
+    OversizedImage,
+    UnsupportedFormat,
+    WallOutOfBounds,
+    PortalOutOfBounds,
+    DegenerateSegment,
+    DuplicateSegment,
+    LightZeroRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LintIssue {
+    pub severity: Severity,
+    pub code: LintCode,
+    pub message: String,
+}
+
+/// Findings for a single map, most severe first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LintReport {
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    fn push(&mut self, severity: Severity, code: LintCode, message: String) {
+        self.issues.push(LintIssue {
+            severity,
+            code,
+            message,
+        });
+    }
+
+    #[must_use]
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    #[must_use]
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    }
+}
+
+/// Area covered by the map, in grid units
+struct Bounds {
+    min: Point,
+    max: Point,
+}
+
+impl Bounds {
+    fn contains(&self, p: &Point) -> bool {
+        p.x >= self.min.x - BOUNDS_TOLERANCE
+            && p.y >= self.min.y - BOUNDS_TOLERANCE
+            && p.x <= self.max.x + BOUNDS_TOLERANCE
+            && p.y <= self.max.y + BOUNDS_TOLERANCE
+    }
+}
+
+/// Segment key independent of direction, quantized so float noise still matches
+fn segment_key(a: &Point, b: &Point) -> [i64; 4] {
+    // Map coordinates are small, so the scaled values fit in i64
+    #[allow(clippy::cast_possible_truncation)]
+    let q = |v: f64| (v * 1000.0).round() as i64;
+    let (a, b) = ((q(a.x), q(a.y)), (q(b.x), q(b.y)));
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    [first.0, first.1, second.0, second.1]
+}
+
+fn check_image(map: &DD2VTTFile, report: &mut LintReport) {
+    let bytes = match map.image_bytes() {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            report.push(Severity::Error, LintCode::InvalidImage, e.to_string());
+            return;
+        }
+    };
+
+    if bytes.len() > MAX_IMAGE_BYTES {
+        report.push(
+            Severity::Warning,
+            LintCode::OversizedImage,
+            format!(
+                "Embedded image is {:.1} MiB (limit {} MiB)",
+                bytes.len() as f64 / 1024.0 / 1024.0,
+                MAX_IMAGE_BYTES / 1024 / 1024
+            ),
+        );
+    }
+
+    let dimensions = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())
+        .and_then(|reader| reader.into_dimensions().map_err(|e| e.to_string()));
+    let (width, height) = match dimensions {
+        Ok(dimensions) => dimensions,
+        Err(e) => {
+            report.push(Severity::Error, LintCode::InvalidImage, e);
+            return;
+        }
+    };
+
+    let resolution = map.resolution();
+    let ppg = u32::from(resolution.pixels_per_grid);
+    let expected = (
+        u32::from(resolution.map_size.x) * ppg,
+        u32::from(resolution.map_size.y) * ppg,
+    );
+    if (width, height) != expected {
+        report.push(
+            Severity::Error,
+            LintCode::ImageSizeMismatch,
+            format!(
+                "Image is {width}x{height} px but map_size {}x{} at {ppg} px per grid needs {}x{}",
+                resolution.map_size.x, resolution.map_size.y, expected.0, expected.1
+            ),
+        );
+    }
+}
+
+fn check_format(map: &DD2VTTFile, report: &mut LintReport) {
+    match map.format {
+        Some(version)
+            if SUPPORTED_FORMATS
+                .iter()
+                .any(|supported| (supported - version).abs() < 1e-9) => {}
+        Some(version) => report.push(
+            Severity::Error,
+            LintCode::UnsupportedFormat,
+            format!("Unsupported format version {version}"),
+        ),
+        None => report.push(
+            Severity::Warning,
+            LintCode::UnsupportedFormat,
+            "Missing format version".to_string(),
+        ),
+    }
+}
+
+fn check_walls(map: &DD2VTTFile, bounds: &Bounds, report: &mut LintReport) {
+    let walls: Vec<&Polyline> = map
+        .line_of_sight
+        .iter()
+        .chain(&map.objects_line_of_sight)
+        .collect();
+
+    let outside = walls
+        .iter()
+        .filter(|wall| wall.iter().any(|p| !bounds.contains(p)))
+        .count();
+    if outside > 0 {
+        report.push(
+            Severity::Warning,
+            LintCode::WallOutOfBounds,
+            format!("{outside} of {} walls extend outside the map", walls.len()),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    let (mut degenerate, mut duplicate) = (0, 0);
+    for pair in walls.iter().flat_map(|wall| wall.windows(2)) {
+        let (a, b) = (&pair[0], &pair[1]);
+        if (b.x - a.x).hypot(b.y - a.y) < MIN_SEGMENT_LENGTH {
+            degenerate += 1;
+        } else if !seen.insert(segment_key(a, b)) {
+            duplicate += 1;
+        }
+    }
+    if degenerate > 0 {
+        report.push(
+            Severity::Warning,
+            LintCode::DegenerateSegment,
+            format!("{degenerate} wall segments have zero length"),
+        );
+    }
+    if duplicate > 0 {
+        report.push(
+            Severity::Warning,
+            LintCode::DuplicateSegment,
+            format!("{duplicate} wall segments are duplicated"),
+        );
+    }
+}
+
+fn check_portals(map: &DD2VTTFile, bounds: &Bounds, report: &mut LintReport) {
+    let outside = map
+        .portals
+        .iter()
+        .filter(|portal| {
+            !bounds.contains(&portal.position) || portal.bounds.iter().any(|p| !bounds.contains(p))
+        })
+        .count();
+    if outside > 0 {
+        report.push(
+            Severity::Warning,
+            LintCode::PortalOutOfBounds,
+            format!(
+                "{outside} of {} doors lie outside the map",
+                map.portals.len()
+            ),
+        );
+    }
+}
+
+fn check_lights(map: &DD2VTTFile, report: &mut LintReport) {
+    let dark = map.lights.iter().filter(|light| light.range <= 0.0).count();
+    if dark > 0 {
+        report.push(
+            Severity::Warning,
+            LintCode::LightZeroRange,
+            format!("{dark} lights have no range"),
+        );
+    }
+}
+
+/// Check a map for problems that make it import badly into a VTT
+#[must_use]
+pub fn lint_map(map: &DD2VTTFile) -> LintReport {
+    let resolution = map.resolution();
+    let bounds = Bounds {
+        min: resolution.map_origin,
+        max: Point {
+            x: resolution.map_origin.x + f64::from(resolution.map_size.x),
+            y: resolution.map_origin.y + f64::from(resolution.map_size.y),
+        },
+    };
+
+    let mut report = LintReport::default();
+    check_format(map, &mut report);
+    check_image(map, &mut report);
+    check_walls(map, &bounds, &mut report);
+    check_portals(map, &bounds, &mut report);
+    check_lights(map, &mut report);
+    report
+        .issues
+        .sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tiles::encode_webp;
+    use base64::{Engine as _, engine::general_purpose};
+    use image::{DynamicImage, RgbaImage};
+    use serde_json::json;
+
+    fn map(width: u32, height: u32, extra: serde_json::Value) -> DD2VTTFile {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(width, height));
+        let mut value = json!({
+            "format": 0.3,
+            "image": general_purpose::STANDARD.encode(encode_webp(&image).unwrap()),
+            "resolution": {
+                "map_origin": { "x": 0, "y": 0 },
+                "map_size": { "x": 2, "y": 2 },
+                "pixels_per_grid": 16
+            }
+        });
+        if let (Some(base), Some(extra)) = (value.as_object_mut(), extra.as_object()) {
+            base.extend(extra.clone());
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn codes(report: &LintReport) -> Vec<LintCode> {
+        report.issues.iter().map(|issue| issue.code).collect()
+    }
+
+    #[test]
+    fn test_clean_map_has_no_issues() {
+        let report = lint_map(&map(
+            32,
+            32,
+            json!({ "line_of_sight": [[{ "x": 0, "y": 0 }, { "x": 2, "y": 0 }]] }),
+        ));
+        assert!(report.is_clean(), "{report:?}");
+    }
+
+    #[test]
+    fn test_image_size_mismatch_is_an_error() {
+        let report = lint_map(&map(30, 32, json!({ "format": 0.9 })));
+        assert_eq!(report.errors(), 2);
+        assert!(codes(&report).contains(&LintCode::ImageSizeMismatch));
+        assert!(codes(&report).contains(&LintCode::UnsupportedFormat));
+    }
+
+    #[test]
+    fn test_geometry_problems_are_flagged() {
+        let report = lint_map(&map(
+            32,
+            32,
+            json!({
+                "line_of_sight": [
+                    [{ "x": 0, "y": 0 }, { "x": 1, "y": 0 }, { "x": 1, "y": 0 }],
+                    [{ "x": 1, "y": 0 }, { "x": 0, "y": 0 }],
+                    [{ "x": 5, "y": 5 }, { "x": 6, "y": 5 }]
+                ],
+                "portals": [{
+                    "position": { "x": -3, "y": 1 },
+                    "bounds": [{ "x": -3, "y": 0.5 }, { "x": -3, "y": 1.5 }],
+                    "rotation": 0,
+                    "closed": true,
+                    "freestanding": false
+                }],
+                "lights": [{ "position": { "x": 1, "y": 1 }, "range": 0, "intensity": 1, "color": "ffffffff", "shadows": true }]
+            }),
+        ));
+
+        assert_eq!(report.errors(), 0);
+        assert_eq!(
+            codes(&report),
+            vec![
+                LintCode::WallOutOfBounds,
+                LintCode::DegenerateSegment,
+                LintCode::DuplicateSegment,
+                LintCode::PortalOutOfBounds,
+                LintCode::LightZeroRange,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_image_is_an_error() {
+        let mut broken = map(32, 32, json!({}));
+        broken.image = "not base64!".to_string();
+        assert_eq!(codes(&lint_map(&broken)), vec![LintCode::InvalidImage]);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::commands::map_stem;
+use anyhow::Context;
+use clap::Args;
+use shared::render::grid::GridStyle;
+use shared::render::print::{PaperSize, PrintOptions, SquareSize, render_print_pdf};
+use shared::render::variant::{OutputFormat, VariantSpec, render_map_variant};
+use shared::types::dd2vtt::DD2VTTFile;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// The `.dd2vtt` file to export
+    pub input: PathBuf,
+
+    /// Output file; `.pdf` prints tiled pages, an image extension
+    /// (`png`, `jpg`, `webp`, `avif`) exports the map image
+    #[arg(short, long)]
+    pub output: PathBuf,
+
+    /// Scale the image down to at most this many pixels wide
+    #[arg(short, long)]
+    pub width: Option<u32>,
+
+    /// Burn the grid into the image
+    #[arg(short, long)]
+    pub grid: bool,
+
+    /// Encoder quality for lossy formats (1-100)
+    #[arg(short, long)]
+    pub quality: Option<u8>,
+
+    /// Paper for PDF output: `letter` or `a4`
+    #[arg(long, default_value = "letter")]
+    pub paper: String,
+
+    /// Printed size of one grid square for PDF output: `1in` or `25mm`
+    #[arg(long, default_value = "1in")]
+    pub square: String,
+}
+
+pub fn run(args: &ExportArgs) -> anyhow::Result<()> {
+    let extension = args
+        .output
+        .extension()
+        .and_then(|e| e.to_str())
+        .context("Output file needs an extension")?
+        .to_ascii_lowercase();
+
+    let map = DD2VTTFile::load(&args.input)
+        .with_context(|| format!("Failed to load {}", args.input.display()))?;
+
+    let bytes = if extension == "pdf" {
+        let options = PrintOptions {
+            paper: args
+                .paper
+                .parse::<PaperSize>()
+                .map_err(anyhow::Error::msg)?,
+            square: args
+                .square
+                .parse::<SquareSize>()
+                .map_err(anyhow::Error::msg)?,
+            ..PrintOptions::default()
+        };
+        render_print_pdf(&map, &map_stem(&args.input), &options)?
+    } else {
+        let mut spec = VariantSpec {
+            width: args.width,
+            grid: args.grid.then(GridStyle::default),
+            ..VariantSpec::new(
+                extension
+                    .parse::<OutputFormat>()
+                    .map_err(anyhow::Error::msg)?,
+            )
+        };
+        if let Some(quality) = args.quality {
+            spec.quality = quality;
+        }
+        render_map_variant(&map, &spec.clamped())?
+    };
+
+    fs::write(&args.output, &bytes)
+        .with_context(|| format!("Failed to write {}", args.output.display()))?;
+    println!(
+        "{} -> {} ({} bytes)",
+        args.input.display(),
+        args.output.display(),
+        bytes.len()
+    );
+    Ok(())
+}
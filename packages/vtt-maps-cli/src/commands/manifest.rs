@@ -0,0 +1,39 @@
+use crate::commands::collect_maps;
+use anyhow::Context;
+use clap::Args;
+use shared::types::dd2vtt::DD2VTTFile;
+use shared::types::map_reference::MapReference;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ManifestArgs {
+    /// Files or directories to describe (defaults to `maps/`)
+    pub paths: Vec<PathBuf>,
+
+    /// Write the manifest to a file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+pub fn run(args: &ManifestArgs) -> anyhow::Result<()> {
+    let references = collect_maps(&args.paths)?
+        .iter()
+        .map(|path| {
+            let map = DD2VTTFile::load(path)
+                .with_context(|| format!("Failed to load {}", path.display()))?;
+            Ok(MapReference::from(&map))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let json = serde_json::to_string_pretty(&references)?;
+    match &args.output {
+        Some(output) => {
+            fs::write(output, json)
+                .with_context(|| format!("Failed to write {}", output.display()))?;
+            eprintln!("{} maps written to {}", references.len(), output.display());
+        }
+        None => println!("{json}"),
+    }
+    Ok(())
+}
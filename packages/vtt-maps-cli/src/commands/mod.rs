@@ -0,0 +1,75 @@
+pub mod export;
+pub mod manifest;
+pub mod overlay;
+pub mod scan;
+pub mod thumbnails;
+pub mod validate;
+
+use anyhow::Context;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory searched when no paths are given, relative to the repository root
+const DEFAULT_MAPS_DIR: &str = "maps";
+
+fn walk(dir: &Path, out: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(&path, out)?;
+        } else if path.extension().is_some_and(|e| e == "dd2vtt") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Expand files and directories into the `.dd2vtt` files they contain, sorted
+pub fn collect_maps(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let default = [PathBuf::from(DEFAULT_MAPS_DIR)];
+    let paths = if paths.is_empty() {
+        &default[..]
+    } else {
+        paths
+    };
+
+    let mut maps = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk(path, &mut maps)?;
+        } else if path.is_file() {
+            maps.push(path.clone());
+        } else {
+            anyhow::bail!("No such file or directory: {}", path.display());
+        }
+    }
+    maps.sort();
+    maps.dedup();
+    Ok(maps)
+}
+
+/// File name of a map without its extension
+pub fn map_stem(path: &Path) -> String {
+    path.file_stem()
+        .map_or_else(|| "map".to_string(), |s| s.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_maps_walks_directories() {
+        let dir = std::env::temp_dir().join(format!("vtt-maps-cli-{}", std::process::id()));
+        fs::create_dir_all(dir.join("rooms")).unwrap();
+        fs::write(dir.join("b.dd2vtt"), "{}").unwrap();
+        fs::write(dir.join("rooms/a.dd2vtt"), "{}").unwrap();
+        fs::write(dir.join("rooms/a.md"), "").unwrap();
+
+        let maps = collect_maps(&[dir.clone(), dir.join("b.dd2vtt")]).unwrap();
+        assert_eq!(maps, vec![dir.join("b.dd2vtt"), dir.join("rooms/a.dd2vtt")]);
+        assert!(collect_maps(&[dir.join("missing")]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use anyhow::Context;
+use clap::Args;
+use shared::render::grid::GridStyle;
+use shared::render::overlay::OverlayOptions;
+use shared::render::variant::{OutputFormat, VariantSpec, render_map_variant};
+use shared::types::dd2vtt::DD2VTTFile;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct OverlayArgs {
+    /// The `.dd2vtt` file to render
+    pub input: PathBuf,
+
+    /// Output image; the extension picks the format (defaults to `<input>.overlay.png`)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// `all` or a comma separated list of `walls`, `objects`, `doors`, `lights`
+    #[arg(short, long, default_value = "all")]
+    pub layers: String,
+
+    /// Scale the image down to at most this many pixels wide
+    #[arg(short, long)]
+    pub width: Option<u32>,
+
+    /// Also draw the grid
+    #[arg(short, long)]
+    pub grid: bool,
+}
+
+fn default_output(input: &Path) -> PathBuf {
+    input.with_extension("overlay.png")
+}
+
+fn output_format(path: &Path) -> anyhow::Result<OutputFormat> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .context("Output file needs an image extension")?;
+    extension.parse().map_err(anyhow::Error::msg)
+}
+
+pub fn run(args: &OverlayArgs) -> anyhow::Result<()> {
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_output(&args.input));
+    let spec = VariantSpec {
+        width: args.width,
+        grid: args.grid.then(GridStyle::default),
+        overlay: Some(OverlayOptions::parse(&args.layers).map_err(anyhow::Error::msg)?),
+        ..VariantSpec::new(output_format(&output)?)
+    }
+    .clamped();
+
+    let map = DD2VTTFile::load(&args.input)
+        .with_context(|| format!("Failed to load {}", args.input.display()))?;
+    let bytes = render_map_variant(&map, &spec)?;
+    fs::write(&output, bytes).with_context(|| format!("Failed to write {}", output.display()))?;
+
+    println!(
+        "{}: {} walls, {} object lines, {} doors, {} lights -> {}",
+        args.input.display(),
+        map.line_of_sight.len(),
+        map.objects_line_of_sight.len(),
+        map.portals.len(),
+        map.lights.len(),
+        output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use crate::commands::{collect_maps, map_stem};
+use clap::Args;
+use shared::types::dd2vtt::DD2VTTFile;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ScanArgs {
+    /// Files or directories to scan (defaults to `maps/`)
+    pub paths: Vec<PathBuf>,
+}
+
+pub fn run(args: &ScanArgs) -> anyhow::Result<()> {
+    let maps = collect_maps(&args.paths)?;
+    let mut unreadable = 0;
+
+    for path in &maps {
+        match DD2VTTFile::load(path) {
+            Ok(map) => {
+                let resolution = map.resolution();
+                println!(
+                    "{:<40} {:>3}x{:<3} squares  {:>3} px/grid  {:>4} walls  {:>3} doors  {:>3} lights  {}",
+                    map_stem(path),
+                    resolution.map_size.x,
+                    resolution.map_size.y,
+                    resolution.pixels_per_grid,
+                    map.line_of_sight.len() + map.objects_line_of_sight.len(),
+                    map.portals.len(),
+                    map.lights.len(),
+                    path.display()
+                );
+            }
+            Err(e) => {
+                unreadable += 1;
+                eprintln!("{}: {e}", path.display());
+            }
+        }
+    }
+
+    println!("{} maps found, {} unreadable", maps.len(), unreadable);
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use crate::commands::{collect_maps, map_stem};
+use anyhow::Context;
+use clap::Args;
+use shared::render::thumbnail::{DEFAULT_THUMBNAIL_SIZES, parse_thumbnail_sizes, render_thumbnail};
+use shared::types::dd2vtt::DD2VTTFile;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ThumbnailsArgs {
+    /// Files or directories to generate thumbnails for (defaults to `maps/`)
+    pub paths: Vec<PathBuf>,
+
+    /// Directory the thumbnails are written to
+    #[arg(short, long, default_value = "thumbnails")]
+    pub output: PathBuf,
+
+    /// Sizes as `name=WIDTHxHEIGHT[:webp|png]`, comma separated
+    #[arg(short, long, default_value = DEFAULT_THUMBNAIL_SIZES)]
+    pub sizes: String,
+}
+
+pub fn run(args: &ThumbnailsArgs) -> anyhow::Result<()> {
+    let sizes = parse_thumbnail_sizes(&args.sizes).map_err(anyhow::Error::msg)?;
+    let maps = collect_maps(&args.paths)?;
+    fs::create_dir_all(&args.output)
+        .with_context(|| format!("Failed to create {}", args.output.display()))?;
+
+    for path in &maps {
+        let map =
+            DD2VTTFile::load(path).with_context(|| format!("Failed to load {}", path.display()))?;
+        let image = map.decode_image()?;
+        let stem = map_stem(path);
+
+        for size in &sizes {
+            let output = args.output.join(format!("{stem}.{}", size.file_name()));
+            fs::write(&output, render_thumbnail(&image, size)?)
+                .with_context(|| format!("Failed to write {}", output.display()))?;
+            println!("{} -> {}", path.display(), output.display());
+        }
+    }
+
+    println!(
+        "{} thumbnails written to {}",
+        maps.len() * sizes.len(),
+        args.output.display()
+    );
+    Ok(())
+}
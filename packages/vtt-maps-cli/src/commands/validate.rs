@@ -0,0 +1,69 @@
+use crate::commands::collect_maps;
+use clap::Args;
+use shared::types::dd2vtt::DD2VTTFile;
+use shared::validate::{Severity, lint_map};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    /// Files or directories to validate (defaults to `maps/`)
+    pub paths: Vec<PathBuf>,
+
+    /// Fail on warnings as well as errors
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Only print maps with findings
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+
+pub fn run(args: &ValidateArgs) -> anyhow::Result<()> {
+    let maps = collect_maps(&args.paths)?;
+    let (mut failing, mut errors, mut warnings) = (0, 0, 0);
+
+    for path in &maps {
+        let report = match DD2VTTFile::load(path) {
+            Ok(map) => lint_map(&map),
+            Err(e) => {
+                failing += 1;
+                errors += 1;
+                println!("✗ {}\n    error: {e}", path.display());
+                continue;
+            }
+        };
+
+        errors += report.errors();
+        warnings += report.warnings();
+        let fails = report.errors() > 0 || (args.strict && report.warnings() > 0);
+        if fails {
+            failing += 1;
+        }
+
+        if report.is_clean() {
+            if !args.quiet {
+                println!("✓ {}", path.display());
+            }
+            continue;
+        }
+        println!("{} {}", if fails { "✗" } else { "!" }, path.display());
+        for issue in &report.issues {
+            let severity = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            println!("    {severity}: {}", issue.message);
+        }
+    }
+
+    println!(
+        "{} maps checked: {} errors, {} warnings",
+        maps.len(),
+        errors,
+        warnings
+    );
+    if failing > 0 {
+        anyhow::bail!("{failing} maps failed validation");
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+mod commands;
+
+use clap::{Parser, Subcommand};
+
+/// Offline tools for working with the DD2VTT map library
+#[derive(Parser)]
+#[command(name = "vtt-maps", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the maps in a directory with their grid size and geometry counts
+    Scan(commands::scan::ScanArgs),
+    /// Check maps for problems before opening a pull request
+    Validate(commands::validate::ValidateArgs),
+    /// Generate thumbnails in the sizes the site uses
+    Thumbnails(commands::thumbnails::ThumbnailsArgs),
+    /// Print `MapReference` manifests as JSON
+    Manifest(commands::manifest::ManifestArgs),
+    /// Export a map as an image or a printable PDF
+    Export(commands::export::ExportArgs),
+    /// Draw walls, doors and lights over a map image for review
+    Overlay(commands::overlay::OverlayArgs),
+}
+
+fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Scan(args) => commands::scan::run(&args),
+        Command::Validate(args) => commands::validate::run(&args),
+        Command::Thumbnails(args) => commands::thumbnails::run(&args),
+        Command::Manifest(args) => commands::manifest::run(&args),
+        Command::Export(args) => commands::export::run(&args),
+        Command::Overlay(args) => commands::overlay::run(&args),
+    }
+}
@@ -1,7 +1,8 @@
 use gloo_net::http::{Request, RequestBuilder};
 
+#[cfg(not(feature = "static-site"))]
 const BASE_LIMIT: u32 = 100;
-const API_BASE: &str = "/api";
+const API_BASE: &str = "/api/v1";
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Endpoint {
@@ -15,15 +16,25 @@ pub enum Endpoint {
     TiledMap {
         id: String,
     },
+    TilePyramid {
+        id: String,
+    },
+    PrintMap {
+        id: String,
+    },
     Markdown {
         path: String,
     },
     MapContent {
         id: String,
     },
+    DownloadMap {
+        id: String,
+    },
 }
 
 impl Endpoint {
+    #[cfg(not(feature = "static-site"))]
     pub fn url(&self) -> String {
         match self {
             Endpoint::AllMaps { limit, offset } => {
@@ -45,8 +56,30 @@ impl Endpoint {
             }
             Endpoint::Map { id } => format!("{API_BASE}/maps/{id}"),
             Endpoint::TiledMap { id } => format!("{API_BASE}/maps/tiled/{id}"),
+            Endpoint::TilePyramid { id } => format!("{API_BASE}/maps/tiles/{id}/pyramid.json"),
+            Endpoint::PrintMap { id } => format!("{API_BASE}/maps/print/{id}.pdf"),
             Endpoint::Markdown { path } => format!("{API_BASE}/docs/{path}"),
             Endpoint::MapContent { id } => format!("{API_BASE}/maps/content/{id}"),
+            Endpoint::DownloadMap { id } => format!("{API_BASE}/maps/download/{id}"),
+        }
+    }
+
+    /// Files written by the static site export; see `shared::static_site`
+    #[cfg(feature = "static-site")]
+    pub fn url(&self) -> String {
+        use shared::static_site;
+
+        match self {
+            Endpoint::AllMaps { offset, .. } => {
+                static_site::catalog_page_for_offset(offset.unwrap_or(0) as usize)
+            }
+            Endpoint::Map { id } => static_site::map_document(id),
+            Endpoint::TiledMap { id } => static_site::map_image(id),
+            Endpoint::TilePyramid { id } => format!("{}/pyramid.json", static_site::tiles(id)),
+            Endpoint::PrintMap { id } => format!("{API_BASE}/maps/print/{id}.pdf"),
+            Endpoint::Markdown { path } => static_site::doc_page(path),
+            Endpoint::MapContent { id } => static_site::map_content(id),
+            Endpoint::DownloadMap { id } => static_site::map_download(id),
         }
     }
 
@@ -55,7 +88,10 @@ impl Endpoint {
             Endpoint::AllMaps { .. }
             | Endpoint::Map { .. }
             | Endpoint::TiledMap { .. }
+            | Endpoint::TilePyramid { .. }
+            | Endpoint::PrintMap { .. }
             | Endpoint::MapContent { .. }
+            | Endpoint::DownloadMap { .. }
             | Endpoint::Markdown { .. } => Request::get(&self.url()),
         }
     }
@@ -1,2 +1,3 @@
 pub mod context;
+#[allow(dead_code)]
 pub mod gh;
@@ -2,6 +2,7 @@
 use crate::api::context::ApiEndpoint;
 use gloo_console::log;
 use serde::Deserialize;
+use shared::utils::casing::kebabcase;
 use yew::prelude::*;
 
 #[derive(Clone, PartialEq, Deserialize)]
@@ -53,15 +54,14 @@ pub fn map_downloader(props: &MapDownloaderProps) -> Html {
 
     if let Some(map) = &*details {
         // build URLs & dimension text
-        let dd2vtt_url = format!("/api/maps/download/{}", map.id);
-        let img_url = format!("/api/maps/tiled/{}", map.id);
-        let download_name = map
-            .name
-            .to_lowercase()
-            .split_whitespace()
-            .collect::<Vec<_>>()
-            .join("-")
-            + ".png";
+        let dd2vtt_url = ApiEndpoint::DownloadMap { id: map.id.clone() }.url();
+        let dd2vtt_name = kebabcase(&map.name) + ".dd2vtt";
+        // The original keeps its embedded format (usually WebP) and is named by
+        // the server; PNG is offered for tools that cannot open WebP
+        let img_url = ApiEndpoint::TiledMap { id: map.id.clone() }.url();
+        let png_url = format!("{img_url}?format=png");
+        let png_name = kebabcase(&map.name) + ".png";
+        let print_url = ApiEndpoint::PrintMap { id: map.id.clone() }.url();
         let dims = {
             html! {
                 <div class="flex flex-col gap-2">
@@ -80,15 +80,28 @@ pub fn map_downloader(props: &MapDownloaderProps) -> Html {
             <div id="action-buttons" class="map-downloader rounded">
                 <div class="flex flex-row gap-2">
                     <div>
-                        <a href={dd2vtt_url} download={"true"} class="btn btn-primary">
+                        <a href={dd2vtt_url} download={dd2vtt_name} class="btn btn-primary">
                             { "Download DD2VTT File" }
                         </a>
                     </div>
                     <div>
-                    <a href={img_url} download={download_name} class="btn btn-primary">
+                    <a href={img_url} download={""} class="btn btn-primary">
                         { "Download Image" }
                     </a>
                    </div>
+                    // Transcoding and printing need the backend
+                    if cfg!(not(feature = "static-site")) {
+                        <div>
+                        <a href={png_url} download={png_name} class="btn btn-secondary">
+                            { "Download PNG" }
+                        </a>
+                       </div>
+                        <div>
+                        <a href={print_url} target="_blank" rel="noopener" class="btn btn-secondary">
+                            { "Print (1\" squares)" }
+                        </a>
+                       </div>
+                    }
                </div>
                 <div class="space-y-1 pt-2">
                   <p class="text-sm m-0">
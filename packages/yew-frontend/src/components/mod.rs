@@ -1,3 +1,4 @@
 pub mod header;
 pub mod map_asset_card;
 pub mod map_downloader;
+pub mod tile_viewer;
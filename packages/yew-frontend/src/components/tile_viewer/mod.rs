@@ -0,0 +1,246 @@
+use crate::api::context::ApiEndpoint;
+use gloo_console::log;
+use serde::Deserialize;
+use shared::render::tiles::TilePyramid;
+use std::ops::Range;
+use web_sys::HtmlElement;
+use yew::prelude::*;
+
+const ZOOM_STEP: f64 = 1.2;
+/// Furthest zoom-in, in screen pixels per source pixel
+const MAX_SCALE: f64 = 2.0;
+
+#[derive(Clone, PartialEq, Deserialize)]
+struct TileManifest {
+    #[serde(flatten)]
+    pyramid: TilePyramid,
+    tile_url: String,
+}
+
+impl TileManifest {
+    fn url(&self, z: u32, x: u32, y: u32) -> String {
+        self.tile_url
+            .replace("{z}", &z.to_string())
+            .replace("{x}", &x.to_string())
+            .replace("{y}", &y.to_string())
+    }
+
+    /// Pick the lowest level that still has at least one pixel per screen pixel
+    fn level_for(&self, scale: f64) -> u32 {
+        let max = self.pyramid.max_zoom;
+        let steps_down = (-scale.log2()).floor().max(0.0);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let steps_down = steps_down.min(f64::from(max)) as u32;
+        max - steps_down
+    }
+}
+
+/// Tiles along one axis that overlap the viewport, plus a one-tile margin.
+/// `offset` and `extent` are in screen pixels, `factor` is screen pixels per
+/// level pixel.
+fn visible_range(offset: f64, extent: f64, factor: f64, tile_size: u32, count: u32) -> Range<u32> {
+    let tile = factor * f64::from(tile_size.max(1));
+    let first = (-offset / tile).floor() - 1.0;
+    let last = ((extent - offset) / tile).ceil() + 1.0;
+    let clamp = |v: f64| {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let v = v.clamp(0.0, f64::from(count)) as u32;
+        v
+    };
+    clamp(first)..clamp(last)
+}
+
+/// Current viewport transform: `scale` screen pixels per source pixel,
+/// offset in screen pixels
+#[derive(Clone, Copy, PartialEq)]
+struct View {
+    scale: f64,
+    x: f64,
+    y: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Drag {
+    start_x: f64,
+    start_y: f64,
+    view: View,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct TileViewerProps {
+    pub id: String,
+    pub alt: AttrValue,
+    /// Shown until the first tiles are available
+    #[prop_or_default]
+    pub placeholder: Option<AttrValue>,
+}
+
+#[function_component(TileViewer)]
+pub fn tile_viewer(props: &TileViewerProps) -> Html {
+    let manifest = use_state(|| None::<TileManifest>);
+    let view = use_state(|| None::<View>);
+    let drag = use_state(|| None::<Drag>);
+    let viewport = use_node_ref();
+
+    {
+        let manifest = manifest.clone();
+        let view = view.clone();
+        use_effect_with(props.id.clone(), move |id| {
+            let request = ApiEndpoint::TilePyramid { id: id.clone() };
+            manifest.set(None);
+            view.set(None);
+            wasm_bindgen_futures::spawn_local(async move {
+                match request.request().send().await {
+                    Ok(resp) if resp.ok() => match resp.json::<TileManifest>().await {
+                        Ok(m) => manifest.set(Some(m)),
+                        Err(e) => log!("Failed to parse tile manifest:", e.to_string()),
+                    },
+                    Ok(resp) => log!("Tile manifest fetch failed:", resp.status()),
+                    Err(e) => log!("Network error:", e.to_string()),
+                }
+            });
+            || ()
+        });
+    }
+
+    // Fit the map to the viewport width once the layout is known
+    {
+        let view = view.clone();
+        let viewport = viewport.clone();
+        use_effect_with((*manifest).clone(), move |manifest| {
+            if let (Some(m), Some(el)) = (manifest, viewport.cast::<HtmlElement>()) {
+                let width = el.get_bounding_client_rect().width();
+                let fit = (width / f64::from(m.pyramid.width.max(1))).min(1.0);
+                view.set(Some(View {
+                    scale: fit,
+                    x: 0.0,
+                    y: 0.0,
+                }));
+            }
+            || ()
+        });
+    }
+
+    let onwheel = {
+        let view = view.clone();
+        let viewport = viewport.clone();
+        Callback::from(move |e: WheelEvent| {
+            let (Some(current), Some(el)) = (*view, viewport.cast::<HtmlElement>()) else {
+                return;
+            };
+            e.prevent_default();
+            let rect = el.get_bounding_client_rect();
+            let cx = f64::from(e.client_x()) - rect.left();
+            let cy = f64::from(e.client_y()) - rect.top();
+            let factor = if e.delta_y() < 0.0 {
+                ZOOM_STEP
+            } else {
+                1.0 / ZOOM_STEP
+            };
+            let scale = (current.scale * factor).min(MAX_SCALE);
+            let ratio = scale / current.scale;
+            view.set(Some(View {
+                scale,
+                x: cx - (cx - current.x) * ratio,
+                y: cy - (cy - current.y) * ratio,
+            }));
+        })
+    };
+
+    let onmousedown = {
+        let view = view.clone();
+        let drag = drag.clone();
+        Callback::from(move |e: MouseEvent| {
+            if let Some(current) = *view {
+                drag.set(Some(Drag {
+                    start_x: f64::from(e.client_x()),
+                    start_y: f64::from(e.client_y()),
+                    view: current,
+                }));
+            }
+        })
+    };
+
+    let onmousemove = {
+        let view = view.clone();
+        let drag = drag.clone();
+        Callback::from(move |e: MouseEvent| {
+            if let Some(d) = *drag {
+                view.set(Some(View {
+                    x: d.view.x + f64::from(e.client_x()) - d.start_x,
+                    y: d.view.y + f64::from(e.client_y()) - d.start_y,
+                    ..d.view
+                }));
+            }
+        })
+    };
+
+    let onmouseup = {
+        let drag = drag.clone();
+        Callback::from(move |_: MouseEvent| drag.set(None))
+    };
+
+    let layer = match (&*manifest, *view) {
+        (Some(m), Some(v)) => {
+            let z = m.level_for(v.scale);
+            let (width, height) = m.pyramid.level_size(z).unwrap_or((0, 0));
+            let (cols, rows) = m.pyramid.tile_count(z).unwrap_or((0, 0));
+            let level_scale = f64::from(width) / f64::from(m.pyramid.width.max(1));
+            let style = format!(
+                "width:{width}px;height:{height}px;transform:translate({}px,{}px) scale({});",
+                v.x,
+                v.y,
+                v.scale / level_scale
+            );
+            let size = m.pyramid.tile_size;
+            let factor = v.scale / level_scale;
+            let (xs, ys) = match viewport.cast::<HtmlElement>() {
+                Some(el) => {
+                    let rect = el.get_bounding_client_rect();
+                    (
+                        visible_range(v.x, rect.width(), factor, size, cols),
+                        visible_range(v.y, rect.height(), factor, size, rows),
+                    )
+                }
+                None => (0..cols, 0..rows),
+            };
+
+            html! {
+                <div class="tile-layer" {style}>
+                    { for ys.flat_map(|y| xs.clone().map(move |x| (x, y))).map(|(x, y)| html! {
+                        <img
+                            key={format!("{z}/{x}/{y}")}
+                            src={m.url(z, x, y)}
+                            style={format!("left:{}px;top:{}px;", x * size, y * size)}
+                            alt=""
+                            draggable="false"
+                            loading="lazy"
+                        />
+                    }) }
+                </div>
+            }
+        }
+        _ => match &props.placeholder {
+            Some(src) => html! {
+                <img class="tile-placeholder" src={src.clone()} alt={props.alt.clone()} />
+            },
+            None => html! {},
+        },
+    };
+
+    html! {
+        <div
+            id="map-explorer"
+            class="map-explorer tile-viewer"
+            ref={viewport}
+            aria-label={props.alt.clone()}
+            {onwheel}
+            {onmousedown}
+            onmousemove={onmousemove}
+            onmouseup={onmouseup.clone()}
+            onmouseleave={onmouseup}
+        >
+            { layer }
+        </div>
+    }
+}
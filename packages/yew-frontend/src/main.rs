@@ -1,5 +1,6 @@
 mod api;
 mod components;
+#[allow(dead_code)]
 mod entities;
 mod utils;
 
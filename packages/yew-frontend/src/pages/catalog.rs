@@ -17,19 +17,15 @@ pub fn catalog() -> Html {
 
         use_effect_once(move || {
             spawn_local(async move {
-                if let Ok(response) = {
-                    ApiEndpoint::AllMaps {
-                        limit: None,
-                        offset: None,
-                    }
+                let request = ApiEndpoint::AllMaps {
+                    limit: None,
+                    offset: None,
                 }
-                .request()
-                .send()
-                .await
+                .request();
+                if let Ok(response) = request.send().await
+                    && let Ok(list) = response.json::<Vec<MapDocument>>().await
                 {
-                    if let Ok(list) = response.json::<Vec<MapDocument>>().await {
-                        maps.set(list);
-                    }
+                    maps.set(list);
                 }
 
                 is_loading.set(false);
@@ -1,21 +1,17 @@
 use crate::api::context::ApiEndpoint;
 use crate::components::map_downloader::MapDownloader;
+use crate::components::tile_viewer::TileViewer;
 use shared::types::map_document::MapDocument;
 use shared::utils::casing::titlecase;
 
 use gloo_console::info;
-use wasm_bindgen::JsCast;
-use wasm_bindgen::prelude::Closure;
 use wasm_bindgen_futures::spawn_local;
-use web_sys::HtmlImageElement;
 use yew::prelude::*;
 
 #[derive(Debug, Clone, PartialEq)]
 enum LoadingState {
     Loading,
-    ThumbnailLoaded,
-    FullMapLoading,
-    FullMapLoaded,
+    Loaded,
     Error(String),
 }
 
@@ -30,16 +26,12 @@ pub fn map_detail(props: &MapDetailProps) -> Html {
     let name = use_state(String::new);
     let data = use_state(|| None as Option<MapDocument>);
     let loading = use_state(|| LoadingState::Loading);
-    let thumb_ref = use_node_ref();
     let content = use_state(String::new);
 
     // --- API endpoints ---
     let metadata_ep = ApiEndpoint::Map {
         id: props.id.clone(),
     };
-    let tiled_ep = ApiEndpoint::TiledMap {
-        id: props.id.clone(),
-    };
 
     // 1) Fetch metadata when `props.id` changes
     {
@@ -62,7 +54,7 @@ pub fn map_detail(props: &MapDetailProps) -> Html {
 
                             name.set(pretty);
                             data.set(Some(map));
-                            loading.set(LoadingState::ThumbnailLoaded);
+                            loading.set(LoadingState::Loaded);
                         } else {
                             loading.set(LoadingState::Error("Failed to parse metadata".into()));
                         }
@@ -77,36 +69,7 @@ pub fn map_detail(props: &MapDetailProps) -> Html {
         });
     }
 
-    // 2) Preload full tiled map when thumbnail has loaded
-    {
-        let tiled_ep = tiled_ep.clone();
-        let loading = loading.clone();
-        let thumb_ref = thumb_ref.clone();
-
-        use_effect_with(loading.clone(), move |state| {
-            if **state == LoadingState::ThumbnailLoaded {
-                spawn_local(async move {
-                    loading.set(LoadingState::FullMapLoading);
-
-                    if let Some(img) = thumb_ref.cast::<HtmlImageElement>() {
-                        let onload = Closure::wrap(Box::new(move || {
-                            loading.set(LoadingState::FullMapLoaded);
-                        }) as Box<dyn FnMut()>);
-
-                        img.set_onload(Some(onload.as_ref().unchecked_ref()));
-                        img.set_src(&tiled_ep.url());
-                        onload.forget();
-                    } else {
-                        loading.set(LoadingState::Error("Preload element missing".into()));
-                    }
-                });
-            }
-
-            || ()
-        });
-    }
-
-    // 3) Fetch map content markdown
+    // 2) Fetch map content markdown
     {
         let content = content.clone();
         use_effect_with(props.id.clone(), move |id: &String| {
@@ -161,25 +124,14 @@ pub fn map_detail(props: &MapDetailProps) -> Html {
 
                         },
 
-                        LoadingState::ThumbnailLoaded | LoadingState::FullMapLoading => {
+                        LoadingState::Loaded => {
                             if let Some(map) = &*data {
                                 html! {
-                                    <div>
-                                        <p id="loading-text" class="loading-text loading-message">
-                                            {"Loading full map"}<span class="loading-dots"></span>
-                                        </p>
-                                        <img
-                                            id="map-thumbnail"
-                                            src={ map.thumbnail.clone() }
-                                            class="map-thumbnail responsive"
-                                            alt={ format!("{} thumbnail", map.name) }
-                                        />
-                                        <img
-                                            id="preload-image"
-                                            ref={ thumb_ref.clone() }
-                                            class="preload-image"
-                                            style="display:none;"
-                                            alt="preload full map"
+                                    <div id="map-viewer" class="map-viewer">
+                                        <TileViewer
+                                            id={ map.id.clone() }
+                                            alt={ format!("{} full map", &*name) }
+                                            placeholder={ map.thumbnail.clone() }
                                         />
                                     </div>
                                 }
@@ -192,28 +144,6 @@ pub fn map_detail(props: &MapDetailProps) -> Html {
                             }
                         },
 
-                        LoadingState::FullMapLoaded => {
-
-
-                            let loaded_map =html! {
-                                <div id="map-scroll-container" class="map-scroll-container">
-                                    <img
-                                        id="full-map-image"
-                                        src={ tiled_ep.url() }
-                                        class="responsive pointer-events-none full-map"
-                                        alt={ format!("{} full map", &*name) }
-                                    />
-                                </div>
-                            };
-
-                            html! {
-                                <div id="map-viewer" class="map-viewer">
-                                    { loaded_map }
-                                </div>
-                            }
-
-                        },
-
                         LoadingState::Error(msg) => html! {
                             <div id="error-message" class="error-message error">
                                 { msg }